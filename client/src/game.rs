@@ -12,7 +12,7 @@ use crate::settings::TowerSettings;
 use crate::state::TowerState;
 use crate::territory::Territories;
 use crate::tutorial::Tutorial;
-use crate::ui::{SelectedTower, TowerUiEvent, TowerUiProps};
+use crate::ui::{SelectedTower, SupplyLineEdit, TowerUiEvent, TowerUiProps};
 use client_util::context::Context;
 use client_util::game_client::GameClient;
 use client_util::keyboard::Key;
@@ -20,16 +20,22 @@ use client_util::mouse::{MouseButton, MouseEvent};
 use client_util::pan_zoom::PanZoom;
 use client_util::visibility::VisibilityEvent;
 use common::chunk::ChunkRectangle;
-use common::force::{Force, Path};
-use common::info::{GainedTowerReason, Info, InfoEvent};
-use common::protocol::{Command, Update};
+use common::force::{Deployment, Force, Path};
+use common::info::{GainedTowerReason, Info, InfoEvent, LostTowerReason};
+use common::pathfinder::PathCache;
+use common::protocol::{Command, Spectate, Update};
+use common::research::Research;
+use common::ruler::RulerAbility;
+use common::supply_line::SupplyLine;
 use common::tower::{Tower, TowerId, TowerRectangle, TowerType};
 use common::unit::Unit;
 use common::units::Units;
 use common::world::{World, WorldChunks};
 use common_util::x_vec2::U16Vec2;
 use core_protocol::id::GameId;
+use core_protocol::name::RealmName;
 use glam::{IVec2, Vec2, Vec3, Vec4};
+use rand::{thread_rng, Rng};
 use renderer::{DefaultRender, Layer, RenderChain};
 use renderer2d::{Camera2d, TextLayer};
 use std::f32::consts::PI;
@@ -43,6 +49,10 @@ pub struct TowerGame {
     /// (start, (current, current time)).
     drag: Option<Drag>,
     selected_tower_id: Option<TowerId>,
+    /// Which units to deploy when dragging from a tower.
+    deployment: Deployment,
+    /// Paths found while dragging.
+    paths: PathCache,
     pan_zoom: PanZoom,
     territories: Territories,
     panning: bool,
@@ -84,6 +94,40 @@ impl Drag {
     }
 }
 
+/// What dragging from a tower does, the same while drawing as when letting go.
+struct DragPlan {
+    /// Clamped to the tower's units.
+    deployment: Deployment,
+    /// Units that would be deployed.
+    strength: Units,
+    /// Furthest the units can go per road, or [`None`] if unlimited.
+    max_edge_distance: Option<u32>,
+    /// Whether to make a supply line instead of deploying units.
+    supply_line: bool,
+}
+
+impl DragPlan {
+    fn new(source_tower: &Tower, deployment: &Deployment, selected: bool) -> Self {
+        let deployment = deployment.clamp(&source_tower.force_units());
+        let strength = source_tower
+            .deployment_units(&deployment)
+            .unwrap_or_default();
+        let tower_edge_distance = source_tower.tower_type.ranged_distance();
+        let strength_edge_distance = (!strength.is_empty()).then(|| strength.max_edge_distance());
+        let max_edge_distance =
+            strength_edge_distance.map_or(tower_edge_distance, |e| e.min(tower_edge_distance));
+        let shorter_max_edge_distance = max_edge_distance != tower_edge_distance;
+        Self {
+            deployment,
+            strength,
+            max_edge_distance,
+            supply_line: selected
+                && source_tower.generates_mobile_units()
+                && !shorter_max_edge_distance,
+        }
+    }
+}
+
 #[derive(Layer)]
 #[render(&Camera2d)]
 pub struct TowerLayer {
@@ -126,6 +170,8 @@ impl GameClient for TowerGame {
             animations: Default::default(),
             drag: Default::default(),
             selected_tower_id: Default::default(),
+            deployment: Default::default(),
+            paths: Default::default(),
             pan_zoom: Default::default(),
             territories: Default::default(),
             panning: Default::default(),
@@ -203,20 +249,21 @@ impl GameClient for TowerGame {
                                     self.selected_tower_id = None;
                                 }
 
-                                let strength = source_tower.force_units();
-                                let tower_edge_distance = source_tower.tower_type.ranged_distance();
-                                let strength_edge_distance =
-                                    (!strength.is_empty()).then(|| strength.max_edge_distance());
-                                let max_edge_distance = strength_edge_distance
-                                    .map_or(tower_edge_distance, |e| e.min(tower_edge_distance));
-                                let shorter_max_edge_distance =
-                                    max_edge_distance != tower_edge_distance;
-                                let supply_tower_id = self.selected_tower_id.filter(|_| {
-                                    source_tower.generates_mobile_units()
-                                        && !shorter_max_edge_distance
-                                });
+                                let DragPlan {
+                                    deployment,
+                                    strength,
+                                    max_edge_distance,
+                                    supply_line,
+                                } = DragPlan::new(
+                                    source_tower,
+                                    &self.deployment,
+                                    self.selected_tower_id.is_some(),
+                                );
+                                let supply_tower_id =
+                                    self.selected_tower_id.filter(|_| supply_line);
 
-                                let path = context.state.game.world.find_best_path(
+                                let path = self.paths.find_best_path(
+                                    &context.state.game.world,
                                     start,
                                     current,
                                     max_edge_distance,
@@ -227,24 +274,56 @@ impl GameClient for TowerGame {
                                 if let Some(path) = path {
                                     let perilous =
                                         path.iter().any(|&tower_id| is_perilous(context, tower_id));
-
-                                    if !perilous
-                                        || !strength.contains(Unit::Ruler)
-                                        || context.client.time_seconds
-                                            >= current_start_time + Self::RULER_DRAG_DELAY
+                                    // Drawn as not viable, so don't cut the route short.
+                                    let too_long = supply_tower_id.is_none()
+                                        && Path::new_legs(path.clone()).len()
+                                            > World::MAX_PATH_LEGS;
+
+                                    if !too_long
+                                        && (!perilous
+                                            || !strength.contains(Unit::Ruler)
+                                            || context.client.time_seconds
+                                                >= current_start_time + Self::RULER_DRAG_DELAY)
                                     {
                                         context.send_to_game(
                                             if let Some(tower_id) = supply_tower_id {
                                                 let path = Path::new(path);
-                                                Command::SetSupplyLine {
-                                                    tower_id,
-                                                    // TODO accept any invalid path.
-                                                    path: (source_tower.supply_line.as_ref()
-                                                        != Some(&path))
-                                                    .then_some(path),
+                                                let existing = &source_tower.supply_lines;
+                                                if context.keyboard.is_down(Key::Shift) {
+                                                    // Add to (or remove from) the network.
+                                                    let mut supply_lines = existing.clone();
+                                                    if let Some(i) = supply_lines
+                                                        .iter()
+                                                        .position(|l| l.path == path)
+                                                    {
+                                                        supply_lines.remove(i);
+                                                    } else if supply_lines.len()
+                                                        < Tower::MAX_SUPPLY_LINES
+                                                    {
+                                                        supply_lines.push(SupplyLine::new(path));
+                                                    }
+                                                    Command::SetSupplyLines {
+                                                        tower_id,
+                                                        supply_lines,
+                                                    }
+                                                } else {
+                                                    let same = existing.len() == 1
+                                                        && existing[0].path == path;
+                                                    Command::SetSupplyLine {
+                                                        tower_id,
+                                                        // TODO accept any invalid path.
+                                                        path: (!same).then_some(path),
+                                                    }
                                                 }
                                             } else {
-                                                Command::deploy_force_from_path(path)
+                                                // Too long for one path, so queue waypoints.
+                                                let mut legs = Path::new_legs(path);
+                                                Command::DeployForce {
+                                                    tower_id: start,
+                                                    path: legs.remove(0),
+                                                    legs,
+                                                    deployment,
+                                                }
                                             },
                                         );
                                     }
@@ -375,33 +454,69 @@ impl GameClient for TowerGame {
                 let is_hover = Some(tower_id) == hovered_tower_id && !is_selected;
                 let is_dragging = Some(tower_id) == self.drag.map(|Drag { start, .. }| start);
 
-                if show_supply_lines || !is_hover || !is_dragging {
-                    if let Some(path) = &tower.supply_line {
-                        if tower.player_id.is_some() && tower.player_id == me {
-                            let alpha = if is_selected {
-                                if is_dragging {
-                                    0.5 // Darken selected while changing it.
-                                } else {
-                                    1.0
-                                }
-                            } else if is_hover && show_supply_lines {
-                                0.5 // Make hovered stand out against the other supply lines.
+                if (show_supply_lines || !is_hover || !is_dragging)
+                    && tower.player_id.is_some()
+                    && tower.player_id == me
+                {
+                    for supply_line in &tower.supply_lines {
+                        let mut alpha = if is_selected {
+                            if is_dragging {
+                                0.5 // Darken selected while changing it.
                             } else {
-                                0.3
-                            };
+                                1.0
+                            }
+                        } else if is_hover && show_supply_lines {
+                            0.5 // Make hovered stand out against the other supply lines.
+                        } else {
+                            0.3
+                        };
+                        if supply_line.paused {
+                            alpha *= 0.4; // Destination is full enough.
+                        }
+
+                        layer.roads.draw_path(
+                            supply_line.path.iter(),
+                            Some(u32::MAX), // Existing supply lines must be valid.
+                            usize::MAX,
+                            true,
+                            |id| get_visibility(id) * alpha,
+                        );
 
-                            layer.roads.draw_path(
-                                path.iter(),
-                                Some(u32::MAX), // Existing supply lines must be valid.
-                                usize::MAX,
-                                true,
-                                |id| get_visibility(id) * alpha,
+                        // Show which unit the supply line is limited to.
+                        if let Some(unit) = supply_line.unit.filter(|_| zoom_per_pixel < 0.4) {
+                            let destination = supply_line.path.destination().as_vec2();
+                            let (stroke, fill) = Color::Blue.colors(true, false, false);
+                            layer.paths.draw_path_a(
+                                PathId::Unit(unit),
+                                destination.lerp(tower_position, 0.25),
+                                0.0,
+                                0.5,
+                                stroke.map(|s| s.extend(alpha)),
+                                fill.map(|f| f.extend(alpha)),
+                                false,
                             );
                         }
                     }
                 }
             }
 
+            // Show where waypoint orders are going.
+            for force in &tower.inbound_forces {
+                if force.legs().is_empty() || force.player_id.is_none() || force.player_id != me {
+                    continue;
+                }
+                layer.roads.draw_path(
+                    force
+                        .path()
+                        .iter()
+                        .chain(force.legs().iter().flat_map(|leg| leg.iter().skip(1))),
+                    Some(u32::MAX), // Legs were valid when ordered.
+                    usize::MAX,
+                    true,
+                    |id| get_visibility(id) * 0.3,
+                );
+            }
+
             fn draw_shield(
                 layer: &mut PathLayer,
                 position: Vec2,
@@ -510,8 +625,14 @@ impl GameClient for TowerGame {
             }
 
             let mut draw_force = |force: &Force| {
-                let force_position =
-                    force.interpolated_position(context.state.game.time_since_last_tick);
+                let speed_bonus = force
+                    .player_id
+                    .filter(|player_id| context.state.game.world.player.contains_key(player_id))
+                    .map_or(0, |player_id| {
+                        Research::speed_bonus(&context.state.game.world.player(player_id).research)
+                    });
+                let force_position = force
+                    .interpolated_position(context.state.game.time_since_last_tick, speed_bonus);
 
                 let color = Color::new(context, force.player_id);
                 let (stroke_color, fill_color) = color.colors(true, hovered, selected);
@@ -654,6 +775,8 @@ impl GameClient for TowerGame {
         Self::draw_drag_path(
             self.drag,
             self.selected_tower_id,
+            &self.deployment,
+            &mut self.paths,
             &get_visibility,
             context,
             layer,
@@ -667,13 +790,25 @@ impl GameClient for TowerGame {
             TowerUiEvent::Alliance {
                 with,
                 break_alliance,
+                minutes,
             } => {
                 context.send_to_game(Command::Alliance {
                     with,
                     break_alliance,
+                    minutes,
                 });
                 self.close_tower_menu();
             }
+            TowerUiEvent::TransferTower { tower_id, to } => {
+                context.send_to_game(Command::TransferTower { tower_id, to });
+                self.close_tower_menu();
+            }
+            TowerUiEvent::CreateRealm { bot_difficulty } => {
+                const BOTS: u8 = 20;
+                // Realm names may be used as subdomains, so they can't be numbers.
+                let realm_name = RealmName::new(&format!("r{:08x}", thread_rng().gen::<u32>()));
+                context.send_create_realm(realm_name, BOTS, bot_difficulty);
+            }
             TowerUiEvent::DismissCaptureTutorial => {
                 self.tutorial.dismiss_capture();
             }
@@ -684,6 +819,58 @@ impl GameClient for TowerGame {
                 context.send_set_alias(alias);
                 context.send_to_game(Command::Spawn);
             }
+            TowerUiEvent::Spectate(spectate) => {
+                context.send_to_game(Command::Spectate(spectate));
+            }
+            TowerUiEvent::DeployPercent(percent) => {
+                self.deployment = if percent >= 100 {
+                    Deployment::All
+                } else {
+                    Deployment::Percent(percent.max(1))
+                };
+            }
+            TowerUiEvent::DeployUnits {
+                tower_id,
+                unit,
+                count,
+            } => {
+                if let Some(tower) = context.state.game.world.chunk.get(tower_id) {
+                    let available = tower.force_units();
+                    let mut units = tower
+                        .deployment_units(&self.deployment.clamp(&available))
+                        .unwrap_or_default();
+                    units.subtract(unit, usize::MAX);
+                    units.add(unit, count.min(available.available(unit)));
+                    self.deployment = Deployment::Units(units);
+                }
+            }
+            TowerUiEvent::EditSupplyLine {
+                tower_id,
+                index,
+                edit,
+            } => {
+                if let Some(tower) = context.state.game.world.chunk.get(tower_id) {
+                    let mut supply_lines = tower.supply_lines.clone();
+                    if let Some(supply_line) = supply_lines.get_mut(index) {
+                        match edit {
+                            SupplyLineEdit::Weight(weight) => {
+                                supply_line.weight = weight.clamp(1, SupplyLine::MAX_WEIGHT)
+                            }
+                            SupplyLineEdit::Unit(unit) => supply_line.unit = unit,
+                            SupplyLineEdit::BelowPercent(percent) => {
+                                supply_line.below_percent = percent
+                            }
+                            SupplyLineEdit::Remove => {
+                                supply_lines.remove(index);
+                            }
+                        }
+                        context.send_to_game(Command::SetSupplyLines {
+                            tower_id,
+                            supply_lines,
+                        });
+                    }
+                }
+            }
             TowerUiEvent::PanTo(tower_id) => {
                 self.pan_zoom.pan_to(tower_id.as_vec2());
             }
@@ -713,6 +900,13 @@ impl GameClient for TowerGame {
             TowerUiEvent::LockDialog(show) => {
                 self.lock_dialog = show;
             }
+            TowerUiEvent::Research(research) => {
+                context.send_to_game(Command::Research(research));
+            }
+            TowerUiEvent::UseRulerAbility { ability, target } => {
+                context.send_to_game(Command::UseRulerAbility { ability, target });
+                self.close_tower_menu();
+            }
         }
     }
 
@@ -731,8 +925,13 @@ impl GameClient for TowerGame {
             self.move_world_space(world_space, context);
         }
 
+        self.paths.set_mode(context.settings.path_mode);
         let ticked = std::mem::take(&mut context.state.game.ticked);
         if ticked {
+            self.paths.tick();
+            if let Some(tower_id) = context.state.game.focus {
+                self.pan_zoom.pan_to(tower_id.as_vec2());
+            }
             self.tutorial.update(context);
             if context.client.rewarded_ads && self.key_dispenser.update(context) {
                 context.settings.set_unlocks(
@@ -746,7 +945,7 @@ impl GameClient for TowerGame {
             if let Some(tower_id) = self.selected_tower_id {
                 // Clear supply line of selected tower.
                 if let Some(tower) = context.state.game.world.chunk.get(tower_id) {
-                    if tower.supply_line.is_some() {
+                    if !tower.supply_lines.is_empty() {
                         context.send_to_game(Command::SetSupplyLine {
                             tower_id,
                             path: None,
@@ -762,7 +961,7 @@ impl GameClient for TowerGame {
                     .iter(&context.state.game.world.chunk)
                     .filter(|&(id, t)| {
                         self.margin_viewport.contains(id)
-                            && t.supply_line.is_some()
+                            && !t.supply_lines.is_empty()
                             && t.player_id.is_some()
                             && t.player_id == me
                     })
@@ -783,7 +982,7 @@ impl GameClient for TowerGame {
         if context.cheats() && context.keyboard.is_down(Key::B) {
             self.pan_zoom.set_bounds(
                 Vec2::splat(-100.0),
-                Vec2::splat(WorldChunks::SIZE as f32 * TowerId::CONVERSION as f32 + 100.0),
+                Vec2::splat(WorldChunks::size() as f32 * TowerId::CONVERSION as f32 + 100.0),
                 true,
             );
         } else {
@@ -798,14 +997,25 @@ impl GameClient for TowerGame {
             );
         }
 
+        // The server only sends so much of the world to spectators.
+        let spectate = context.state.game.spectate;
+        self.pan_zoom.set_zoom_limit(
+            (spectate == Some(Spectate::Viewport))
+                .then_some(Spectate::MAX_CAMERA_TOWERS as f32 * TowerId::CONVERSION as f32 * 0.5),
+        );
+
         context.audio.set_muted_by_game(!context.state.game.alive);
 
-        if context.state.game.alive {
-            if !context.audio.is_playing(Audio::Music) {
+        if context.state.game.alive || spectate.is_some() {
+            if !context.state.game.alive {
+                context.audio.stop_playing(Audio::Music);
+                self.selected_tower_id = None;
+                self.drag = None;
+            } else if !context.audio.is_playing(Audio::Music) {
                 context.audio.play(Audio::Music);
             }
 
-            if !self.was_alive {
+            if context.state.game.alive && !self.was_alive {
                 self.pan_zoom.reset_center();
                 self.pan_zoom.reset_zoom()
             }
@@ -910,11 +1120,16 @@ impl GameClient for TowerGame {
                 Info::GainedTower {
                     player_id, reason, ..
                 } if Some(player_id) == me
-                    && matches!(reason, GainedTowerReason::CapturedFrom(_)) =>
+                    && matches!(
+                        reason,
+                        GainedTowerReason::CapturedFrom(_) | GainedTowerReason::GivenBy(_)
+                    ) =>
                 {
                     context.audio.play_with_volume(Audio::Success, volume);
                 }
-                Info::LostTower { player_id, .. } if Some(player_id) == me => {
+                Info::LostTower {
+                    player_id, reason, ..
+                } if Some(player_id) == me && !matches!(reason, LostTowerReason::GivenTo(_)) => {
                     context.audio.play_with_volume(Audio::Loss, volume);
                 }
                 Info::LostForce(player_id) if Some(player_id) == me => {
@@ -974,12 +1189,76 @@ impl GameClient for TowerGame {
                                 context.state.game.world.player(us).allies.contains(&them)
                             })
                             .unwrap_or(false),
+                        allies: context
+                            .state
+                            .core
+                            .player_id
+                            .filter(|&us| tower.player_id == Some(us))
+                            .map(|us| {
+                                let world = &context.state.game.world;
+                                let mut allies: Vec<_> = world
+                                    .player(us)
+                                    .allies
+                                    .iter()
+                                    .copied()
+                                    .filter(|&ally| {
+                                        world.player.contains_key(&ally)
+                                            && world.have_alliance(us, ally)
+                                    })
+                                    .chain(world.teammates(us))
+                                    .filter_map(|ally| {
+                                        Some((ally, context.state.core.player_or_bot(ally)?.alias))
+                                    })
+                                    .collect();
+                                allies.sort_unstable_by_key(|&(ally, _)| ally);
+                                allies.dedup_by_key(|&mut (ally, _)| ally);
+                                allies
+                            })
+                            .unwrap_or_default(),
+                        ability_cooldowns: context
+                            .state
+                            .core
+                            .player_id
+                            .filter(|&us| context.state.game.world.player.contains_key(&us))
+                            .map(|us| context.state.game.world.player(us).ability_cooldowns)
+                            .unwrap_or_default(),
+                        can_teleport: tower.player_id.is_some()
+                            && tower.player_id == context.state.core.player_id
+                            && context
+                                .state
+                                .game
+                                .alerts
+                                .ruler_position
+                                .is_some_and(|ruler| RulerAbility::can_teleport(ruler, tower_id)),
+                        research: tower
+                            .player_id
+                            .filter(|id| context.state.game.world.player.contains_key(id))
+                            .map(|id| context.state.game.world.player(id).research)
+                            .unwrap_or_default(),
+                        research_points: tower
+                            .player_id
+                            .filter(|id| context.state.game.world.player.contains_key(id))
+                            .map_or(0, |id| context.state.game.world.player(id).research_points),
                         tower,
                         tower_id,
                     })
             }),
             tower_counts: context.state.game.tower_counts,
             alerts: context.state.game.alerts,
+            alliance_requests: context
+                .state
+                .game
+                .alliance_requests
+                .0
+                .iter()
+                .filter_map(|request| {
+                    let player = context.state.core.player_or_bot(request.player_id)?;
+                    Some((*request, player.alias))
+                })
+                .collect(),
+            deployment: self.deployment.clone(),
+            round: context.state.game.round,
+            spectate,
             tutorial_alert: self.tutorial.alert(),
             unlocks: context.settings.unlocks.clone(),
         });
@@ -1014,6 +1293,8 @@ impl TowerGame {
     fn draw_drag_path(
         drag: Option<Drag>,
         selected_tower_id: Option<TowerId>,
+        deployment: &Deployment,
+        paths: &mut PathCache,
         get_visibility: &impl Fn(TowerId) -> f32,
         context: &Context<TowerGame>,
         layer: &mut TowerLayer,
@@ -1026,43 +1307,42 @@ impl TowerGame {
                 return;
             }
 
-            // TODO don't duplicate this code with find best incomplete path.
-            let strength = source_tower.force_units();
-            let tower_edge_distance = source_tower.tower_type.ranged_distance();
-            let strength_edge_distance =
-                (!strength.is_empty()).then(|| strength.max_edge_distance());
-            let max_edge_distance =
-                strength_edge_distance.map_or(tower_edge_distance, |e| e.min(tower_edge_distance));
-            let shorter_max_edge_distance = max_edge_distance != tower_edge_distance;
-
-            let do_supply_line = selected_tower_id.is_some()
-                && source_tower.generates_mobile_units()
-                && !shorter_max_edge_distance;
+            let DragPlan {
+                strength,
+                max_edge_distance,
+                supply_line: do_supply_line,
+                ..
+            } = DragPlan::new(source_tower, deployment, selected_tower_id.is_some());
 
             // Can drag supply lines even without units.
             if strength.is_empty() && !do_supply_line {
                 return;
             }
 
+            // Deployments too long for one path become waypoint orders.
+            let max_edges = if do_supply_line {
+                World::MAX_PATH_ROADS
+            } else {
+                World::MAX_PATH_LEGS * (World::MAX_PATH_ROADS - 1) + 1
+            };
+
             let mut perilous = false;
             let viable = layer.roads.draw_path(
-                context
-                    .state
-                    .game
-                    .world
+                paths
                     .find_best_incomplete_path(
+                        &context.state.game.world,
                         start,
                         current,
                         max_edge_distance,
                         context.player_id().unwrap(),
-                        &|tower_id| is_visible(context, tower_id),
+                        |tower_id| is_visible(context, tower_id),
                     )
                     .into_iter()
                     .filter(|&tower_id| tower_id != current)
                     .chain(std::iter::once(current))
                     .inspect(|&tower_id| perilous |= is_perilous(context, tower_id)),
                 max_edge_distance,
-                World::MAX_PATH_ROADS,
+                max_edges,
                 do_supply_line,
                 get_visibility,
             );
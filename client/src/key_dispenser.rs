@@ -101,7 +101,13 @@ impl KeyDispenser {
         tower
             .player_id
             .map(|tower_player_id| {
-                tower_player_id != player_id && !player.allies.contains(&tower_player_id)
+                tower_player_id != player_id
+                    && !player.allies.contains(&tower_player_id)
+                    && !context
+                        .state
+                        .game
+                        .world
+                        .are_teammates(player_id, tower_player_id)
             })
             .unwrap_or(true)
     }
@@ -3,6 +3,7 @@
 
 use client_util::browser_storage::BrowserStorages;
 use client_util::setting::{SettingCategory, Settings};
+use common::pathfinder::PathMode;
 use common::tower::TowerType;
 use std::collections::HashSet;
 use std::fmt::{self, Display, Formatter, Write};
@@ -11,6 +12,10 @@ use std::str::FromStr;
 #[derive(Clone, Default, PartialEq, Settings)]
 pub struct TowerSettings {
     pub(crate) unlocks: Unlocks,
+    /// What paths that are dragged prioritize.
+    // TODO translate.
+    #[setting(dropdown = "Paths")]
+    pub(crate) path_mode: PathMode,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -3,6 +3,8 @@
 
 use crate::visible::Visible;
 use client_util::apply::Apply;
+use common::balance::Balance;
+use common::generation::Generation;
 use common::info::InfoEvent;
 use common::protocol::{Diff, NonActor, Update};
 use common::ticks::Ticks;
@@ -32,6 +34,14 @@ impl Apply<Update> for TowerState {
     fn apply(&mut self, update: Update) {
         self.non_actor.apply(&update.non_actor_diff);
 
+        // Must be set before simulating.
+        if let Some(balance) = update.balance {
+            Balance::set(*balance);
+        }
+        if let Some(generation) = update.generation {
+            Generation::set(*generation);
+        }
+
         let mut on_info_event = |info_event| {
             if self.info_events.len() < 128 {
                 self.info_events.push(info_event);
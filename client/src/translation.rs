@@ -126,16 +126,24 @@ pub trait TowerTranslation: Copy + Sized {
                 // TODO don't use to_lowercase as it adds 32.6 kb to the binary.
                 self.unit_label(unit),
             ),
+            DeathReason::RulerKilledByZombies { unit } => {
+                self.ruler_killed(None, self.unit_label(unit))
+            }
+            DeathReason::RulerStranded => "Your ruler was stranded!".to_owned(), // TODO
+            DeathReason::RoundEnded => self.round_ended_reason().to_owned(),
+            DeathReason::KilledByModerator => self.killed_by_moderator_reason().to_owned(),
         }
     }
 
     fn ruler_killed(self, alias: Option<PlayerAlias>, lower_unit_label: &str) -> String;
+    s!(killed_by_moderator_reason);
 
     // Tower menu actions.
     s!(demolish_hint);
     s!(request_alliance_hint);
     s!(cancel_alliance_hint);
     s!(break_alliance_hint);
+    fn request_timed_alliance_hint(self, minutes: u8) -> String;
 
     // Alerts
     s!(alert_capture_instruction);
@@ -152,6 +160,32 @@ pub trait TowerTranslation: Copy + Sized {
     s!(alert_full_hint);
     s!(alert_overflowing_warning);
     s!(alert_overflowing_hint);
+    fn alert_alliance_requested_instruction(
+        self,
+        alias: PlayerAlias,
+        minutes: Option<u8>,
+    ) -> String;
+    s!(alert_alliance_requested_hint);
+    s!(alert_alliance_made_instruction);
+    s!(alert_alliance_broken_instruction);
+
+    // Spectating
+    s!(spectate_hint);
+    s!(spectate_anywhere_label);
+    s!(stop_spectating_hint);
+
+    // Private realms
+    s!(create_realm_hint);
+    s!(easy_bots_hint);
+    s!(normal_bots_hint);
+    s!(hard_bots_hint);
+    s!(creating_realm_label);
+
+    // Rounds
+    s!(round_ended_reason);
+    fn round_ends_in_label(self, timer: &str) -> String;
+    s!(round_towers_label);
+    s!(round_center_label);
 }
 
 impl TowerTranslation for LanguageId {
@@ -870,6 +904,23 @@ impl TowerTranslation for LanguageId {
         }
     }
 
+    fn request_timed_alliance_hint(self, minutes: u8) -> String {
+        match self {
+            English => format!("Request alliance for {minutes} minutes"),
+            Spanish => format!("Solicitar alianza por {minutes} minutos"),
+            French => format!("Demander une alliance pour {minutes} minutes"),
+            German => format!("Bündnis für {minutes} Minuten anfragen"),
+            Italian => format!("Richiedi un'alleanza per {minutes} minuti"),
+            Japanese => format!("{minutes}分間の同盟を申し込む"),
+            Russian => format!("Предложить союз на {minutes} минут"),
+            Arabic => format!("اطلب تحالفًا لمدة {minutes} دقيقة"),
+            Hindi => format!("{minutes} मिनट के लिए गठबंधन का अनुरोध करें"),
+            SimplifiedChinese => format!("请求结盟 {minutes} 分钟"),
+            Vietnamese => format!("Đề nghị liên minh trong {minutes} phút"),
+            Bork => format!("Bork for {minutes} minutes"),
+        }
+    }
+
     fn alert_capture_instruction(self) -> &'static str {
         match self {
             English => "Capture more towers",
@@ -1110,6 +1161,313 @@ impl TowerTranslation for LanguageId {
         }
     }
 
+    fn alert_alliance_requested_instruction(
+        self,
+        alias: PlayerAlias,
+        minutes: Option<u8>,
+    ) -> String {
+        if let Some(minutes) = minutes {
+            match self {
+                English | Bork => format!("{alias} requests an alliance for {minutes} minutes"),
+                Spanish => format!("{alias} solicita una alianza por {minutes} minutos"),
+                French => format!("{alias} demande une alliance pour {minutes} minutes"),
+                German => format!("{alias} bittet um ein Bündnis für {minutes} Minuten"),
+                Italian => format!("{alias} chiede un'alleanza per {minutes} minuti"),
+                Russian => format!("{alias} предлагает союз на {minutes} минут"),
+                Arabic => format!("{alias} يطلب تحالفًا لمدة {minutes} دقيقة"),
+                Hindi => format!("{alias} {minutes} मिनट के लिए गठबंधन का अनुरोध करता है"),
+                SimplifiedChinese => format!("{alias} 请求结盟 {minutes} 分钟"),
+                Japanese => format!("{alias}が{minutes}分間の同盟を申し込んでいます"),
+                Vietnamese => format!("{alias} đề nghị liên minh trong {minutes} phút"),
+            }
+        } else {
+            match self {
+                English | Bork => format!("{alias} requests an alliance"),
+                Spanish => format!("{alias} solicita una alianza"),
+                French => format!("{alias} demande une alliance"),
+                German => format!("{alias} bittet um ein Bündnis"),
+                Italian => format!("{alias} chiede un'alleanza"),
+                Russian => format!("{alias} предлагает союз"),
+                Arabic => format!("{alias} يطلب تحالفًا"),
+                Hindi => format!("{alias} गठबंधन का अनुरोध करता है"),
+                SimplifiedChinese => format!("{alias} 请求结盟"),
+                Japanese => format!("{alias}が同盟を申し込んでいます"),
+                Vietnamese => format!("{alias} đề nghị liên minh"),
+            }
+        }
+    }
+
+    fn alert_alliance_requested_hint(self) -> &'static str {
+        match self {
+            English => "Click to accept, or ✘ to decline",
+            Spanish => "Haz clic para aceptar, o ✘ para rechazar",
+            French => "Cliquez pour accepter, ou ✘ pour refuser",
+            German => "Klicken zum Annehmen, oder ✘ zum Ablehnen",
+            Italian => "Clicca per accettare, o ✘ per rifiutare",
+            Russian => "Нажмите, чтобы принять, или ✘, чтобы отклонить",
+            Arabic => "انقر للقبول، أو ✘ للرفض",
+            Hindi => "स्वीकार करने के लिए क्लिक करें, या अस्वीकार करने के लिए ✘",
+            SimplifiedChinese => "点击接受，或点击 ✘ 拒绝",
+            Japanese => "クリックで承認、✘で拒否",
+            Vietnamese => "Nhấp để chấp nhận, hoặc ✘ để từ chối",
+            Bork => "Click to bork, or ✘ to not bork",
+        }
+    }
+
+    fn alert_alliance_made_instruction(self) -> &'static str {
+        match self {
+            English => "Alliance formed",
+            Spanish => "Alianza formada",
+            French => "Alliance formée",
+            German => "Bündnis geschlossen",
+            Italian => "Alleanza formata",
+            Russian => "Союз заключён",
+            Arabic => "تم تشكيل التحالف",
+            Hindi => "गठबंधन बना",
+            SimplifiedChinese => "联盟已建立",
+            Japanese => "同盟が結ばれました",
+            Vietnamese => "Đã lập liên minh",
+            Bork => "Bork formed",
+        }
+    }
+
+    fn alert_alliance_broken_instruction(self) -> &'static str {
+        match self {
+            English => "Alliance broken",
+            Spanish => "Alianza rota",
+            French => "Alliance rompue",
+            German => "Bündnis gebrochen",
+            Italian => "Alleanza rotta",
+            Russian => "Союз разорван",
+            Arabic => "تم كسر التحالف",
+            Hindi => "गठबंधन टूट गया",
+            SimplifiedChinese => "联盟已破裂",
+            Japanese => "同盟が破棄されました",
+            Vietnamese => "Liên minh đã tan vỡ",
+            Bork => "Krob",
+        }
+    }
+
+    fn spectate_hint(self) -> &'static str {
+        match self {
+            English => "Spectate",
+            Spanish => "Observar",
+            French => "Observer",
+            German => "Zuschauen",
+            Italian => "Osserva",
+            Russian => "Наблюдать",
+            Arabic => "شاهد",
+            Hindi => "देखें",
+            SimplifiedChinese => "观战",
+            Japanese => "観戦",
+            Vietnamese => "Xem",
+            Bork => "Watch the borks",
+        }
+    }
+
+    fn spectate_anywhere_label(self) -> &'static str {
+        match self {
+            English => "Anywhere",
+            Spanish => "Cualquier lugar",
+            French => "N'importe où",
+            German => "Überall",
+            Italian => "Ovunque",
+            Russian => "Везде",
+            Arabic => "في أي مكان",
+            Hindi => "कहीं भी",
+            SimplifiedChinese => "任意位置",
+            Japanese => "どこでも",
+            Vietnamese => "Bất cứ đâu",
+            Bork => "Borkywhere",
+        }
+    }
+
+    fn stop_spectating_hint(self) -> &'static str {
+        match self {
+            English => "Stop spectating",
+            Spanish => "Dejar de observar",
+            French => "Arrêter d'observer",
+            German => "Zuschauen beenden",
+            Italian => "Smetti di osservare",
+            Russian => "Прекратить наблюдение",
+            Arabic => "توقف عن المشاهدة",
+            Hindi => "देखना बंद करें",
+            SimplifiedChinese => "停止观战",
+            Japanese => "観戦をやめる",
+            Vietnamese => "Dừng xem",
+            Bork => "Stop watching the borks",
+        }
+    }
+
+    fn create_realm_hint(self) -> &'static str {
+        match self {
+            English => "Private realm",
+            Spanish => "Reino privado",
+            French => "Royaume privé",
+            German => "Privates Reich",
+            Italian => "Regno privato",
+            Russian => "Частное королевство",
+            Arabic => "مملكة خاصة",
+            Hindi => "निजी क्षेत्र",
+            SimplifiedChinese => "私人领域",
+            Japanese => "プライベートレルム",
+            Vietnamese => "Vương quốc riêng",
+            Bork => "Private borkdom",
+        }
+    }
+
+    fn easy_bots_hint(self) -> &'static str {
+        match self {
+            English => "Easy bots",
+            Spanish => "Bots fáciles",
+            French => "Bots faciles",
+            German => "Leichte Bots",
+            Italian => "Bot facili",
+            Russian => "Лёгкие боты",
+            Arabic => "روبوتات سهلة",
+            Hindi => "आसान बॉट",
+            SimplifiedChinese => "简单机器人",
+            Japanese => "簡単なボット",
+            Vietnamese => "Bot dễ",
+            Bork => "Smol borks",
+        }
+    }
+
+    fn normal_bots_hint(self) -> &'static str {
+        match self {
+            English => "Normal bots",
+            Spanish => "Bots normales",
+            French => "Bots normaux",
+            German => "Normale Bots",
+            Italian => "Bot normali",
+            Russian => "Обычные боты",
+            Arabic => "روبوتات عادية",
+            Hindi => "सामान्य बॉट",
+            SimplifiedChinese => "普通机器人",
+            Japanese => "普通のボット",
+            Vietnamese => "Bot thường",
+            Bork => "Regular borks",
+        }
+    }
+
+    fn hard_bots_hint(self) -> &'static str {
+        match self {
+            English => "Hard bots",
+            Spanish => "Bots difíciles",
+            French => "Bots difficiles",
+            German => "Schwere Bots",
+            Italian => "Bot difficili",
+            Russian => "Сложные боты",
+            Arabic => "روبوتات صعبة",
+            Hindi => "कठिन बॉट",
+            SimplifiedChinese => "困难机器人",
+            Japanese => "難しいボット",
+            Vietnamese => "Bot khó",
+            Bork => "Big borks",
+        }
+    }
+
+    fn creating_realm_label(self) -> &'static str {
+        match self {
+            English => "Creating realm...",
+            Spanish => "Creando reino...",
+            French => "Création du royaume...",
+            German => "Reich wird erstellt...",
+            Italian => "Creazione del regno...",
+            Russian => "Создание королевства...",
+            Arabic => "جارٍ إنشاء المملكة...",
+            Hindi => "क्षेत्र बनाया जा रहा है...",
+            SimplifiedChinese => "正在创建领域...",
+            Japanese => "レルムを作成中...",
+            Vietnamese => "Đang tạo vương quốc...",
+            Bork => "Borking a borkdom...",
+        }
+    }
+
+    fn round_ended_reason(self) -> &'static str {
+        match self {
+            English => "The round ended!",
+            Spanish => "¡La ronda terminó!",
+            French => "La manche est terminée !",
+            German => "Die Runde ist vorbei!",
+            Italian => "Il round è finito!",
+            Russian => "Раунд окончен!",
+            Arabic => "انتهت الجولة!",
+            Hindi => "राउंड समाप्त हो गया!",
+            SimplifiedChinese => "本回合结束了！",
+            Japanese => "ラウンドが終了しました！",
+            Vietnamese => "Vòng đấu đã kết thúc!",
+            Bork => "The bork ended!",
+        }
+    }
+
+    fn round_ends_in_label(self, timer: &str) -> String {
+        match self {
+            English | Bork => format!("Round ends in {timer}"),
+            Spanish => format!("La ronda termina en {timer}"),
+            French => format!("La manche se termine dans {timer}"),
+            German => format!("Runde endet in {timer}"),
+            Italian => format!("Il round termina tra {timer}"),
+            Russian => format!("Раунд закончится через {timer}"),
+            Arabic => format!("تنتهي الجولة خلال {timer}"),
+            Hindi => format!("राउंड {timer} में समाप्त होगा"),
+            SimplifiedChinese => format!("本回合将在 {timer} 后结束"),
+            Japanese => format!("ラウンド終了まで {timer}"),
+            Vietnamese => format!("Vòng đấu kết thúc sau {timer}"),
+        }
+    }
+
+    fn round_towers_label(self) -> &'static str {
+        match self {
+            English => "Towers",
+            Spanish => "Torres",
+            French => "Tours",
+            German => "Türme",
+            Italian => "Torri",
+            Russian => "Башни",
+            Arabic => "الأبراج",
+            Hindi => "टावर",
+            SimplifiedChinese => "塔",
+            Japanese => "タワー",
+            Vietnamese => "Tháp",
+            Bork => "Borks",
+        }
+    }
+
+    fn round_center_label(self) -> &'static str {
+        match self {
+            English => "Center",
+            Spanish => "Centro",
+            French => "Centre",
+            German => "Zentrum",
+            Italian => "Centro",
+            Russian => "Центр",
+            Arabic => "المركز",
+            Hindi => "केंद्र",
+            SimplifiedChinese => "中心",
+            Japanese => "中心",
+            Vietnamese => "Trung tâm",
+            Bork => "Bork center",
+        }
+    }
+
+    fn killed_by_moderator_reason(self) -> &'static str {
+        match self {
+            English => "You were killed by a moderator!",
+            Spanish => "¡Un moderador te eliminó!",
+            French => "Vous avez été tué par un modérateur !",
+            German => "Du wurdest von einem Moderator getötet!",
+            Italian => "Sei stato ucciso da un moderatore!",
+            Russian => "Вас убил модератор!",
+            Arabic => "لقد قتلك أحد المشرفين!",
+            Hindi => "आपको एक मॉडरेटर ने मार दिया!",
+            SimplifiedChinese => "你被管理员杀死了！",
+            Japanese => "モデレーターに倒されました！",
+            Vietnamese => "Bạn đã bị người kiểm duyệt tiêu diệt!",
+            Bork => "You were borked by a moderator!",
+        }
+    }
+
     fn ruler_killed(self, alias: Option<PlayerAlias>, unit: &str) -> String {
         let ruler = self.ruler_label();
         let owner = alias.map_or(
@@ -1173,20 +1531,22 @@ mod tests {
 
     #[test]
     fn test_death_reason() {
-        let reason = DeathReason::RulerKilled {
-            alias: Some(PlayerAlias::new_unsanitized("Bob")),
-            unit: Unit::Soldier,
-        };
-        for id in LanguageId::iter() {
-            println!("{}", id.death_reason(reason))
-        }
-
-        let reason = DeathReason::RulerKilled {
-            alias: None,
-            unit: Unit::Soldier,
-        };
         for id in LanguageId::iter() {
-            println!("{}", id.death_reason(reason))
+            let by_player = id.death_reason(DeathReason::RulerKilled {
+                alias: Some(PlayerAlias::new_unsanitized("Bob")),
+                unit: Unit::Soldier,
+            });
+            assert!(by_player.contains("Bob"), "{by_player}");
+
+            let by_zombie = id.death_reason(DeathReason::RulerKilled {
+                alias: None,
+                unit: Unit::Tank,
+            });
+            assert!(!by_zombie.is_empty());
+            assert_eq!(
+                id.death_reason(DeathReason::RulerKilledByZombies { unit: Unit::Tank }),
+                by_zombie
+            );
         }
     }
 }
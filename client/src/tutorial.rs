@@ -283,6 +283,7 @@ mod deployment {
                     .core
                     .player_id
                     .unwrap_or(PlayerId::SOLO_OFFLINE),
+                context.settings.path_mode,
                 |tower_id| {
                     is_visible(context, tower_id)
                         && (tower_id == src_id
@@ -7,6 +7,9 @@ mod button;
 mod changelog_dialog;
 mod help_dialog;
 mod lock_dialog;
+mod realm_link;
+mod round_overlay;
+mod spectate_overlay;
 mod tower_icon;
 mod tower_overlay;
 mod towers_dialog;
@@ -22,10 +25,20 @@ use crate::ui::about_dialog::AboutDialog;
 use crate::ui::alert_overlay::AlertOverlay;
 use crate::ui::changelog_dialog::ChangelogDialog;
 use crate::ui::help_dialog::HelpDialog;
+use crate::ui::realm_link::RealmLink;
+use crate::ui::round_overlay::RoundOverlay;
+use crate::ui::spectate_overlay::SpectateOverlay;
 use crate::ui::towers_dialog::TowersDialog;
 use crate::TowerGame;
 use common::alerts::Alerts;
+use common::alliance::AllianceRequest;
 use common::death_reason::DeathReason;
+use common::force::Deployment;
+use common::protocol::Spectate;
+use common::research::{Research, ResearchArray};
+use common::round::Round;
+use common::ruler::{RulerAbility, RulerAbilityArray};
+use common::ticks::Ticks;
 use common::tower::{Tower, TowerArray, TowerId, TowerType};
 use common::unit::Unit;
 use core_protocol::name::PlayerAlias;
@@ -45,6 +58,7 @@ use yew_frontend::component::account_menu::AccountMenu;
 use yew_frontend::component::discord_icon::DiscordIcon;
 use yew_frontend::component::invitation_link::InvitationLink;
 use yew_frontend::component::language_menu::LanguageMenu;
+use yew_frontend::component::link::Link;
 use yew_frontend::component::positioner::{Align, Flex, Position, Positioner};
 use yew_frontend::component::privacy_link::PrivacyLink;
 use yew_frontend::component::route_link::RouteLink;
@@ -57,6 +71,7 @@ use yew_frontend::frontend::{
 use yew_frontend::overlay::chat::ChatOverlay;
 use yew_frontend::overlay::leaderboard::LeaderboardOverlay;
 use yew_frontend::overlay::spawn::SpawnOverlay;
+use yew_frontend::overlay::team::TeamOverlay;
 use yew_frontend::translation::{use_translation, Translation};
 use yew_router::prelude::*;
 
@@ -65,17 +80,61 @@ pub enum TowerUiEvent {
     Alliance {
         with: PlayerId,
         break_alliance: bool,
+        /// If [`Some`], a requested alliance expires after this many minutes.
+        minutes: Option<u8>,
+    },
+    /// Create a private realm with its own world and bots.
+    CreateRealm {
+        /// See [`core_protocol::rpc::InvitationRequest::CreateRealm`].
+        bot_difficulty: u8,
     },
     DismissCaptureTutorial,
     DismissUpgradeTutorial,
+    /// Deploy this percent of units from now on (100 for all of them).
+    DeployPercent(u8),
+    /// Deploy this many of a unit from a tower, along with the units already selected.
+    DeployUnits {
+        tower_id: TowerId,
+        unit: Unit,
+        count: usize,
+    },
+    /// Change one of a tower's supply lines.
+    EditSupplyLine {
+        tower_id: TowerId,
+        index: usize,
+        edit: SupplyLineEdit,
+    },
     PanTo(TowerId),
+    /// Spend research points on the next level of a [`Research`].
+    Research(Research),
     Spawn(PlayerAlias),
+    /// Start (or with [`None`], stop) spectating.
+    Spectate(Option<Spectate>),
+    /// Give a tower to an ally.
+    TransferTower {
+        tower_id: TowerId,
+        to: PlayerId,
+    },
     Upgrade {
         tower_id: TowerId,
         tower_type: TowerType,
     },
     Unlock(TowerType),
     LockDialog(Option<TowerType>),
+    /// Use a ruler ability, targeting a tower if [`RulerAbility::has_target`].
+    UseRulerAbility {
+        ability: RulerAbility,
+        target: Option<TowerId>,
+    },
+}
+
+/// A change to a [`SupplyLine`][`common::supply_line::SupplyLine`].
+#[derive(Copy, Clone)]
+pub enum SupplyLineEdit {
+    Weight(u8),
+    Unit(Option<Unit>),
+    BelowPercent(Option<u8>),
+    Remove,
 }
 
 #[derive(Clone, PartialEq, Default)]
@@ -85,6 +144,13 @@ pub struct TowerUiProps {
     pub selected_tower: Option<SelectedTower>,
     pub tower_counts: TowerArray<u8>,
     pub alerts: Alerts,
+    /// Pending alliance requests, and who they are from.
+    pub alliance_requests: Vec<(AllianceRequest, PlayerAlias)>,
+    /// Which units to deploy when dragging from a tower.
+    pub deployment: Deployment,
+    pub round: Round,
+    /// What is being watched, if spectating.
+    pub spectate: Option<Spectate>,
     pub tutorial_alert: Option<TutorialAlert>,
     pub unlocks: Unlocks,
     pub lock_dialog: Option<TowerType>,
@@ -102,12 +168,24 @@ pub struct SelectedTower {
     pub tower_id: TowerId,
     /// If we are trying to ally with them or already allied with them.
     pub outgoing_alliance: bool,
+    /// Allies the tower could be given to, if it is ours.
+    pub allies: Vec<(PlayerId, PlayerAlias)>,
+    /// Time until each of our ruler's abilities can be used again.
+    pub ability_cooldowns: RulerAbilityArray<Ticks>,
+    /// If our ruler could teleport to the tower.
+    pub can_teleport: bool,
+    /// Research levels of the tower's player.
+    pub research: ResearchArray<u8>,
+    /// Unspent research points of the tower's player.
+    pub research_points: u16,
 }
 
 #[styled_component(TowerUi)]
 pub fn tower_ui(props: &PropertiesWrapper<TowerUiProps>) -> Html {
     let ui_event_callback = use_ui_event_callback::<TowerGame>();
     let on_play = ui_event_callback.reform(TowerUiEvent::Spawn);
+    let on_spectate =
+        ui_event_callback.reform(|_: MouseEvent| TowerUiEvent::Spectate(Some(Spectate::Viewport)));
 
     let header_css = css!(
         r#"
@@ -167,14 +245,25 @@ pub fn tower_ui(props: &PropertiesWrapper<TowerUiProps>) -> Html {
                     <LanguageMenu/>
                 </Positioner>
                 <LeaderboardOverlay position={Position::TopRight{margin: MARGIN}} style="max-width: 25%;"/>
-                if let Some(SelectedTower{client_position, color, tower, tower_id, outgoing_alliance}) = props.selected_tower.clone() {
+                if props.round.is_enabled() {
+                    <Positioner position={Position::TopMiddle{margin: MARGIN}}>
+                        <RoundOverlay round={props.round}/>
+                    </Positioner>
+                }
+                if let Some(SelectedTower{client_position, color, tower, tower_id, outgoing_alliance, allies, ability_cooldowns, can_teleport, research, research_points}) = props.selected_tower.clone() {
                     <TowerOverlay
                         {client_position}
                         {color}
                         {tower}
                         {tower_id}
                         {outgoing_alliance}
+                        {allies}
+                        {ability_cooldowns}
+                        {can_teleport}
+                        {research}
+                        {research_points}
                         tower_counts={props.tower_counts}
+                        deployment={props.deployment.clone()}
                         tutorial_alert={props.tutorial_alert}
                         unlocks={props.unlocks.clone()}
                     />
@@ -183,12 +272,25 @@ pub fn tower_ui(props: &PropertiesWrapper<TowerUiProps>) -> Html {
                     <RouteLink<TowerRoute> route={TowerRoute::Help}>{t.help_hint()}</RouteLink<TowerRoute>>
                 </Positioner>
                 <Positioner position={Position::TopLeft{margin: MARGIN}} align={Align::Left} max_width="25%">
-                    <AlertOverlay alerts={props.alerts} tutorial_alert={props.tutorial_alert}/>
+                    <AlertOverlay alerts={props.alerts} alliance_requests={props.alliance_requests.clone()} tutorial_alert={props.tutorial_alert}/>
                 </Positioner>
                 <ChatOverlay position={Position::BottomLeft{margin: MARGIN}} style="max-width: 25%;" hints={HINTS}/>
+                <TeamOverlay position={Position::CenterLeft{margin: MARGIN}} style="max-width: 25%;"/>
                 if let Some(tower_type) = props.lock_dialog {
                     <LockDialog keys={props.unlocks.keys} {tower_type}/>
                 }
+            } else if let Some(spectate) = props.spectate {
+                <Positioner position={Position::CenterRight{margin: MARGIN}} flex={Flex::Column}>
+                    <ZoomIcon amount={-4}/>
+                    <ZoomIcon amount={4}/>
+                    <VolumeIcon/>
+                    <LanguageMenu/>
+                </Positioner>
+                <LeaderboardOverlay position={Position::TopRight{margin: MARGIN}} style="max-width: 25%;"/>
+                <Positioner position={Position::TopMiddle{margin: MARGIN}}>
+                    <SpectateOverlay {spectate}/>
+                </Positioner>
+                <ChatOverlay position={Position::BottomLeft{margin: MARGIN}} style="max-width: 25%;"/>
             } else {
                 <SpawnOverlay {on_play}>
                     <p class={header_css}>
@@ -210,6 +312,8 @@ pub fn tower_ui(props: &PropertiesWrapper<TowerUiProps>) -> Html {
                     </Positioner>
                 }
                 <Positioner position={Position::BottomMiddle{margin: MARGIN}} flex={Flex::Row}>
+                    <Link onclick={on_spectate}>{t.spectate_hint()}</Link>
+                    <RealmLink/>
                     <RouteLink<TowerRoute> route={TowerRoute::Help}>{t.help_hint()}</RouteLink<TowerRoute>>
                     <RouteLink<TowerRoute> route={TowerRoute::About}>{t.about_hint()}</RouteLink<TowerRoute>>
                     <PrivacyLink/>
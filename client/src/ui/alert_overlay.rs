@@ -3,7 +3,9 @@ use crate::tutorial::TutorialAlert;
 use crate::ui::TowerUiEvent;
 use crate::TowerGame;
 use common::alerts::{AlertFlag, Alerts};
+use common::alliance::AllianceRequest;
 use common::tower::TowerId;
+use core_protocol::name::PlayerAlias;
 use stylist::yew::styled_component;
 use yew::virtual_dom::AttrValue;
 use yew::{classes, hook, html, use_state, Callback, Html, MouseEvent, Properties, UseStateHandle};
@@ -14,6 +16,7 @@ use yew_icons::{Icon, IconId};
 #[derive(PartialEq, Properties)]
 pub struct AlertOverlayProps {
     pub alerts: Alerts,
+    pub alliance_requests: Vec<(AllianceRequest, PlayerAlias)>,
     pub tutorial_alert: Option<TutorialAlert>,
 }
 
@@ -55,6 +58,34 @@ pub fn alert_overlay(props: &AlertOverlayProps) -> Html {
 
     html! {
         <table class={overlay_css}>
+            {props.alliance_requests.iter().map(|&(AllianceRequest{player_id, minutes}, alias)| {
+                let alliance = |break_alliance: bool| TowerUiEvent::Alliance {
+                    with: player_id,
+                    break_alliance,
+                    minutes: None,
+                };
+                html!{
+                    <Alert
+                        instruction={t.alert_alliance_requested_instruction(alias, minutes)}
+                        hint={t.alert_alliance_requested_hint()}
+                        icon_id={IconId::FontAwesomeSolidHandshake}
+                        onclick={send_event_factory(alliance(false))}
+                        onclick_dismiss={send_event_factory(alliance(true))}
+                    />
+                }
+            }).collect::<Html>()}
+            if props.alerts.flags().contains(AlertFlag::MadeAlliance) {
+                <Alert
+                    instruction={t.alert_alliance_made_instruction()}
+                    icon_id={IconId::FontAwesomeSolidHandshake}
+                />
+            }
+            if props.alerts.flags().contains(AlertFlag::BrokeAlliance) {
+                <Alert
+                    instruction={t.alert_alliance_broken_instruction()}
+                    icon_id={IconId::FontAwesomeSolidHandshakeSlash}
+                />
+            }
             if props.alerts.flags().contains(AlertFlag::RulerUnderAttack) {
                 <Alert
                     instruction={t.alert_ruler_under_attack_warning()}
@@ -41,12 +41,25 @@ pub fn help_dialog() -> Html {
                 <UnitIcon unit={Unit::Nuke}/>
                 {"."}
             </p>
+            <p>
+                {"Click the tower with your "}
+                <UnitIcon unit={Unit::Ruler}/>
+                {" to surge its shields or rally the units of nearby towers to it. In an emergency, click one of your towers nearby to teleport your "}
+                <UnitIcon unit={Unit::Ruler}/>
+                {" there. Each ability has a cooldown before it can be used again."}
+            </p>
+            <p>
+                {"Each "}
+                <TowerIcon tower_type={TowerType::Lab}/>
+                {" you own produces research points. Click one to spend them on permanent upgrades, such as more shield capacity or faster units. Research is lost when you die."}
+            </p>
             <h2>{"Supply Lines"}</h2>
             <p>
             {TowerType::iter().filter(TowerType::generates_mobile_units).map(|tower_type| html! {
                  <TowerIcon {tower_type}/>
             }).intersperse_with(|| html!({{" "}})).collect::<Html>()}
             {" can automatically send units via supply lines. To create a supply line, click a tower to open its menu. Then drag from the tower as normal. If the resulting path has moving arrows, you've succeeded. Hold R to display all your supply lines. To delete a supply line, create the same one again or hold Shift + R."}</p>
+            <p>{"Hold Shift while dragging to add up to four supply lines to a tower, which share its units. The tower menu sets how much each one gets, which units it carries, and whether it pauses while the destination is full enough."}</p>
             <h2>{"Alliances"}</h2>
             <p>
                 {"Select an enemy tower and click "}
@@ -56,8 +69,11 @@ pub fn help_dialog() -> Html {
                     alt={"the handshake button"}
                 />
                 {" to request or accept an alliance. Until broken, the alliance will prevent each side from attacking."}</p>
+            <p>{"Units you send to an ally's tower reinforce it, becoming theirs. You can also give an ally any tower except your ruler's from its menu."}</p>
+            <p>{"Requests you receive appear in the top left, where you can accept or decline them. Alliances can also be requested for a limited time, after which they expire. After breaking an alliance, you must wait a minute before requesting another."}</p>
             <h2>{"Chat"}</h2>
             <p>{"Use the panel in the bottom left to send messages to other players. Remember to never share personal information in chat!"}</p>
+            <p>{"Chat also accepts commands: type /ally or /unally followed by a player's name to request or break an alliance, or /stats to see a summary of your empire."}</p>
         </Dialog>
     }
 }
@@ -0,0 +1,66 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::translation::TowerTranslation;
+use crate::ui::TowerUiEvent;
+use crate::TowerGame;
+use core_protocol::id::InvitationId;
+use yew::{function_component, html, use_effect_with_deps, use_state, Callback, Html, MouseEvent};
+use yew_frontend::component::link::Link;
+use yew_frontend::frontend::{use_core_state, use_ui_event_callback};
+use yew_frontend::translation::use_translation;
+use yew_frontend::window::location::set_location_href;
+
+/// Creates a private realm with bots of the chosen difficulty, and joins it once the invitation to
+/// it arrives.
+#[function_component(RealmLink)]
+pub fn realm_link() -> Html {
+    let t = use_translation();
+    let send_event = use_ui_event_callback::<TowerGame>();
+    let created_invitation_id = use_core_state().created_invitation_id;
+    let choosing_difficulty = use_state(|| false);
+    // Invitation from before creating a realm, so the realm's invitation can be told apart.
+    let creating = use_state::<Option<Option<InvitationId>>, _>(|| None);
+
+    use_effect_with_deps(
+        |&(creating, created_invitation_id)| {
+            if let Some((previous, invitation_id)) = creating.zip(created_invitation_id) {
+                if previous != Some(invitation_id) {
+                    set_location_href(&format!("/invite/{}", invitation_id.0));
+                }
+            }
+            || {}
+        },
+        (*creating, created_invitation_id),
+    );
+
+    if creating.is_some() {
+        return html! {
+            <span>{t.creating_realm_label()}</span>
+        };
+    }
+
+    if !*choosing_difficulty {
+        let onclick = Callback::from(move |_: MouseEvent| choosing_difficulty.set(true));
+        return html! {
+            <Link {onclick}>{t.create_realm_hint()}</Link>
+        };
+    }
+
+    // Levels are in order of `Difficulty::ALL` on the server.
+    let difficulties = [t.easy_bots_hint(), t.normal_bots_hint(), t.hard_bots_hint()];
+    html! {
+        <>{for difficulties.into_iter().enumerate().map(|(bot_difficulty, label)| {
+            let creating = creating.clone();
+            let onclick = send_event.reform(move |_: MouseEvent| {
+                creating.set(Some(created_invitation_id));
+                TowerUiEvent::CreateRealm {
+                    bot_difficulty: bot_difficulty as u8,
+                }
+            });
+            html! {
+                <Link {onclick}>{label}</Link>
+            }
+        })}</>
+    }
+}
@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::translation::TowerTranslation;
+use common::round::Round;
+use stylist::yew::styled_component;
+use yew::{html, Html, Properties};
+use yew_frontend::translation::use_translation;
+
+#[derive(PartialEq, Properties)]
+pub struct RoundOverlayProps {
+    pub round: Round,
+}
+
+/// Shows the round timer and how close the player's alliance is to winning.
+#[styled_component(RoundOverlay)]
+pub fn round_overlay(props: &RoundOverlayProps) -> Html {
+    let overlay_css = css!(
+        r#"
+        color: white;
+        font-size: 1rem;
+        text-align: center;
+        user-select: none;
+        "#
+    );
+
+    let t = use_translation();
+    let round = &props.round;
+    let timer = |seconds: u16| format!("{}:{:02}", seconds / 60, seconds % 60);
+
+    html! {
+        <div class={overlay_css}>
+            <div>{t.round_ends_in_label(&timer(round.seconds_remaining))}</div>
+            if round.tower_percent_goal > 0 {
+                <div>{format!("{}: {}% / {}%", t.round_towers_label(), round.tower_percent, round.tower_percent_goal)}</div>
+            }
+            if round.center_seconds_goal > 0 {
+                <div>{format!("{}: {} / {}", t.round_center_label(), timer(round.center_seconds), timer(round.center_seconds_goal))}</div>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,68 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::translation::TowerTranslation;
+use crate::ui::TowerUiEvent;
+use crate::TowerGame;
+use common::protocol::Spectate;
+use stylist::yew::styled_component;
+use yew::{html, Html, MouseEvent, Properties};
+use yew_frontend::component::link::Link;
+use yew_frontend::frontend::{use_core_state, use_ui_event_callback};
+use yew_frontend::translation::use_translation;
+
+#[derive(PartialEq, Properties)]
+pub struct SpectateOverlayProps {
+    pub spectate: Spectate,
+}
+
+/// Lets spectators choose who to follow (or to watch anywhere), or stop spectating.
+#[styled_component(SpectateOverlay)]
+pub fn spectate_overlay(props: &SpectateOverlayProps) -> Html {
+    /// Players on the liveboard that can be followed.
+    const MAX_FOLLOWABLE: usize = 5;
+
+    let overlay_css = css!(
+        r#"
+        color: white;
+        font-size: 1rem;
+        text-align: center;
+        user-select: none;
+        display: flex;
+        flex-direction: row;
+        gap: 0.75rem;
+        "#
+    );
+
+    let selected_css = css!(
+        r#"
+        font-weight: bold;
+        "#
+    );
+
+    let t = use_translation();
+    let core_state = use_core_state();
+    let send_event = use_ui_event_callback::<TowerGame>();
+    let spectate_factory = |spectate: Option<Spectate>| {
+        send_event.reform(move |_: MouseEvent| TowerUiEvent::Spectate(spectate))
+    };
+    let option = |spectate: Spectate, label: String| {
+        let class = (props.spectate == spectate).then(|| selected_css.clone());
+        html! {
+            <span {class}>
+                <Link onclick={spectate_factory(Some(spectate))}>{label}</Link>
+            </span>
+        }
+    };
+
+    html! {
+        <div class={overlay_css}>
+            {option(Spectate::Viewport, t.spectate_anywhere_label().to_owned())}
+            {core_state.liveboard.iter().take(MAX_FOLLOWABLE).filter_map(|dto| {
+                let player = core_state.player_or_bot(dto.player_id)?;
+                Some(option(Spectate::Player(dto.player_id), player.alias.to_string()))
+            }).collect::<Html>()}
+            <Link onclick={spectate_factory(None)}>{t.stop_spectating_hint()}</Link>
+        </div>
+    }
+}
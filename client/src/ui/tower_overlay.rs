@@ -6,9 +6,16 @@ use crate::tutorial::TutorialAlert;
 use crate::ui::button::Button;
 use crate::ui::tower_icon::TowerIcon;
 use crate::ui::unit_icon::UnitIcon;
-use crate::ui::TowerUiEvent;
+use crate::ui::{SupplyLineEdit, TowerUiEvent};
 use crate::TowerGame;
+use common::force::Deployment;
+use common::research::{Research, ResearchArray};
+use common::ruler::{RulerAbility, RulerAbilityArray};
+use common::ticks::Ticks;
 use common::tower::{Tower, TowerArray, TowerId, TowerType};
+use common::unit::Unit;
+use core_protocol::name::PlayerAlias;
+use core_protocol::PlayerId;
 use glam::IVec2;
 use stylist::css;
 use stylist::yew::styled_component;
@@ -17,14 +24,23 @@ use yew::{classes, html, html_nested, Callback, Html, MouseEvent, Properties};
 use yew_frontend::frontend::{use_core_state, use_rewarded_ad, use_ui_event_callback};
 use yew_frontend::translation::{use_translation, Translation};
 
+/// Length of alliances requested with the timed alliance button.
+const TIMED_ALLIANCE_MINUTES: u8 = 10;
+
 #[derive(PartialEq, Properties)]
 pub struct TowerOverlayProps {
     pub color: Color,
     pub outgoing_alliance: bool,
+    pub allies: Vec<(PlayerId, PlayerAlias)>,
+    pub ability_cooldowns: RulerAbilityArray<Ticks>,
+    pub can_teleport: bool,
+    pub research: ResearchArray<u8>,
+    pub research_points: u16,
     pub tower_id: TowerId,
     pub tower: Tower,
     pub client_position: IVec2,
     pub tower_counts: TowerArray<u8>,
+    pub deployment: Deployment,
     pub tutorial_alert: Option<TutorialAlert>,
     pub unlocks: Unlocks,
 }
@@ -106,10 +122,69 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
     let on_alliance_factory = {
         let send_ui_event = use_ui_event_callback::<TowerGame>();
 
-        move |break_alliance: bool| {
+        move |break_alliance: bool, minutes: Option<u8>| {
             send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::Alliance {
                 with: player_id.unwrap(),
                 break_alliance,
+                minutes,
+            })
+        }
+    };
+
+    let on_transfer_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |to: PlayerId| {
+            send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::TransferTower { tower_id, to })
+        }
+    };
+
+    let on_ruler_ability_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |ability: RulerAbility| {
+            let target = ability.has_target().then_some(tower_id);
+            send_ui_event
+                .reform(move |_: MouseEvent| TowerUiEvent::UseRulerAbility { ability, target })
+        }
+    };
+
+    let on_research_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |research: Research| {
+            send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::Research(research))
+        }
+    };
+
+    let on_deploy_units_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |unit: Unit, count: usize| {
+            send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::DeployUnits {
+                tower_id,
+                unit,
+                count,
+            })
+        }
+    };
+
+    let on_deploy_percent_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |percent: u8| {
+            send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::DeployPercent(percent))
+        }
+    };
+
+    let on_edit_supply_line_factory = {
+        let send_ui_event = use_ui_event_callback::<TowerGame>();
+
+        move |index: usize, edit: SupplyLineEdit| {
+            send_ui_event.reform(move |_: MouseEvent| TowerUiEvent::EditSupplyLine {
+                tower_id,
+                index,
+                edit,
             })
         }
     };
@@ -151,6 +226,36 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
     // Only render cursor once.
     let mut has_cursor = true;
 
+    // Which units dragging from this tower would deploy.
+    let force_units = props.tower.force_units();
+    let deploying = props
+        .tower
+        .deployment_units(&props.deployment.clamp(&force_units))
+        .unwrap_or_default();
+    let deploy_percent = match props.deployment {
+        Deployment::All => Some(100),
+        Deployment::Percent(percent) => Some(percent),
+        Deployment::Units(_) => None,
+    };
+
+    // Supply lines can be limited to any unit the tower generates.
+    let supply_units: Vec<Unit> = Unit::iter()
+        .filter(|&unit| {
+            unit != Unit::Ruler
+                && unit.is_mobile(Some(tower_type))
+                && props.tower.unit_generation(unit).is_some()
+        })
+        .collect();
+    let next_supply_unit = |unit: Option<Unit>| -> Option<Unit> {
+        let index = unit.and_then(|unit| supply_units.iter().position(|&u| u == unit));
+        supply_units.get(index.map_or(0, |i| i + 1)).copied()
+    };
+    let next_below_percent = |percent: Option<u8>| match percent {
+        None => Some(25),
+        Some(percent) if percent < 75 => Some(percent + 25),
+        _ => None,
+    };
+
     html! {
         <Button
             style={format!("left: {}px; bottom: {}px;", props.client_position.x + 10, props.client_position.y + 10)}
@@ -166,10 +271,57 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
                 html_nested!{
                     <p style="margin: 0;" title={t.unit_label(unit)}>
                         <UnitIcon {unit} size={"1.25rem"} fill={unit_color}/>
-                        {format!("{}/{}", count, props.tower.units.capacity(unit, Some(props.tower.tower_type)))}
+                        {format!("{}/{}", count, props.tower.units.researched_capacity(unit, Some(props.tower.tower_type), &props.research))}
+                        if is_mine && force_units.contains(unit) {
+                            {" "}
+                            <Button onclick={on_deploy_units_factory(unit, deploying.available(unit).saturating_sub(1))} title={"Deploy fewer"}>{"−"}</Button>
+                            {format!(" {} ", deploying.available(unit))}
+                            <Button onclick={on_deploy_units_factory(unit, deploying.available(unit) + 1)} title={"Deploy more"}>{"+"}</Button>
+                        }
                     </p>
                 }
             }).collect::<Html>()}
+            if is_mine && !force_units.is_empty() {
+                <div style="display: flex; flex-direction: row; gap: 0.25rem;">
+                    {[25, 50, 75, 100].into_iter().map(|percent| {
+                        let color = if deploy_percent == Some(percent) { Color::Blue } else { Color::Gray };
+                        html_nested!{
+                            <Button
+                                onclick={on_deploy_percent_factory(percent)}
+                                title={"Percent of units to deploy"}
+                                style={format!("background-color: {};", color.background_color_css())}
+                            >
+                                {format!("{percent}%")}
+                            </Button>
+                        }
+                    }).collect::<Html>()}
+                </div>
+            }
+            if is_mine {
+                {props.tower.supply_lines.iter().enumerate().map(|(index, supply_line)| {
+                    let unit = supply_line.unit;
+                    let weight = supply_line.weight;
+                    let below_percent = supply_line.below_percent;
+                    html_nested!{
+                        <div style="display: flex; flex-direction: row; gap: 0.25rem; align-items: center;">
+                            <Button onclick={on_edit_supply_line_factory(index, SupplyLineEdit::Unit(next_supply_unit(unit)))} title={"Units to supply"}>
+                                if let Some(unit) = unit {
+                                    <UnitIcon {unit} size={"1rem"} fill={unit_color}/>
+                                } else {
+                                    {"All"}
+                                }
+                            </Button>
+                            <Button onclick={on_edit_supply_line_factory(index, SupplyLineEdit::Weight(weight.saturating_sub(1)))} title={"Supply less"}>{"−"}</Button>
+                            {format!(" ×{weight} ")}
+                            <Button onclick={on_edit_supply_line_factory(index, SupplyLineEdit::Weight(weight + 1))} title={"Supply more"}>{"+"}</Button>
+                            <Button onclick={on_edit_supply_line_factory(index, SupplyLineEdit::BelowPercent(next_below_percent(below_percent)))} title={"Only supply while the destination is below this percent full"}>
+                                {below_percent.map_or(String::from("Always"), |percent| format!("<{percent}%"))}
+                            </Button>
+                            <Button onclick={on_edit_supply_line_factory(index, SupplyLineEdit::Remove)} title={"Remove supply line"} style={format!("background-color: {};", Color::Red.background_color_css())}>{"✕"}</Button>
+                        </div>
+                    }
+                }).collect::<Html>()}
+            }
             if is_mine && props.tower.active() {
                 {props.tower.tower_type.upgrades().chain((basis != tower_type).then_some(basis)).map(|upgrade| {
                     let locked = locked(upgrade);
@@ -227,6 +379,75 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
                     }
                 }).collect::<Html>()}
             }
+            if is_mine && (props.tower.units.has_ruler() || props.can_teleport) {
+                // TODO translate.
+                <div style="display: flex; flex-direction: row; flex-wrap: wrap; gap: 0.5rem;">
+                    {RulerAbility::iter().filter(|ability| if ability.has_target() {
+                        props.can_teleport
+                    } else {
+                        props.tower.units.has_ruler()
+                    }).map(|ability| {
+                        let cooldown = props.ability_cooldowns[ability];
+                        let (label, title) = match ability {
+                            RulerAbility::Teleport => ("Teleport ruler here", "Move your ruler to this tower instantly"),
+                            RulerAbility::ShieldSurge => ("Shield surge", "Overfill this tower with shields, which slowly decay"),
+                            RulerAbility::Rally => ("Rally", "Send the units of nearby towers to your ruler"),
+                        };
+                        html_nested!{
+                            <Button
+                                disabled={cooldown != Ticks::ZERO}
+                                onclick={on_ruler_ability_factory(ability)}
+                                {title}
+                                style={format!("background-color: {};", Color::Purple.background_color_css())}
+                            >
+                                if cooldown == Ticks::ZERO {
+                                    {label}
+                                } else {
+                                    {format!("{label} ({}s)", cooldown.to_whole_secs())}
+                                }
+                            </Button>
+                        }
+                    }).collect::<Html>()}
+                </div>
+            }
+            if is_mine && tower_type == TowerType::Lab {
+                // TODO translate.
+                <p style="margin: 0;">{format!("{} research points", props.research_points)}</p>
+                <div style="display: flex; flex-direction: row; flex-wrap: wrap; gap: 0.5rem;">
+                    {Research::iter().map(|research| {
+                        let level = props.research[research];
+                        let max_level = research.max_level();
+                        let (label, title) = match research {
+                            Research::ShieldCapacity => ("Shield capacity", format!("Towers hold 10% more shields per level, up to {} more", Unit::Shield.max_overflow())),
+                            Research::UnitSpeed => ("Unit speed", String::from("Forces move one speed tier faster")),
+                        };
+                        let researchable = level < max_level && props.research_points >= research.cost(level);
+                        html_nested!{
+                            <Button
+                                disabled={!researchable}
+                                onclick={on_research_factory(research)}
+                                {title}
+                            >
+                                if level < max_level {
+                                    {format!("{label} {}/{max_level} ({} points)", level, research.cost(level))}
+                                } else {
+                                    {format!("{label} {level}/{max_level}")}
+                                }
+                            </Button>
+                        }
+                    }).collect::<Html>()}
+                </div>
+            }
+            if is_mine && !props.tower.units.has_ruler() && !props.allies.is_empty() {
+                // TODO translate.
+                <div style="display: flex; flex-direction: row; flex-wrap: wrap; gap: 0.5rem;">
+                    {props.allies.iter().map(|&(ally, alias)| html_nested!{
+                        <Button onclick={on_transfer_factory(ally)} title={format!("Give this tower and its units to {alias}")}>
+                            {format!("Give to {alias}")}
+                        </Button>
+                    }).collect::<Html>()}
+                </div>
+            }
             {enemy_player_alias.map(|enemy_player_alias| {
                 let break_alliance = outgoing_alliance;
                 let (color, path_id, title) = if break_alliance {
@@ -243,12 +464,21 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
                 html_nested! {
                     <div style="display: flex; flex-direction: row; gap: 0.5rem;">
                         <Button
-                            onclick={on_alliance_factory(break_alliance)}
+                            onclick={on_alliance_factory(break_alliance, None)}
                             {title}
                             style={format!("background-color: {};", color.background_color_css())}
                         >
                             <img {alt} style={"width: 2.5rem; height: 2.5rem; vertical-align: bottom; user-drag: none; -webkit-user-drag: none;"} src={attr(SvgCache::get(path_id, color))}/>
                         </Button>
+                        if !break_alliance {
+                            <Button
+                                onclick={on_alliance_factory(false, Some(TIMED_ALLIANCE_MINUTES))}
+                                title={t.request_timed_alliance_hint(TIMED_ALLIANCE_MINUTES)}
+                                style={format!("background-color: {};", color.background_color_css())}
+                            >
+                                {format!("{TIMED_ALLIANCE_MINUTES}m")}
+                            </Button>
+                        }
                         <p style="margin: 0;">{enemy_player_alias.to_string()}</p>
                     </div>
                 }
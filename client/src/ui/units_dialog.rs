@@ -79,12 +79,8 @@ pub fn units_dialog(props: &UnitsDialogProps) -> Html {
             }
         }
 
-        let surface = unit
-            .damage(Field::Surface, Field::Surface)
-            .max(unit.damage(Field::Air, Field::Surface));
-        let air = unit
-            .damage(Field::Surface, Field::Air)
-            .max(unit.damage(Field::Air, Field::Air));
+        let surface = unit.max_damage(Field::Surface).max(unit.parked_damage());
+        let air = unit.max_damage(Field::Air);
         if surface == air {
             format!("Does {} damage.", format_damage(surface))
         } else {
@@ -44,7 +44,7 @@ impl Visible {
             .iter_towers()
             .filter(|(_, t)| all_visible || t.player_id == Some(me));
 
-        let mut min = U16Vec2::splat(WorldChunks::SIZE as u16 - 1);
+        let mut min = U16Vec2::splat(WorldChunks::size() as u16 - 1);
         let mut max = U16Vec2::ZERO;
 
         for (id, _) in iter.clone() {
@@ -18,7 +18,7 @@ pub struct Alerts {
     /// Zombies are attacking this tower.
     pub zombies: Option<TowerId>,
     /// Packed bit flags. TODO don't gamma.
-    flags: u8,
+    flags: u16,
 }
 
 impl Alerts {
@@ -28,25 +28,41 @@ impl Alerts {
         self.full = None;
         self.overflowing = None;
         self.zombies = None;
-        self.set_flags(self.flags() - (AlertFlag::RulerUnderAttack | AlertFlag::RulerNotSafe));
+        self.set_flags(
+            self.flags()
+                - (AlertFlag::RulerUnderAttack
+                    | AlertFlag::RulerNotSafe
+                    | AlertFlag::MadeAlliance
+                    | AlertFlag::BrokeAlliance
+                    | AlertFlag::AllianceRequested),
+        );
     }
 
-    pub fn flags(&self) -> FlagSet<AlertFlag> {
+    pub fn flags(&self) -> AlertFlags {
         FlagSet::new_truncated(self.flags)
     }
 
-    pub fn set_flags(&mut self, flags: FlagSet<AlertFlag>) {
+    pub fn set_flags(&mut self, flags: AlertFlags) {
         self.flags = flags.bits();
     }
 }
 
+/// A set of [`AlertFlag`]s.
+pub type AlertFlags = FlagSet<AlertFlag>;
+
 flags! {
-    pub enum AlertFlag: u8 {
+    pub enum AlertFlag: u16 {
         RulerNotSafe,
         RulerUnderAttack,
         DeployedAnyForce,
         UpgradedAnyTower,
         SetAnySupplyLine,
         UnsetAnySupplyLine,
+        /// An alliance was formed in the last second.
+        MadeAlliance,
+        /// An alliance was broken (or expired) in the last second.
+        BrokeAlliance,
+        /// Another player requested an alliance in the last second.
+        AllianceRequested,
     }
 }
@@ -0,0 +1,54 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use core_protocol::prelude::*;
+use core_protocol::PlayerId;
+use diff::Diff;
+
+/// Another player asking to form an alliance.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllianceRequest {
+    pub player_id: PlayerId,
+    /// If [`Some`], the alliance expires after this many minutes.
+    pub minutes: Option<u8>,
+}
+
+impl AllianceRequest {
+    /// Longest timed alliance.
+    pub const MAX_MINUTES: u8 = 60;
+}
+
+/// Alliance requests that can be accepted or declined. Wraps [`Vec`] to implement [`Diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllianceRequests(pub Vec<AllianceRequest>);
+
+// Requests rarely change, so send all of them when they do.
+#[derive(Debug, Serialize, Deserialize)]
+#[doc(hidden)]
+pub enum AllianceRequestsDiff {
+    Some(Vec<AllianceRequest>),
+    NoChange,
+}
+
+impl Diff for AllianceRequests {
+    type Repr = AllianceRequestsDiff;
+
+    fn diff(&self, other: &Self) -> Self::Repr {
+        if self == other {
+            Self::Repr::NoChange
+        } else {
+            Self::Repr::Some(other.0.clone())
+        }
+    }
+
+    fn apply(&mut self, diff: &Self::Repr) {
+        match diff {
+            Self::Repr::Some(new) => self.0 = new.clone(),
+            Self::Repr::NoChange => (),
+        }
+    }
+
+    fn identity() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,566 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::field::{Field, FieldArray};
+use crate::ticks::Ticks;
+use crate::tower::{TowerArray, TowerType};
+use crate::unit::{Speed, Unit, UnitArray};
+use core_protocol::prelude::*;
+use std::collections::BTreeMap;
+use std::ptr::null_mut;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::LazyLock;
+
+/// Tower and unit statistics. Clients must simulate with the same [`Balance`] as the server, so
+/// the server sends it along with the first [`Update`][`crate::protocol::Update`].
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Balance {
+    towers: TowerArray<TowerBalance>,
+    units: UnitArray<UnitBalance>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+pub struct TowerBalance {
+    /// Doesn't count ruler boost.
+    pub capacity: UnitArray<u8>,
+    pub generate: UnitArray<Option<Ticks>>,
+    pub prerequisites: TowerArray<u8>,
+    pub delay: Ticks,
+    pub sensor_radius: u16,
+    pub score_weight: u32,
+    pub spawnable: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct UnitBalance {
+    /// Damage matrix row, indexed by the enemy unit being fought and then by the field the fight
+    /// is in (see [`Unit::damage`]).
+    pub damage: UnitArray<FieldArray<u8>>,
+    /// Damage while on the surface in a tower, despite being in the air in forces (see
+    /// [`Unit::parked_damage`]).
+    pub parked_damage: u8,
+    pub max_overflow: u8,
+    /// Speed in a force (shields are always immobile in towers other than projectors).
+    pub speed: Speed,
+}
+
+static BUILT_IN: LazyLock<Balance> = LazyLock::new(Balance::default);
+/// Null means [`BUILT_IN`]. Otherwise, points to a leaked [`Balance`].
+static CURRENT: AtomicPtr<Balance> = AtomicPtr::new(null_mut());
+
+#[cfg(test)]
+thread_local! {
+    /// Takes precedence over [`CURRENT`] on this thread, so tests running in parallel can use
+    /// different [`Balance`]s. See [`Balance::with`].
+    static OVERRIDE: std::cell::Cell<Option<&'static Balance>> = std::cell::Cell::new(None);
+}
+
+impl Balance {
+    /// Version of [`BalanceFile`]s that can be loaded.
+    pub const VERSION: u16 = 2;
+
+    /// Gets the [`Balance`] in effect, which is the built-in one unless [`Self::set`] was called.
+    #[inline]
+    pub fn get() -> &'static Self {
+        #[cfg(test)]
+        if let Some(balance) = OVERRIDE.with(std::cell::Cell::get) {
+            return balance;
+        }
+        let current = CURRENT.load(Ordering::Acquire);
+        if current.is_null() {
+            &BUILT_IN
+        } else {
+            // Safety: Only ever points to leaked boxes.
+            unsafe { &*current }
+        }
+    }
+
+    /// Sets the [`Balance`] in effect for the whole process. The previous [`Balance`] is leaked,
+    /// since references to it may still exist, so only call this rarely (e.g. on startup or when
+    /// joining a server).
+    pub fn set(balance: Self) {
+        if Self::get() != &balance {
+            CURRENT.store(Box::leak(Box::new(balance)), Ordering::Release);
+        }
+    }
+
+    /// Calls `f` with `balance` in effect on the current thread only. Only for testing.
+    #[cfg(test)]
+    pub(crate) fn with<R>(balance: Self, f: impl FnOnce() -> R) -> R {
+        let previous = OVERRIDE.with(|o| o.replace(Some(Box::leak(Box::new(balance)))));
+        let ret = f();
+        OVERRIDE.with(|o| o.set(previous));
+        ret
+    }
+
+    pub fn tower(&self, tower_type: TowerType) -> &TowerBalance {
+        &self.towers[tower_type]
+    }
+
+    pub fn unit(&self, unit: Unit) -> &UnitBalance {
+        &self.units[unit]
+    }
+
+    /// Formats every unit's damage against every other unit, one line per unit and field, in a
+    /// stable format suitable for diffing. Infinite damage is written as `inf`.
+    pub fn damage_table(&self) -> String {
+        let mut table = String::new();
+        table.push_str(&format!("{:<18}", "unit/field"));
+        for target in Unit::iter() {
+            table.push_str(&format!("{target:>10}"));
+        }
+        table.push_str(&format!("{:>10}\n", "parked"));
+
+        let format_damage = |damage: u8| {
+            if damage == Unit::INFINITE_DAMAGE {
+                String::from("inf")
+            } else {
+                damage.to_string()
+            }
+        };
+        for (unit, unit_balance) in self.units.iter() {
+            for field in Field::iter() {
+                table.push_str(&format!("{:<18}", format!("{unit}/{field}")));
+                for target in Unit::iter() {
+                    let damage = unit_balance.damage[target][field];
+                    table.push_str(&format!("{:>10}", format_damage(damage)));
+                }
+                let parked = format_damage(unit_balance.parked_damage);
+                table.push_str(&format!("{parked:>10}\n"));
+            }
+        }
+        table
+    }
+
+    /// Checks the invariants that the simulation relies on.
+    fn validate(&self) -> Result<(), String> {
+        for (unit, unit_balance) in self.units.iter() {
+            let damages = unit_balance
+                .damage
+                .iter()
+                .flat_map(|(_, damages)| damages.iter().map(|(_, &damage)| damage));
+            for damage in damages.chain(std::iter::once(unit_balance.parked_damage)) {
+                // See [`Unit::damage`].
+                if damage != Unit::INFINITE_DAMAGE && !(1..=30).contains(&damage) {
+                    return Err(format!(
+                        "{unit} damage must be in 1..=30 or {} (infinite)",
+                        Unit::INFINITE_DAMAGE
+                    ));
+                }
+            }
+            if unit_balance.speed == Speed::Immobile {
+                return Err(format!("{unit} must be mobile"));
+            }
+        }
+
+        for (tower_type, tower_balance) in self.towers.iter() {
+            if tower_balance.capacity[Unit::Ruler] != 1 {
+                return Err(format!("{tower_type} must have a ruler capacity of 1"));
+            }
+            if tower_balance.generate[Unit::Ruler].is_some() {
+                return Err(format!("{tower_type} cannot generate rulers"));
+            }
+        }
+
+        // [`TowerType::level`] recurses through prerequisites and downgrades.
+        fn visit(
+            balance: &Balance,
+            tower_type: TowerType,
+            state: &mut TowerArray<u8>,
+        ) -> Result<(), String> {
+            const VISITING: u8 = 1;
+            const VISITED: u8 = 2;
+            match state[tower_type] {
+                VISITING => return Err(format!("{tower_type} is its own prerequisite")),
+                VISITED => return Ok(()),
+                _ => {}
+            }
+            state[tower_type] = VISITING;
+            for (prerequisite, &count) in balance.towers[tower_type].prerequisites.iter() {
+                if count != 0 {
+                    visit(balance, prerequisite, state)?;
+                }
+            }
+            if let Some(downgrade) = tower_type.downgrade() {
+                visit(balance, downgrade, state)?;
+            }
+            state[tower_type] = VISITED;
+            Ok(())
+        }
+
+        let mut state = TowerArray::default();
+        for tower_type in TowerType::iter() {
+            visit(self, tower_type, &mut state)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Balance {
+    /// The built-in [`Balance`] (from the attributes on [`TowerType`] and from [`Unit`]).
+    fn default() -> Self {
+        Self {
+            towers: TowerArray::from_fn(|tower_type| TowerBalance {
+                capacity: UnitArray::from_fn(|unit| {
+                    tower_type.default_raw_unit_capacity(unit) as u8
+                }),
+                generate: UnitArray::from_fn(|unit| tower_type.default_unit_generation(unit)),
+                prerequisites: TowerArray::from_fn(|other| tower_type.default_prerequisite(other)),
+                delay: tower_type.default_delay(),
+                sensor_radius: tower_type.default_sensor_radius(),
+                score_weight: tower_type.default_score_weight(),
+                spawnable: tower_type.default_is_spawnable(),
+            }),
+            units: UnitArray::from_fn(|unit| UnitBalance {
+                damage: UnitArray::from_fn(|_| {
+                    FieldArray::from_fn(|field| {
+                        unit.default_damage(unit.field(false, true, false), field)
+                    })
+                }),
+                parked_damage: unit.default_damage(Field::Surface, Field::Surface),
+                max_overflow: unit.default_max_overflow() as u8,
+                speed: unit.default_speed(),
+            }),
+        }
+    }
+}
+
+/// Overrides of the built-in [`Balance`] (e.g. loaded from TOML). Everything that is omitted keeps
+/// its built-in value. Towers, units, and fields are referred to by name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceFile {
+    /// Must equal [`Balance::VERSION`].
+    pub version: u16,
+    pub towers: BTreeMap<String, TowerBalanceFile>,
+    pub units: BTreeMap<String, UnitBalanceFile>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TowerBalanceFile {
+    /// Unit to capacity.
+    pub capacity: BTreeMap<String, u8>,
+    /// Unit to seconds per generation, where 0 disables generation.
+    pub generate: BTreeMap<String, u16>,
+    /// Tower to count.
+    pub prerequisites: BTreeMap<String, u8>,
+    /// In seconds.
+    pub delay: Option<u16>,
+    pub sensor_radius: Option<u16>,
+    pub score_weight: Option<u32>,
+    pub spawnable: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitBalanceFile {
+    /// Field to damage against every unit.
+    pub damage: BTreeMap<String, u8>,
+    /// Enemy unit to field to damage, overriding [`Self::damage`].
+    pub damage_against: BTreeMap<String, BTreeMap<String, u8>>,
+    pub parked_damage: Option<u8>,
+    pub max_overflow: Option<u8>,
+    pub speed: Option<Speed>,
+}
+
+impl TryFrom<BalanceFile> for Balance {
+    type Error = String;
+
+    fn try_from(file: BalanceFile) -> Result<Self, Self::Error> {
+        if file.version != Self::VERSION {
+            return Err(format!(
+                "balance version {} is unsupported (expected {})",
+                file.version,
+                Self::VERSION
+            ));
+        }
+
+        fn parse<T: FromStr>(kind: &str, name: &str) -> Result<T, String> {
+            name.parse().map_err(|_| format!("unknown {kind} {name:?}"))
+        }
+
+        fn secs(secs: u16) -> Result<Ticks, String> {
+            secs.checked_mul(Ticks::FREQUENCY_HZ.0)
+                .map(Ticks::from_repr)
+                .ok_or_else(|| format!("{secs}s is too long"))
+        }
+
+        let mut balance = Self::default();
+
+        for (name, tower_file) in file.towers {
+            let tower = &mut balance.towers[parse::<TowerType>("tower", &name)?];
+            for (unit, capacity) in tower_file.capacity {
+                tower.capacity[parse::<Unit>("unit", &unit)?] = capacity;
+            }
+            for (unit, period) in tower_file.generate {
+                tower.generate[parse::<Unit>("unit", &unit)?] =
+                    (period != 0).then(|| secs(period)).transpose()?;
+            }
+            for (prerequisite, count) in tower_file.prerequisites {
+                tower.prerequisites[parse::<TowerType>("tower", &prerequisite)?] = count;
+            }
+            if let Some(delay) = tower_file.delay {
+                tower.delay = secs(delay)?;
+            }
+            if let Some(sensor_radius) = tower_file.sensor_radius {
+                tower.sensor_radius = sensor_radius;
+            }
+            if let Some(score_weight) = tower_file.score_weight {
+                tower.score_weight = score_weight;
+            }
+            if let Some(spawnable) = tower_file.spawnable {
+                tower.spawnable = spawnable;
+            }
+        }
+
+        for (name, unit_file) in file.units {
+            let unit = &mut balance.units[parse::<Unit>("unit", &name)?];
+            for (field, damage) in unit_file.damage {
+                let field = parse::<Field>("field", &field)?;
+                for (_, damages) in unit.damage.iter_mut() {
+                    damages[field] = damage;
+                }
+            }
+            for (target, damages) in unit_file.damage_against {
+                let target = parse::<Unit>("unit", &target)?;
+                for (field, damage) in damages {
+                    unit.damage[target][parse::<Field>("field", &field)?] = damage;
+                }
+            }
+            if let Some(parked_damage) = unit_file.parked_damage {
+                unit.parked_damage = parked_damage;
+            }
+            if let Some(max_overflow) = unit_file.max_overflow {
+                unit.max_overflow = max_overflow;
+            }
+            if let Some(speed) = unit_file.speed {
+                unit.speed = speed;
+            }
+        }
+
+        balance.validate()?;
+        Ok(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::balance::{Balance, BalanceFile, TowerBalanceFile, UnitBalanceFile};
+    use crate::field::Field;
+    use crate::ticks::Ticks;
+    use crate::tower::TowerType;
+    use crate::unit::{Speed, Unit};
+
+    #[test]
+    fn built_in() {
+        let balance = Balance::default();
+        balance.validate().unwrap();
+
+        let airfield = balance.tower(TowerType::Airfield);
+        assert_eq!(airfield.capacity[Unit::Bomber], 4);
+        assert_eq!(airfield.capacity[Unit::Ruler], 1);
+        assert_eq!(
+            airfield.generate[Unit::Bomber],
+            Some(Ticks::from_whole_secs(30))
+        );
+        assert_eq!(airfield.prerequisites[TowerType::Factory], 2);
+        assert_eq!(airfield.delay, Ticks::from_whole_secs(20));
+        assert_eq!(airfield.sensor_radius, 12);
+        assert!(airfield.spawnable);
+        assert_eq!(balance.tower(TowerType::Icbm).sensor_radius, 48);
+        assert_eq!(balance.tower(TowerType::Metropolis).score_weight, 12);
+
+        let bomber = balance.unit(Unit::Bomber);
+        assert_eq!(bomber.damage[Unit::Soldier][Field::Surface], 5);
+        assert_eq!(bomber.damage[Unit::Fighter][Field::Air], 1);
+        assert_eq!(bomber.parked_damage, 1);
+        assert_eq!(bomber.speed, Speed::Fast);
+        assert_eq!(
+            balance.unit(Unit::Nuke).damage[Unit::Shield][Field::Surface],
+            Unit::INFINITE_DAMAGE
+        );
+        assert_eq!(
+            balance.unit(Unit::Nuke).parked_damage,
+            Unit::INFINITE_DAMAGE
+        );
+        assert_eq!(balance.unit(Unit::Fighter).parked_damage, 1);
+        assert_eq!(balance.unit(Unit::Tank).parked_damage, 3);
+    }
+
+    #[test]
+    fn damage_matrix() {
+        let balance = Balance::default();
+        // Built-in damage doesn't depend on the enemy unit.
+        for (unit, unit_balance) in balance.units.iter() {
+            for field in Field::iter() {
+                let damages: Vec<_> = unit_balance
+                    .damage
+                    .iter()
+                    .map(|(_, damages)| damages[field])
+                    .collect();
+                assert!(
+                    damages.windows(2).all(|w| w[0] == w[1]),
+                    "{unit} in {field}"
+                );
+            }
+        }
+
+        let table = balance.damage_table();
+        assert_eq!(
+            table.lines().count(),
+            1 + Unit::iter().count() * Field::iter().count()
+        );
+        let bomber = table
+            .lines()
+            .find(|line| line.starts_with("Bomber/Surface"))
+            .unwrap();
+        assert_eq!(bomber.split_whitespace().nth(1), Some("5"));
+        let nuke = table
+            .lines()
+            .find(|line| line.starts_with("Nuke/Air"))
+            .unwrap();
+        assert_eq!(nuke.split_whitespace().last(), Some("inf"));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut file = BalanceFile {
+            version: Balance::VERSION,
+            ..Default::default()
+        };
+        let mut barracks = TowerBalanceFile::default();
+        barracks.capacity.insert("Soldier".into(), 20);
+        barracks.generate.insert("Soldier".into(), 0);
+        barracks.delay = Some(5);
+        file.towers.insert("Barracks".into(), barracks);
+        let mut tank = UnitBalanceFile::default();
+        tank.damage.insert("Air".into(), 2);
+        tank.speed = Some(Speed::Normal);
+        file.units.insert("Tank".into(), tank);
+        let mut chopper = UnitBalanceFile::default();
+        for aircraft in ["Fighter", "Chopper", "Bomber"] {
+            chopper
+                .damage_against
+                .entry(aircraft.into())
+                .or_default()
+                .insert("Air".into(), 2);
+        }
+        chopper.parked_damage = Some(2);
+        file.units.insert("Chopper".into(), chopper);
+
+        let balance = Balance::try_from(file).unwrap();
+        let built_in = Balance::default();
+
+        let barracks = balance.tower(TowerType::Barracks);
+        assert_eq!(barracks.capacity[Unit::Soldier], 20);
+        assert_eq!(barracks.capacity[Unit::Tank], 2);
+        assert_eq!(barracks.generate[Unit::Soldier], None);
+        assert_eq!(barracks.delay, Ticks::from_whole_secs(5));
+        assert_eq!(
+            balance.tower(TowerType::City),
+            built_in.tower(TowerType::City)
+        );
+
+        let tank = balance.unit(Unit::Tank);
+        assert_eq!(tank.damage[Unit::Fighter][Field::Air], 2);
+        assert_eq!(tank.damage[Unit::Soldier][Field::Surface], 3);
+        assert_eq!(tank.speed, Speed::Normal);
+
+        let chopper = balance.unit(Unit::Chopper);
+        assert_eq!(chopper.damage[Unit::Bomber][Field::Air], 2);
+        assert_eq!(chopper.damage[Unit::Shield][Field::Air], 3);
+        assert_eq!(chopper.damage[Unit::Soldier][Field::Surface], 3);
+        assert_eq!(chopper.parked_damage, 2);
+    }
+
+    #[test]
+    fn invalid() {
+        let valid = || BalanceFile {
+            version: Balance::VERSION,
+            ..Default::default()
+        };
+        assert!(Balance::try_from(valid()).is_ok());
+
+        let mut file = valid();
+        file.version += 1;
+        assert!(Balance::try_from(file).is_err());
+
+        let mut file = valid();
+        file.towers.insert("Castle".into(), Default::default());
+        assert!(Balance::try_from(file).is_err());
+
+        // Capitol requires Headquarters which upgrades from Village.
+        let mut file = valid();
+        let mut village = TowerBalanceFile::default();
+        village.prerequisites.insert("Capitol".into(), 1);
+        file.towers.insert("Village".into(), village);
+        assert!(Balance::try_from(file).is_err());
+
+        let mut file = valid();
+        let mut soldier = UnitBalanceFile::default();
+        soldier.speed = Some(Speed::Immobile);
+        file.units.insert("Soldier".into(), soldier);
+        assert!(Balance::try_from(file).is_err());
+
+        let mut file = valid();
+        let mut shell = UnitBalanceFile::default();
+        shell
+            .damage_against
+            .entry("Soldier".into())
+            .or_default()
+            .insert("Surface".into(), 0);
+        file.units.insert("Shell".into(), shell);
+        assert!(Balance::try_from(file).is_err());
+
+        let mut file = valid();
+        let mut tank = UnitBalanceFile::default();
+        tank.parked_damage = Some(Unit::INFINITE_DAMAGE + 1);
+        file.units.insert("Tank".into(), tank);
+        assert!(Balance::try_from(file).is_err());
+    }
+
+    #[test]
+    fn toml() {
+        let file: BalanceFile = toml::from_str(
+            r#"
+            version = 2
+
+            [towers.Barracks]
+            delay = 5
+            capacity = { Soldier = 20 }
+            generate = { Soldier = 0 }
+
+            [units.Chopper]
+            parked_damage = 2
+            speed = "Normal"
+
+            [units.Chopper.damage_against.Fighter]
+            Air = 2
+
+            [units.Shell]
+            damage = { Surface = 31 }
+            "#,
+        )
+        .unwrap();
+        let balance = Balance::try_from(file).unwrap();
+
+        let barracks = balance.tower(TowerType::Barracks);
+        assert_eq!(barracks.delay, Ticks::from_whole_secs(5));
+        assert_eq!(barracks.capacity[Unit::Soldier], 20);
+        assert_eq!(barracks.generate[Unit::Soldier], None);
+
+        let chopper = balance.unit(Unit::Chopper);
+        assert_eq!(chopper.parked_damage, 2);
+        assert_eq!(chopper.speed, Speed::Normal);
+        assert_eq!(chopper.damage[Unit::Fighter][Field::Air], 2);
+        assert_eq!(
+            balance.unit(Unit::Shell).damage[Unit::Soldier][Field::Surface],
+            Unit::INFINITE_DAMAGE
+        );
+
+        let unknown = "version = 2\n[units.Soldier]\nhealth = 5\n";
+        assert!(toml::from_str::<BalanceFile>(unknown).is_err());
+    }
+}
@@ -2,14 +2,16 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::combatants::{CombatInfo, CombatSide, Combatants};
+use crate::force::Deployment;
 use crate::info::{GainedTowerReason, Info, InfoEvent, LostRulerReason, LostTowerReason, OnInfo};
 use crate::player::{Player, PlayerId};
+use crate::research::{Research, ResearchArray};
 use crate::shrink_vec;
 use crate::singleton::Singleton;
 use crate::ticks::Ticks;
 use crate::tower::Tower;
 use crate::tower::TowerId;
-use crate::unit::Unit;
+use crate::unit::{Unit, UnitArray};
 use common_util::ticks::TicksRepr;
 use core_protocol::prelude::*;
 use std::num::NonZeroU8;
@@ -133,17 +135,26 @@ impl Chunk {
         let relationship = |a: Option<PlayerId>, b: Option<PlayerId>| -> Relationship {
             if a == b {
                 Relationship::Comrade
-            } else if a
-                .zip(b)
-                .map(|(a, b)| players(a).allies.contains(&b) && players(b).allies.contains(&a))
-                .unwrap_or(false)
-            {
-                Relationship::Ally
+            } else if let Some((a, b)) = a.zip(b) {
+                let (a_player, b_player) = (players(a), players(b));
+                if a_player.is_teammate(b_player) {
+                    Relationship::Teammate
+                } else if a_player.allies.contains(&b) && b_player.allies.contains(&a) {
+                    Relationship::Ally
+                } else {
+                    Relationship::Enemy
+                }
             } else {
                 Relationship::Enemy
             }
         };
 
+        let no_research = ResearchArray::default();
+        let research = |player_id: Option<PlayerId>| {
+            player_id.map_or(&no_research, |player_id| &players(player_id).research)
+        };
+        let speed_bonus = |player_id: Option<PlayerId>| Research::speed_bonus(research(player_id));
+
         // TODO better random tick offset (maybe per tower).
         let tick_offset = Ticks::from_repr(u16::from_le_bytes([chunk_id.x, chunk_id.y]));
         let tick = singleton.tick.wrapping_add(tick_offset);
@@ -153,22 +164,42 @@ impl Chunk {
             // Un-owned towers must not have rulers.
             debug_assert!(tower.player_id.is_some() || !tower.units.has_ruler());
 
+            // Remove supply lines that were halted.
+            tower.supply_lines.retain(|supply_line| !supply_line.halted);
+
             let mut deploy = false;
             if tick.every(Ticks::from_whole_secs(if tower.player_id.is_some() {
                 30
             } else {
                 10
             })) {
-                deploy |= tower.diminish_units_if_dead_or_overflow() != 0 && tower.active();
+                deploy |= tower.diminish_units_if_dead_or_overflow(research(tower.player_id)) != 0
+                    && tower.active();
             }
 
             // Either delay or generate/decay, but not both.
             if let Some(delay) = tower.delay {
                 tower.delay = NonZeroU8::new(delay.get() - 1);
             } else if tower.player_id.is_some() {
+                let research = research(tower.player_id);
                 for unit in Unit::iter() {
                     if let Some(period) = tower.tower_type.unit_generation(unit) {
                         if tick.every(period) {
+                            let capacity = tower.units.capacity(unit, Some(tower.tower_type));
+                            let available = tower.units.available(unit);
+                            if available >= capacity
+                                && available
+                                    < tower.units.researched_capacity(
+                                        unit,
+                                        Some(tower.tower_type),
+                                        research,
+                                    )
+                            {
+                                // Research allows overflowing (within the unit's max overflow).
+                                tower.units.add_to_tower(unit, 1, tower.tower_type, true);
+                                continue;
+                            }
+
                             // Add 2 but subtract up to 1 of the added ones to see if there is room.
                             let a = tower.units.add_to_tower(unit, 2, tower.tower_type, false);
                             tower.units.subtract(unit, a.saturating_sub(1));
@@ -182,13 +213,22 @@ impl Chunk {
             }
 
             if deploy && !tower.units.has_ruler() {
-                if let Some(path) = tower.supply_line.as_ref() {
+                let ranged_distance = tower.tower_type.ranged_distance();
+                let index = tower.next_supply_line(|supply_line| {
                     // Don't send soldiers along nuke supply line.
-                    if tower.force_units().max_edge_distance() >= tower.tower_type.ranged_distance()
+                    tower
+                        .deployment_units(&supply_line.deployment(tower))
+                        .is_ok_and(|units| units.max_edge_distance() >= ranged_distance)
+                });
+                if let Some(index) = index {
+                    let supply_line = &tower.supply_lines[index];
+                    let path = supply_line.path.clone();
+                    let deployment = supply_line.deployment(tower);
+                    tower.supply_turn = tower.supply_turn.wrapping_add(1);
+                    for AddressedChunkEvent { dst, event } in
+                        tower.deploy_force(path, Vec::new(), &deployment)
                     {
-                        for AddressedChunkEvent { dst, event } in tower.deploy_force(path.clone()) {
-                            on_event(dst, event); // TODO make on_event take AddressedChunkEvent.
-                        }
+                        on_event(dst, event); // TODO make on_event take AddressedChunkEvent.
                     }
                 }
             }
@@ -206,10 +246,12 @@ impl Chunk {
                     }
 
                     let inbound_progress_required = inbound_force.progress_required() as u16;
+                    let inbound_progress_per_tick =
+                        inbound_force.progress_per_tick(speed_bonus(inbound_force.player_id));
                     let inbound_path_progress = inbound_progress_required.saturating_sub(
                         inbound_force
                             .path_progress
-                            .saturating_add(inbound_force.progress_per_tick())
+                            .saturating_add(inbound_progress_per_tick)
                             as u16,
                     );
                     let inbound_next_path_progress = inbound_progress_required
@@ -251,9 +293,11 @@ impl Chunk {
 
                         let effective_outbound_path_progress =
                             outbound_force.path_progress as u16 * inbound_progress_required;
+                        let outbound_progress_per_tick =
+                            outbound_force.progress_per_tick(speed_bonus(outbound_force.player_id));
                         let effective_outbound_next_path_progress = outbound_force
                             .path_progress
-                            .saturating_add(outbound_force.progress_per_tick())
+                            .saturating_add(outbound_progress_per_tick)
                             as u16
                             * inbound_progress_required;
 
@@ -273,7 +317,8 @@ impl Chunk {
                             && relationship(inbound_force.player_id, outbound_force.player_id)
                                 .is_unfriendly(false)
                         {
-                            let position = inbound_force.interpolated_position(0.0);
+                            let position = inbound_force
+                                .interpolated_position(0.0, speed_bonus(inbound_force.player_id));
 
                             // Only one version of the battle will generate events (guaranteeing
                             // consistency even if the fighting isn't commutative).
@@ -312,7 +357,10 @@ impl Chunk {
             let position = tower_id.as_vec2();
 
             // Force vs. tower.
-            for mut force in tower.inbound_forces.drain_filter(|f| f.tick(tower_id)) {
+            for mut force in tower
+                .inbound_forces
+                .drain_filter(|f| f.tick(tower_id, speed_bonus(f.player_id)))
+            {
                 let tower_player_id = tower.player_id;
                 if tower_player_id.is_some() || !tower.units.is_empty() {
                     let force_player_id = force.player_id;
@@ -394,7 +442,7 @@ impl Chunk {
                                 Tower::set_player_id_inner(
                                     &mut tower.player_id,
                                     &tower.units,
-                                    &mut tower.supply_line,
+                                    &mut tower.supply_lines,
                                     new_player_id,
                                 );
                             }
@@ -427,7 +475,7 @@ impl Chunk {
                     Tower::set_player_id_inner(
                         &mut tower.player_id,
                         &tower.units,
-                        &mut tower.supply_line,
+                        &mut tower.supply_lines,
                         Some(force_player_id),
                     );
                     tower
@@ -436,6 +484,8 @@ impl Chunk {
                 }
 
                 let relationship = relationship(force.player_id, tower.player_id);
+                let supply_index =
+                    tower.next_supply_line(|supply_line| supply_line.carries(&force.units));
 
                 if force.units.is_empty() {
                     // Drop.
@@ -447,14 +497,22 @@ impl Chunk {
                             info: Info::LostForce(player_id),
                         });
                     }
-                } else if matches!(relationship, Relationship::Ally | Relationship::Comrade)
-                    && force.try_move_on(
-                        tower.tower_type,
-                        &mut tower.units,
-                        tower.player_id.filter(|_| relationship.is_ally()),
-                        tower.supply_line.as_ref(),
-                    )
-                {
+                } else if matches!(
+                    relationship,
+                    Relationship::Ally | Relationship::Teammate | Relationship::Comrade
+                ) && force.try_move_on(
+                    tower.tower_type,
+                    &mut tower.units,
+                    tower.player_id.filter(|_| relationship.is_ally()),
+                    supply_index.map(|index| &tower.supply_lines[index].path),
+                ) {
+                    if supply_index
+                        .is_some_and(|index| force.path() == &tower.supply_lines[index].path)
+                    {
+                        // Took the supply line, so the next force takes the next one.
+                        tower.supply_turn = tower.supply_turn.wrapping_add(1);
+                    }
+
                     if force.units.is_many()
                         && tower
                             .outbound_forces
@@ -478,7 +536,25 @@ impl Chunk {
                         on_event(chunk_id, ChunkEvent::AddInboundForce { tower_id, force });
                     }
                 } else if relationship.is_friendly(force.units.has_ruler()) {
-                    // Force arrived.
+                    // Force arrived. Neutral towers were captured above and forces follow their
+                    // legs through allies' towers, so broken waypoints stop at comrade towers.
+                    if relationship.is_comrade()
+                        && let Some(player_id) = force.player_id
+                        && let Some(leg) = force.legs().last()
+                    {
+                        context.on_info(InfoEvent {
+                            position: tower_id.as_vec2(),
+                            info: Info::WaypointsBroken {
+                                tower_id,
+                                player_id,
+                                destination: leg.destination(),
+                                units: UnitArray::from_fn(|unit| {
+                                    force.units.available(unit) as u8
+                                }),
+                            },
+                        });
+                    }
+
                     // Only real players are eligible for overflowing forces.
                     tower.units.add_units_to_tower(
                         force.units,
@@ -491,7 +567,7 @@ impl Chunk {
                             position,
                             info: Info::LostRuler {
                                 player_id,
-                                reason: LostRulerReason::KilledBy(None, Unit::Shield),
+                                reason: LostRulerReason::Stranded,
                             },
                         })
                     } else {
@@ -502,7 +578,7 @@ impl Chunk {
 
             tower
                 .outbound_forces
-                .retain_mut(|force| !force.raw_tick(None));
+                .retain_mut(|force| !force.raw_tick(None, speed_bonus(force.player_id)));
 
             shrink_vec(&mut tower.inbound_forces);
             shrink_vec(&mut tower.outbound_forces);
@@ -516,12 +592,14 @@ enum Relationship {
     Comrade,
     /// Allied player.
     Ally,
+    /// Player on the same team. Unlike an [`Relationship::Ally`], forces pass through their
+    /// towers instead of being given to them.
+    Teammate,
     /// Enemy player/zombie.
     Enemy,
 }
 
 impl Relationship {
-    #[allow(unused)]
     fn is_comrade(&self) -> bool {
         matches!(self, Self::Comrade)
     }
@@ -533,7 +611,7 @@ impl Relationship {
     fn is_friendly(&self, ruler_arriving_at_tower: bool) -> bool {
         match self {
             Self::Comrade => true,
-            Self::Ally => !ruler_arriving_at_tower,
+            Self::Ally | Self::Teammate => !ruler_arriving_at_tower,
             Self::Enemy => false,
         }
     }
@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::chunk::{Chunk, ChunkId, RelativeTowerId};
-use crate::force::{Force, Path};
+use crate::force::{Deployment, Force, Path};
 use crate::info::*;
+use crate::ruler::RulerAbility;
+use crate::supply_line::SupplyLine;
 use crate::tower::Tower;
-use crate::tower::TowerType;
+use crate::tower::{TowerId, TowerType};
 use crate::unit::Unit;
 use crate::units::Units;
 use crate::world::Apply;
@@ -37,11 +39,16 @@ pub trait OnChunkEvent {
 impl Tower {
     // TODO move?
     #[must_use]
-    pub fn deploy_force(&mut self, path: Path) -> [AddressedChunkEvent; 2] {
+    pub fn deploy_force(
+        &mut self,
+        path: Path,
+        legs: Vec<Path>,
+        deployment: &Deployment,
+    ) -> [AddressedChunkEvent; 2] {
         #[cfg(debug_assertions)]
         let had = self.units.clone();
 
-        let units = self.take_force_units();
+        let units = self.take_force_units(deployment);
         let player_id = self.player_id.unwrap();
         if units.is_empty() {
             #[cfg(debug_assertions)]
@@ -52,7 +59,7 @@ impl Tower {
             );
         }
 
-        self.send_force(Force::new(player_id, units, path))
+        self.send_force(Force::new(player_id, units, path).with_legs(legs))
     }
 
     #[must_use]
@@ -76,7 +83,7 @@ impl Tower {
 
 #[derive(Clone, Debug, Encode, Decode)]
 pub enum ChunkInput {
-    // Only used for debugging with chonk.
+    // Only used by the `/grant` and `/bombard` chat commands.
     AddInboundForce {
         tower_id: RelativeTowerId,
         force: Force,
@@ -86,18 +93,43 @@ pub enum ChunkInput {
     DeployForce {
         tower_id: RelativeTowerId,
         path: Path,
+        /// Paths to follow after `path`.
+        legs: Vec<Path>,
+        deployment: Deployment,
     },
     Generate {
         tower_ids: Vec<RelativeTowerId>, // TODO RelativeTowerIdSet
     },
-    SetSupplyLine {
+    /// Zombies rise from an unowned tower and attack along `path`.
+    LaunchZombies {
+        tower_id: RelativeTowerId,
+        path: Path,
+        units: Units,
+    },
+    /// The ruler arrives from another of the player's towers, at the same time as it departs
+    /// (see [`World::teleport_ruler`][`crate::world::World::teleport_ruler`]).
+    RulerArrive {
+        tower_id: RelativeTowerId,
+        player_id: PlayerId,
+        source: TowerId,
+    },
+    /// The ruler departs for another of the player's towers, without passing through anything.
+    RulerDepart { tower_id: RelativeTowerId },
+    SetSupplyLines {
         tower_id: RelativeTowerId,
-        path: Option<Path>,
+        supply_lines: Vec<SupplyLine>,
     },
+    /// Overfills the ruler's tower with shields.
+    ShieldSurge { tower_id: RelativeTowerId },
     Spawn {
         tower_id: RelativeTowerId,
         player_id: PlayerId,
     },
+    /// Gives a tower, along with its mobile units, to another player.
+    TransferTower {
+        tower_id: RelativeTowerId,
+        player_id: PlayerId,
+    },
     UpgradeTower {
         tower_id: RelativeTowerId,
         tower_type: TowerType,
@@ -119,15 +151,73 @@ impl<C: OnInfo + OnChunkEvent> Apply<ChunkInput, C> for Chunk { // Important fun
                     tower.units.clear();
                 }
             }
-            ChunkInput::DeployForce { tower_id, path } => {
-                context.on_chunk_events(self.chunk_id, self[tower_id].deploy_force(path));
+            ChunkInput::DeployForce {
+                tower_id,
+                path,
+                legs,
+                deployment,
+            } => {
+                let tower = &mut self[tower_id];
+                if tower.deployment_units(&deployment).is_ok() {
+                    let events = tower.deploy_force(path, legs, &deployment);
+                    context.on_chunk_events(self.chunk_id, events);
+                } else {
+                    debug_assert!(false, "invalid deployment {deployment:?}");
+                }
             }
             ChunkInput::Generate { tower_ids } => {
                 for tower_id in tower_ids {
                     self.insert(tower_id, Tower::new(tower_id.upgrade(self.chunk_id)));
                 }
             }
-            ChunkInput::SetSupplyLine { tower_id, path } => self[tower_id].supply_line = path,
+            ChunkInput::LaunchZombies {
+                tower_id,
+                path,
+                units,
+            } => {
+                let tower = &mut self[tower_id];
+                debug_assert_eq!(tower.player_id, None);
+                let events = tower.send_force(Force::zombie(units, path));
+                context.on_chunk_events(self.chunk_id, events);
+            }
+            ChunkInput::RulerArrive {
+                tower_id,
+                player_id,
+                source,
+            } => {
+                let destination = tower_id.upgrade(self.chunk_id);
+                let tower = &mut self[tower_id];
+                if tower.player_id != Some(player_id)
+                    || !RulerAbility::can_teleport(source, destination)
+                {
+                    debug_assert!(false, "teleporting to invalid destination");
+                    return;
+                }
+                let added = tower
+                    .units
+                    .add_to_tower(Unit::Ruler, 1, tower.tower_type, false);
+                debug_assert_eq!(added, 1);
+            }
+            ChunkInput::RulerDepart { tower_id } => {
+                let tower = &mut self[tower_id];
+                if !tower.units.has_ruler() {
+                    debug_assert!(false, "teleporting without ruler");
+                    return;
+                }
+                tower.units.subtract(Unit::Ruler, 1);
+            }
+            ChunkInput::SetSupplyLines {
+                tower_id,
+                supply_lines,
+            } => self[tower_id].supply_lines = supply_lines,
+            ChunkInput::ShieldSurge { tower_id } => {
+                let tower = &mut self[tower_id];
+                debug_assert!(tower.units.has_ruler());
+                // Overflowing shields decay like any other overflowing unit.
+                tower
+                    .units
+                    .add_to_tower(Unit::Shield, usize::MAX, tower.tower_type, true);
+            }
             ChunkInput::Spawn {
                 tower_id,
                 player_id,
@@ -170,6 +260,41 @@ impl<C: OnInfo + OnChunkEvent> Apply<ChunkInput, C> for Chunk { // Important fun
                     context.on_chunk_events(chunk_id, tower.send_force(force));
                 }
             }
+            ChunkInput::TransferTower {
+                tower_id,
+                player_id,
+            } => {
+                let chunk_id = self.chunk_id;
+                let tower = &mut self[tower_id];
+                let tower_id = tower_id.upgrade(chunk_id);
+                let Some(old_player_id) = tower.player_id else {
+                    debug_assert!(false, "transferring unowned tower");
+                    return;
+                };
+                debug_assert_ne!(old_player_id, player_id);
+                debug_assert!(!tower.units.has_ruler());
+
+                // Shields stay behind, like when a tower is captured.
+                tower.units.subtract(Unit::Shield, usize::MAX);
+                tower.set_player_id(Some(player_id));
+
+                context.on_info(InfoEvent {
+                    info: Info::LostTower {
+                        tower_id,
+                        player_id: old_player_id,
+                        reason: LostTowerReason::GivenTo(player_id),
+                    },
+                    position: tower_id.as_vec2(),
+                });
+                context.on_info(InfoEvent {
+                    info: Info::GainedTower {
+                        tower_id,
+                        player_id,
+                        reason: GainedTowerReason::GivenBy(old_player_id),
+                    },
+                    position: tower_id.as_vec2(),
+                });
+            }
             ChunkInput::UpgradeTower {
                 tower_id,
                 tower_type,
@@ -183,8 +308,8 @@ impl<C: OnInfo + OnChunkEvent> Apply<ChunkInput, C> for Chunk { // Important fun
                 // The new tower may have different unit capacities.
                 tower.reconcile_units();
 
-                if tower.supply_line.is_some() && !tower.generates_mobile_units() {
-                    tower.supply_line = None;
+                if !tower.generates_mobile_units() {
+                    tower.supply_lines.clear();
                 }
             }
         }
@@ -9,7 +9,10 @@ use core_protocol::prelude::*;
 #[derive(Clone, Copy, Debug, Encode, Decode)]
 pub enum ChunkHaltEvent {
     Force(RelativeTowerId, #[bitcode_hint(gamma)] u32),
-    SupplyLine(RelativeTowerId),
+    /// Halts the supply line at an index, because its path broke.
+    SupplyLine(RelativeTowerId, u8),
+    /// Pauses or resumes the supply line at an index, depending on how full its destination is.
+    PauseSupplyLine(RelativeTowerId, u8, bool),
 }
 
 impl Message for ChunkHaltEvent {}
@@ -20,8 +23,11 @@ impl<C: OnInfo> Apply<ChunkHaltEvent, C> for Chunk {
             ChunkHaltEvent::Force(relative_tower_id, index) => {
                 self[relative_tower_id].inbound_forces[index as usize].halt();
             }
-            ChunkHaltEvent::SupplyLine(relative_tower_id) => {
-                self[relative_tower_id].supply_line = None;
+            ChunkHaltEvent::SupplyLine(relative_tower_id, index) => {
+                self[relative_tower_id].supply_lines[index as usize].halted = true;
+            }
+            ChunkHaltEvent::PauseSupplyLine(relative_tower_id, index, paused) => {
+                self[relative_tower_id].supply_lines[index as usize].paused = paused;
             }
         }
     }
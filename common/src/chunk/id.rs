@@ -3,6 +3,7 @@
 
 use crate::chunk::Chunk;
 use crate::tower::TowerId;
+use crate::world::WorldChunks;
 use common_util::x_vec2::{U16Vec2, U8Vec2};
 use core_protocol::prelude::*;
 use std::cmp::Ordering;
@@ -50,6 +51,26 @@ impl ChunkId {
         let scaled = u16vec2 * Chunk::SIZE as u16;
         TowerId(scaled.add(U16Vec2::splat(Chunk::SIZE as u16 - 1)))
     }
+
+    /// Iterates the [`ChunkId`]s in the world that intersect a circle of `radius` towers around
+    /// `center`.
+    pub fn iter_circle(center: TowerId, radius: u16) -> impl Iterator<Item = Self> {
+        let size = Chunk::SIZE as u16;
+        let max = WorldChunks::size_chunks() as u16 - 1;
+        let start = |v: u16| v.saturating_sub(radius) / size;
+        let end = |v: u16| (v.saturating_add(radius) / size).min(max);
+        let (xs, ys) = (start(center.x)..=end(center.x), start(center.y)..=end(center.y));
+        let radius_squared = radius as u32 * radius as u32;
+
+        ys.flat_map(move |y| xs.clone().map(move |x| Self::new(x as u8, y as u8)))
+            .filter(move |chunk_id| {
+                // Distance to the closest tower in the chunk.
+                let (bottom_left, top_right) = (chunk_id.bottom_left(), chunk_id.top_right());
+                let dx = center.x.clamp(bottom_left.x, top_right.x).abs_diff(center.x) as u32;
+                let dy = center.y.clamp(bottom_left.y, top_right.y).abs_diff(center.y) as u32;
+                dx * dx + dy * dy <= radius_squared
+            })
+    }
 }
 
 impl Deref for ChunkId {
@@ -108,6 +129,7 @@ impl RelativeTowerId {
 
 #[cfg(test)]
 mod tests {
+    use crate::chunk::ChunkId;
     use crate::tower::TowerId;
 
     #[test]
@@ -116,4 +138,24 @@ mod tests {
         let (chunk_id, relative_id) = tower_id.split();
         assert_eq!(tower_id, relative_id.upgrade(chunk_id))
     }
+
+    #[test]
+    fn test_iter_circle() {
+        let chunk_ids = |x, y, radius| {
+            let mut chunk_ids: Vec<_> = ChunkId::iter_circle(TowerId::new(x, y), radius).collect();
+            chunk_ids.sort();
+            chunk_ids
+        };
+
+        assert_eq!(chunk_ids(40, 40, 5), vec![ChunkId::new(2, 2)]);
+        assert_eq!(
+            chunk_ids(47, 40, 1),
+            vec![ChunkId::new(2, 2), ChunkId::new(3, 2)]
+        );
+        // The diagonal chunk is further than the adjacent ones.
+        assert_eq!(chunk_ids(47, 47, 1).len(), 3);
+        assert_eq!(chunk_ids(47, 47, 2).len(), 4);
+        // Clamped to the world.
+        assert_eq!(chunk_ids(0, 0, 10), vec![ChunkId::new(0, 0)]);
+    }
 }
@@ -16,6 +16,9 @@ use serde::{Deserialize, Serialize};
 pub enum ChunkMaintenance {
     /// If `ChunkEvent`s are in flight, might destroy `Tower` that has incoming units.
     Destroy { tower_ids: Vec<RelativeTowerId> },
+    /// Destroys every [`Tower`][`crate::tower::Tower`] regardless of forces, so must be sent to
+    /// every chunk at once (e.g. to reset the world).
+    Clear,
     /// If `ChunkEvent`s are in flight with units of `player_id` this won't kill them.
     KillPlayer { player_id: PlayerId },
 }
@@ -31,6 +34,9 @@ impl<C: OnInfo> Apply<ChunkMaintenance, C> for Chunk {
                     debug_assert!(tower.can_destroy());
                 }
             }
+            ChunkMaintenance::Clear => {
+                self.towers.iter_mut().for_each(|tower| *tower = None);
+            }
             ChunkMaintenance::KillPlayer { player_id } => {
                 for (tower_id, tower) in self.iter_mut(self.chunk_id) {
                     if tower.player_id == Some(player_id) {
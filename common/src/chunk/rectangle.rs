@@ -63,6 +63,20 @@ impl ChunkRectangle {
             ),
         }
     }
+
+    /// Return a new rectangle around the same center that is at most `max` chunks wide and tall.
+    pub fn shrink_to(self, max: u8) -> Self {
+        if !self.is_valid() {
+            return self;
+        }
+        let shrink = |bottom: u8, top: u8| {
+            let excess = (top - bottom).saturating_sub(max.saturating_sub(1));
+            (bottom + excess / 2, top - (excess - excess / 2))
+        };
+        let (left, right) = shrink(self.bottom_left.x, self.top_right.x);
+        let (bottom, top) = shrink(self.bottom_left.y, self.top_right.y);
+        Self::new(ChunkId::new(left, bottom), ChunkId::new(right, top))
+    }
 }
 
 impl IntoIterator for ChunkRectangle {
@@ -87,10 +101,25 @@ impl From<TowerRectangle> for ChunkRectangle {
 
 #[cfg(test)]
 mod tests {
-    use crate::chunk::ChunkRectangle;
+    use crate::chunk::{ChunkId, ChunkRectangle};
 
     #[test]
     fn invalid() {
         assert!(!ChunkRectangle::invalid().is_valid());
     }
+
+    #[test]
+    fn shrink_to() {
+        let rectangle = ChunkRectangle::new(ChunkId::new(2, 3), ChunkId::new(11, 5));
+        assert_eq!(rectangle.shrink_to(10), rectangle);
+        assert_eq!(
+            rectangle.shrink_to(4),
+            ChunkRectangle::new(ChunkId::new(5, 3), ChunkId::new(8, 5))
+        );
+        assert_eq!(
+            rectangle.shrink_to(1),
+            ChunkRectangle::new(ChunkId::new(6, 4), ChunkId::new(6, 4))
+        );
+        assert!(!ChunkRectangle::invalid().shrink_to(4).is_valid());
+    }
 }
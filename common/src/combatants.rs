@@ -215,12 +215,22 @@ impl<'a> Combatants<'a> {
             })
         }
 
+        // Looks up the damage matrix. `target` is the enemy unit that is engaged first, which takes
+        // the damage along with any enemy units after it.
         let damage_against = |unit: Unit,
                               unit_field: Field,
                               enemy: &Combatants,
+                              target: Option<Unit>,
                               enemy_field: Field,
                               prev_dmg: i32| {
-            let mut unit_damage = unit.damage(unit_field, enemy_field);
+            let mut unit_damage = if unit.is_parked(unit_field) {
+                unit.parked_damage()
+            } else if let Some(target) = target {
+                unit.damage(target, enemy_field)
+            } else {
+                // Nothing left to fight, so only the sign of the damage matters.
+                unit.max_damage(enemy_field)
+            };
             if unit.is_ranged() {
                 if let Some(tower_type) = enemy.tower_type {
                     // Nukes don't 1 shot silos.
@@ -281,22 +291,35 @@ impl<'a> Combatants<'a> {
                     }
                 };
 
+                // Units engage the enemy's unit that is still fighting, or its next unit if there
+                // is none (damage is zero) so the engagement is the same for either side.
+                let engaging = damage == 0;
+                let target = |enemy: &Self, enemy_last: Option<Unit>| {
+                    if engaging {
+                        next_unit(enemy, enemy_last).map(|(u, _)| u)
+                    } else {
+                        enemy_last
+                    }
+                };
+
                 damage += if let Some((next_attacker, unit_field)) = next_attacker {
+                    let target = target(defender, last_defender);
                     replace_unit(
                         attacker,
                         &mut last_attacker,
                         last_defender,
                         Some(next_attacker),
                     );
-                    damage_against(next_attacker, unit_field, defender, field, damage)
+                    damage_against(next_attacker, unit_field, defender, target, field, damage)
                 } else if let Some((next_defender, unit_field)) = next_defender {
+                    let target = target(attacker, last_attacker);
                     replace_unit(
                         defender,
                         &mut last_defender,
                         last_attacker,
                         Some(next_defender),
                     );
-                    damage_against(next_defender, unit_field, attacker, field, damage)
+                    damage_against(next_defender, unit_field, attacker, target, field, damage)
                 } else {
                     break;
                 }
@@ -328,15 +351,27 @@ impl<'a> Combatants<'a> {
             match side {
                 CombatSide::Attacker if damage <= 0 => {
                     if let Some(unit) = next_attacker {
-                        damage +=
-                            damage_against(unit, Field::Surface, defender, Field::Surface, damage);
+                        damage += damage_against(
+                            unit,
+                            Field::Surface,
+                            defender,
+                            last_defender,
+                            Field::Surface,
+                            damage,
+                        );
                     }
                     replace_unit(attacker, &mut last_attacker, last_defender, next_attacker);
                 }
                 CombatSide::Defender if damage >= 0 => {
                     if let Some(unit) = next_defender {
-                        damage +=
-                            damage_against(unit, Field::Surface, attacker, Field::Surface, damage);
+                        damage += damage_against(
+                            unit,
+                            Field::Surface,
+                            attacker,
+                            last_attacker,
+                            Field::Surface,
+                            damage,
+                        );
                     }
                     replace_unit(defender, &mut last_defender, last_attacker, next_defender)
                 }
@@ -365,7 +400,9 @@ impl<'a> Combatants<'a> {
 
 #[cfg(test)]
 mod tests {
+    use crate::balance::{Balance, BalanceFile, UnitBalanceFile};
     use crate::combatants::{CombatInfo, CombatSide, Combatants};
+    use crate::field::Field;
     use crate::force::{Force, Path};
     use crate::tower::{Tower, TowerId, TowerType};
     use crate::unit::Unit;
@@ -940,6 +977,72 @@ mod tests {
         assert_eq!(info, [CombatInfo::ShellExplosion]);
     }
 
+    #[test]
+    fn damage_matrix() {
+        // Choppers deal 3 damage to bombers, but only 1 to fighters and soldiers.
+        let mut chopper = UnitBalanceFile::default();
+        for (target, field, damage) in [
+            ("Bomber", "Air", 3),
+            ("Fighter", "Air", 1),
+            ("Soldier", "Surface", 1),
+        ] {
+            chopper
+                .damage_against
+                .entry(target.into())
+                .or_default()
+                .insert(field.into(), damage);
+        }
+        let mut file = BalanceFile {
+            version: Balance::VERSION,
+            ..Default::default()
+        };
+        file.units.insert("Chopper".into(), chopper);
+        let balance = Balance::try_from(file).unwrap();
+
+        Balance::with(balance, || {
+            assert_eq!(Unit::Chopper.damage(Unit::Bomber, Field::Air), 3);
+            assert_eq!(Unit::Chopper.damage(Unit::Fighter, Field::Air), 1);
+            assert_eq!(Unit::Chopper.damage(Unit::Chopper, Field::Air), 3);
+            assert_eq!(Unit::Chopper.damage(Unit::Soldier, Field::Surface), 1);
+
+            let mut choppers = make_force();
+            let mut bombers = make_force();
+            choppers.add(Unit::Chopper, 2);
+            bombers.add(Unit::Bomber, 3);
+
+            let winner = Combatants::fight(&mut choppers, &mut bombers, |_| {});
+            assert_eq!(winner, Some(CombatSide::Attacker));
+
+            // 1 chopper kills all 3 bombers.
+            assert_eq!(choppers.units.len(), 1);
+            assert_eq!(bombers.units.len(), 0);
+
+            let mut choppers = make_force();
+            let mut fighters = make_force();
+            choppers.add(Unit::Chopper, 2);
+            fighters.add(Unit::Fighter, 3);
+
+            let winner = Combatants::fight(&mut choppers, &mut fighters, |_| {});
+            assert_eq!(winner, Some(CombatSide::Defender));
+
+            // Both choppers only damage the first fighter, which survives.
+            assert_eq!(choppers.units.len(), 0);
+            assert_eq!(fighters.units.len(), 3);
+
+            let mut chopper = make_force();
+            let mut soldiers = make_force();
+            chopper.add(Unit::Chopper, 1);
+            soldiers.add(Unit::Soldier, 2);
+
+            let winner = Combatants::fight(&mut chopper, &mut soldiers, |_| {});
+            assert_eq!(winner, Some(CombatSide::Defender));
+
+            // The chopper trades with 1 soldier.
+            assert_eq!(chopper.units.len(), 0);
+            assert_eq!(soldiers.units.len(), 1);
+        });
+    }
+
     #[test]
     fn emp_vs_tower() {
         let (mut tower, mut force) = make_tower_force();
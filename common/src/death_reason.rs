@@ -9,10 +9,19 @@ use diff::Diff;
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeathReason {
     RulerKilled {
-        /// Is [`None`] if was killed by zombies.
+        /// Is [`None`] if the killer left.
         alias: Option<PlayerAlias>,
         unit: Unit,
     },
+    RulerKilledByZombies {
+        unit: Unit,
+    },
+    /// The ruler was sent to a tower it couldn't enter.
+    RulerStranded,
+    /// The round ended, so the world was reset.
+    RoundEnded,
+    /// A moderator used the `/kill` chat command.
+    KilledByModerator,
 }
 
 /// Wraps [`Option<DeathReason>`]. Required to override [`Diff`].
@@ -54,6 +54,17 @@ impl<K, V: Default, const N: usize> EnumArray<K, V, N> {
     }
 }
 
+impl<K: IntoEnumIterator, V, const N: usize> EnumArray<K, V, N> {
+    /// Creates an [`EnumArray`] by calling `f` for each key in order.
+    pub fn from_fn(mut f: impl FnMut(K) -> V) -> Self {
+        let mut keys = K::iter();
+        Self {
+            values: [(); N].map(|_| f(keys.next().unwrap())),
+            spooky: PhantomData,
+        }
+    }
+}
+
 impl<K, V, const N: usize> EnumArray<K, V, N>
 where
     u8: From<K>,
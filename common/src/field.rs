@@ -2,9 +2,9 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::enum_array::EnumArray;
+use core_protocol::prelude::*;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::{Deserialize, Serialize};
-use strum::{EnumIter, IntoEnumIterator};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
 /// Fields ordered by distance above ground.
 #[derive(
@@ -16,8 +16,12 @@ use strum::{EnumIter, IntoEnumIterator};
     PartialOrd,
     Hash,
     Debug,
+    Display,
+    EnumString,
     Serialize,
     Deserialize,
+    Encode,
+    Decode,
     EnumIter,
     IntoPrimitive,
     TryFromPrimitive,
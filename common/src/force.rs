@@ -51,12 +51,13 @@ impl Path {
 
         let max_distance_squared = max_edge_distance.map(|d| (d as u64 + 1).pow(2) - 1);
 
+        let rectangle = WorldChunks::rectangle();
         let mut prev = source_tower_id;
         for &next in iter {
             if next == prev {
                 return Err("duplicate tower in path");
             }
-            if !WorldChunks::RECTANGLE.contains(next) {
+            if !rectangle.contains(next) {
                 return Err("outside world");
             }
 
@@ -114,6 +115,51 @@ impl Path {
     fn is_empty(&self) -> bool {
         self.path.len() < 2
     }
+
+    /// Splits a path of any length into consecutive legs that are short enough to be valid, each
+    /// starting where the previous one ends.
+    pub fn new_legs(path: Vec<TowerId>) -> Vec<Self> {
+        assert!(path.len() >= 2);
+        let step = World::MAX_PATH_ROADS - 1;
+        (0..path.len() - 1)
+            .step_by(step)
+            .map(|start| {
+                let end = (start + World::MAX_PATH_ROADS).min(path.len());
+                Self::new(path[start..end].to_vec())
+            })
+            .collect()
+    }
+}
+
+/// Which of a tower's [`force_units`][`crate::tower::Tower::force_units`] to deploy, so some can
+/// be kept behind.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub enum Deployment {
+    /// Every unit that can be deployed.
+    #[default]
+    All,
+    /// Percent (from 1 to 100) of each unit, rounded down. Never includes the ruler, which has to
+    /// be sent on purpose.
+    Percent(u8),
+    /// Exactly these units, which must be available.
+    Units(Units),
+}
+
+impl Deployment {
+    /// Limits [`Self::Units`] to what is `available`, so it can still be deployed after some of
+    /// the units are gone.
+    pub fn clamp(&self, available: &Units) -> Self {
+        match self {
+            Self::Units(units) => {
+                let mut clamped = Units::default();
+                for (unit, count) in units.iter() {
+                    clamped.add(unit, count.min(available.available(unit)));
+                }
+                Self::Units(clamped)
+            }
+            _ => self.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, Encode, Decode)]
@@ -126,6 +172,9 @@ pub struct Force {
     /// If [`None`], they can kill but not capture (e.g. for shrinking world).
     pub player_id: Option<PlayerId>,
     pub units: Units,
+    /// Paths to follow after [`Self::path`] (a waypoint order), each starting where the previous
+    /// one ends.
+    legs: Vec<Path>,
 }
 
 impl Force {
@@ -141,6 +190,7 @@ impl Force {
             fuel: 150,
             player_id,
             units,
+            legs: Vec::new(),
         }
     }
 
@@ -148,6 +198,11 @@ impl Force {
         Self::new_inner(Some(player_id), units, path)
     }
 
+    /// A force without a player, which is hostile to every player.
+    pub fn zombie(units: Units, path: Path) -> Self {
+        Self::new_inner(None, units, path)
+    }
+
     /// Returns where the force is coming from.
     pub fn current_source(&self) -> TowerId {
         self.path.coming_from()
@@ -162,24 +217,41 @@ impl Force {
         &self.path
     }
 
-    pub fn interpolated_position(&self, time_since_tick: f32) -> Vec2 {
+    /// Gives the force more paths to follow once it reaches the end of its current one.
+    pub fn with_legs(mut self, legs: Vec<Path>) -> Self {
+        debug_assert!(legs.iter().all(|leg| !leg.is_empty()));
+        self.legs = legs;
+        self
+    }
+
+    /// The paths left to follow after the current one.
+    pub fn legs(&self) -> &[Path] {
+        &self.legs
+    }
+
+    /// `speed_bonus` is [`Research::speed_bonus`][`crate::research::Research::speed_bonus`] of
+    /// the force's player.
+    pub fn interpolated_position(&self, time_since_tick: f32, speed_bonus: u8) -> Vec2 {
         let source = self.current_source().as_vec2();
         let destination = self.current_destination().as_vec2();
         source.lerp(
             destination,
             ((self.path_progress as f32
-                + time_since_tick * (1.0 / Ticks::PERIOD_SECS) * self.progress_per_tick() as f32)
+                + time_since_tick
+                    * (1.0 / Ticks::PERIOD_SECS)
+                    * self.progress_per_tick(speed_bonus) as f32)
                 / self.progress_required() as f32)
                 .min(1.0),
         )
     }
 
-    /// Force will arrive at current destination but not continue.
+    /// Force will arrive at current destination but not continue. Its [`Self::legs`] are kept, so
+    /// the broken waypoint order can be re-planned.
     pub fn halt(&mut self) {
         self.path = Path::new(self.path.iter().take(2).collect());
     }
 
-    /// Equivalent to `force.clone().halt()` but more efficient.
+    /// Equivalent to `force.clone().halt()` but more efficient, and without any [`Self::legs`].
     pub fn halted(&self) -> Self {
         let &Self {
             path_progress,
@@ -195,6 +267,7 @@ impl Force {
             fuel,
             player_id,
             units,
+            legs: Vec::new(),
         }
     }
 
@@ -206,11 +279,19 @@ impl Force {
         ally: Option<PlayerId>,
         supply_line: Option<&Path>,
     ) -> bool {
-        if self.path.is_empty() || ally.is_some() {
+        // Waypoint orders pass through allies' towers, since they were planned through them.
+        if self.path.is_empty() && !self.legs.is_empty() {
+            if self.legs[0].source() != self.path.coming_from() {
+                // Waypoint order broke (e.g. halted), so arrive instead of taking a supply line.
+                return false;
+            }
+            self.path = self.legs.remove(0);
+        } else if self.path.is_empty() || (ally.is_some() && self.legs.is_empty()) {
             if let Some(supply_line) = supply_line && tower_type.ranged_distance().is_none() && self.units.is_many() {
                 self.path = supply_line.clone();
                 if let Some(ally) = ally {
                     self.player_id = Some(ally);
+                    self.legs.clear();
                 } else {
                     if tower_type == TowerType::Projector {
                         let max_shield = TowerType::Projector.raw_unit_capacity(Unit::Shield);
@@ -265,16 +346,18 @@ impl Force {
         true
     }
 
-    pub(crate) fn progress_per_tick(&self) -> u8 {
-        match self.speed() {
+    /// Each point of `speed_bonus` is one [`Speed`] tier faster.
+    pub(crate) fn progress_per_tick(&self, speed_bonus: u8) -> u8 {
+        let progress = match self.speed() {
             Speed::Immobile => {
                 debug_assert!(false, "will never make progress");
-                0
+                return 0;
             }
             Speed::Slow => 1,
             Speed::Normal => 2,
             Speed::Fast => 3,
-        }
+        };
+        progress.saturating_add(speed_bonus)
     }
 
     pub fn progress_required(&self) -> u8 {
@@ -330,9 +413,15 @@ impl Force {
         }
     }
 
-    pub(crate) fn raw_tick(&mut self, assert_current_source_equals: Option<TowerId>) -> bool {
+    pub(crate) fn raw_tick(
+        &mut self,
+        assert_current_source_equals: Option<TowerId>,
+        speed_bonus: u8,
+    ) -> bool {
         // Arriving if progress per tick reaches progress required.
-        self.path_progress = self.path_progress.saturating_add(self.progress_per_tick());
+        self.path_progress = self
+            .path_progress
+            .saturating_add(self.progress_per_tick(speed_bonus));
 
         if self.path_progress >= self.progress_required() {
             // Mark arrived so next tick is leaving.
@@ -347,9 +436,9 @@ impl Force {
     }
 
     /// Advances a force by one tick and returns true if the force is arriving or is leaving.
-    pub(crate) fn tick(&mut self, inbound_tower_id: TowerId) -> bool {
+    pub(crate) fn tick(&mut self, inbound_tower_id: TowerId, speed_bonus: u8) -> bool {
         debug_assert_ne!(self.current_source(), inbound_tower_id);
-        self.raw_tick(Some(inbound_tower_id))
+        self.raw_tick(Some(inbound_tower_id), speed_bonus)
     }
 }
 
@@ -359,6 +448,7 @@ mod tests {
     use crate::tower::TowerId;
     use crate::unit::{Speed, Unit};
     use crate::units::Units;
+    use crate::world::World;
     use core_protocol::id::PlayerId;
 
     #[test]
@@ -389,4 +479,20 @@ mod tests {
         force.units.subtract(Unit::Tank, 3);
         assert_eq!(force.speed(), Speed::Fast);
     }
+
+    #[test]
+    fn new_legs() {
+        for len in 2..=3 * World::MAX_PATH_ROADS {
+            let path: Vec<_> = (0..len as u16).map(|x| TowerId::new(x, 0)).collect();
+            let legs = Path::new_legs(path.clone());
+            assert!(legs
+                .iter()
+                .all(|leg| leg.iter().count() <= World::MAX_PATH_ROADS));
+            assert_eq!(legs[0].source(), path[0]);
+            assert_eq!(legs.last().unwrap().destination(), *path.last().unwrap());
+            for pair in legs.windows(2) {
+                assert_eq!(pair[0].destination(), pair[1].source());
+            }
+        }
+    }
 }
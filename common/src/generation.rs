@@ -0,0 +1,402 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::chunk::Chunk;
+#[cfg(any(test, feature = "server"))]
+use crate::tower::ConnectivityTable;
+use crate::tower::{NeighborTable, TowerArray, TowerId, TowerType};
+use crate::world::WorldChunks;
+use common_util::x_vec2::U16Vec2;
+use core_protocol::prelude::*;
+use noise::{NoiseFn, Perlin};
+use std::collections::BTreeMap;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{LazyLock, OnceLock};
+
+/// Procedural world generation parameters, which determine [`TowerId::tower_type`]. Clients must
+/// generate with the same [`Generation`] as the server, so the server sends it along with the
+/// first [`Update`][`crate::protocol::Update`].
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Generation {
+    /// Width and height of the world in towers. A power of two multiple of [`Chunk::SIZE`] up to
+    /// [`WorldChunks::MAX_SIZE`].
+    pub size: u16,
+    pub seed: u32,
+    /// Number of layers of noise, each with double the frequency and half the amplitude.
+    pub octaves: u8,
+    /// Frequency of the first octave, per [`TowerId`].
+    pub scale: f32,
+    /// Approximate fraction of towers that are aquatic, or [`None`] for the original
+    /// [`Self::DEFAULT_AQUATIC_THRESHOLD`].
+    pub water_ratio: Option<f32>,
+    /// Relative weights of generated tower types. Only towers without a downgrade may be nonzero.
+    pub weights: TowerArray<u8>,
+}
+
+/// A [`Generation`] and everything that is derived from it.
+pub(crate) struct GenerationState {
+    generation: Generation,
+    perlin: Perlin,
+    /// Noise values below this are aquatic.
+    aquatic_threshold: f64,
+    /// Depends on tower types, so it's lazily computed per [`Generation`].
+    neighbors: OnceLock<NeighborTable>,
+    /// Depends on neighbors and [`World::center`][`crate::world::World::center`].
+    #[cfg(any(test, feature = "server"))]
+    connectivity: OnceLock<ConnectivityTable>,
+}
+
+static BUILT_IN: LazyLock<GenerationState> =
+    LazyLock::new(|| GenerationState::new(Generation::default()));
+/// Null means [`BUILT_IN`]. Otherwise, points to a leaked [`GenerationState`].
+static CURRENT: AtomicPtr<GenerationState> = AtomicPtr::new(null_mut());
+
+#[cfg(test)]
+thread_local! {
+    /// Takes precedence over [`CURRENT`] on this thread, like the [`Balance`] override. See
+    /// [`Generation::with`].
+    ///
+    /// [`Balance`]: crate::balance::Balance
+    static OVERRIDE: std::cell::Cell<Option<&'static GenerationState>> =
+        std::cell::Cell::new(None);
+}
+
+impl Generation {
+    /// Version of [`GenerationFile`]s that can be loaded.
+    pub const VERSION: u16 = 1;
+    /// The seed of the original map.
+    pub const DEFAULT_SEED: u32 = 314159;
+    /// The size of the original map.
+    pub const DEFAULT_SIZE: u16 = 256;
+    /// Noise values below this are aquatic, unless there is a [`Self::water_ratio`].
+    pub const DEFAULT_AQUATIC_THRESHOLD: f64 = -0.25;
+
+    /// Gets the [`Generation`] in effect, which is the built-in one unless [`Self::set`] was
+    /// called.
+    pub fn get() -> &'static Self {
+        &GenerationState::get().generation
+    }
+
+    /// Sets the [`Generation`] in effect for the whole process. Like
+    /// [`Balance::set`][`crate::balance::Balance::set`], the previous one is leaked, so only call
+    /// this rarely (e.g. on startup or when joining a server).
+    pub fn set(generation: Self) {
+        if Self::get() != &generation {
+            let state = Box::new(GenerationState::new(generation));
+            CURRENT.store(Box::leak(state), Ordering::Release);
+        }
+    }
+
+    /// Calls `f` with `generation` in effect on the current thread only. Only for testing.
+    #[cfg(test)]
+    pub(crate) fn with<R>(generation: Self, f: impl FnOnce() -> R) -> R {
+        let state = Box::leak(Box::new(GenerationState::new(generation)));
+        let previous = OVERRIDE.with(|o| o.replace(Some(state)));
+        let ret = f();
+        OVERRIDE.with(|o| o.set(previous));
+        ret
+    }
+
+    /// Checks the invariants that [`TowerType::generate`] relies on.
+    fn validate(&self) -> Result<(), String> {
+        let sizes = Chunk::SIZE * 4..=WorldChunks::MAX_SIZE;
+        let size = self.size as usize;
+        if !size.is_power_of_two() || !sizes.contains(&size) {
+            return Err(format!(
+                "size must be a power of two in {}..={}",
+                sizes.start(),
+                sizes.end()
+            ));
+        }
+        if self.octaves == 0 || self.octaves > 8 {
+            return Err(String::from("octaves must be in 1..=8"));
+        }
+        if !(self.scale.is_finite() && self.scale > 0.0) {
+            return Err(String::from("scale must be positive"));
+        }
+        if self
+            .water_ratio
+            .map_or(false, |water_ratio| !(0.0..=1.0).contains(&water_ratio))
+        {
+            return Err(String::from("water ratio must be in 0..=1"));
+        }
+        for (tower_type, &weight) in self.weights.iter() {
+            if weight != 0 && tower_type.downgrade().is_some() {
+                return Err(format!("{tower_type} cannot be generated"));
+            }
+        }
+        for is_aquatic in [false, true] {
+            if self.total_weight(is_aquatic) == 0 {
+                return Err(format!(
+                    "{} towers must have a nonzero weight",
+                    if is_aquatic { "aquatic" } else { "land" }
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sum of the weights of either aquatic or land towers.
+    pub(crate) fn total_weight(&self, is_aquatic: bool) -> u32 {
+        self.weights
+            .iter()
+            .filter(|(tower_type, _)| tower_type.is_aquatic() == is_aquatic)
+            .map(|(_, &weight)| weight as u32)
+            .sum()
+    }
+}
+
+impl Default for Generation {
+    /// Close to the original map, with all basic towers equally likely.
+    fn default() -> Self {
+        Self {
+            size: Self::DEFAULT_SIZE,
+            seed: Self::DEFAULT_SEED,
+            octaves: 1,
+            scale: 0.15,
+            water_ratio: None,
+            weights: TowerArray::from_fn(|tower_type| tower_type.downgrade().is_none() as u8),
+        }
+    }
+}
+
+impl GenerationState {
+    fn new(generation: Generation) -> Self {
+        let mut me = Self {
+            perlin: Perlin::new(generation.seed),
+            generation,
+            aquatic_threshold: f64::NEG_INFINITY,
+            neighbors: OnceLock::new(),
+            #[cfg(any(test, feature = "server"))]
+            connectivity: OnceLock::new(),
+        };
+        me.aquatic_threshold = me
+            .generation
+            .water_ratio
+            .map_or(Generation::DEFAULT_AQUATIC_THRESHOLD, |water_ratio| {
+                me.quantile(water_ratio)
+            });
+        me
+    }
+
+    #[inline]
+    pub(crate) fn get() -> &'static Self {
+        #[cfg(test)]
+        if let Some(state) = OVERRIDE.with(std::cell::Cell::get) {
+            return state;
+        }
+        let current = CURRENT.load(Ordering::Acquire);
+        if current.is_null() {
+            &BUILT_IN
+        } else {
+            // Safety: Only ever points to leaked boxes.
+            unsafe { &*current }
+        }
+    }
+
+    pub(crate) fn generation(&self) -> &Generation {
+        &self.generation
+    }
+
+    /// Returns roughly -1 to 1 at a (pre-offset) position.
+    pub(crate) fn noise(&self, position: U16Vec2) -> f64 {
+        let mut frequency = self.generation.scale as f64;
+        let mut amplitude = 1.0;
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        for _ in 0..self.generation.octaves {
+            sum += self
+                .perlin
+                .get([position.x as f64 * frequency, position.y as f64 * frequency])
+                * amplitude;
+            total_amplitude += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum / total_amplitude
+    }
+
+    pub(crate) fn is_aquatic(&self, noise: f64) -> bool {
+        noise < self.aquatic_threshold
+    }
+
+    pub(crate) fn neighbors(&self) -> &NeighborTable {
+        self.neighbors.get_or_init(NeighborTable::new)
+    }
+
+    #[cfg(any(test, feature = "server"))]
+    pub(crate) fn connectivity(&self) -> &ConnectivityTable {
+        self.connectivity.get_or_init(ConnectivityTable::new)
+    }
+
+    /// Estimates the noise value that `ratio` of the world is below, by sampling every other
+    /// [`TowerId`]. Deterministic, so the client and server agree.
+    fn quantile(&self, ratio: f32) -> f64 {
+        if ratio <= 0.0 {
+            return f64::NEG_INFINITY;
+        } else if ratio >= 1.0 {
+            return f64::INFINITY;
+        }
+        let size = self.generation.size;
+        let mut samples: Vec<f64> = (0..size)
+            .step_by(2)
+            .flat_map(move |y| {
+                (0..size)
+                    .step_by(2)
+                    .map(move |x| TowerId::new(x, y).noise_position())
+            })
+            .map(|position| self.noise(position))
+            .collect();
+        let index = ((samples.len() as f32 * ratio) as usize).min(samples.len() - 1);
+        *samples.select_nth_unstable_by(index, f64::total_cmp).1
+    }
+}
+
+/// Overrides of the built-in [`Generation`] (e.g. loaded from TOML). Everything that is omitted
+/// keeps its built-in value. Towers are referred to by name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationFile {
+    /// Must equal [`Generation::VERSION`].
+    pub version: u16,
+    pub size: Option<u16>,
+    pub seed: Option<u32>,
+    pub octaves: Option<u8>,
+    pub scale: Option<f32>,
+    pub water_ratio: Option<f32>,
+    /// Tower to weight. If present, omitted towers aren't generated.
+    pub weights: Option<BTreeMap<String, u8>>,
+}
+
+impl TryFrom<GenerationFile> for Generation {
+    type Error = String;
+
+    fn try_from(file: GenerationFile) -> Result<Self, Self::Error> {
+        if file.version != Self::VERSION {
+            return Err(format!(
+                "generation version {} is unsupported (expected {})",
+                file.version,
+                Self::VERSION
+            ));
+        }
+
+        let mut generation = Self::default();
+        if let Some(size) = file.size {
+            generation.size = size;
+        }
+        if let Some(seed) = file.seed {
+            generation.seed = seed;
+        }
+        if let Some(octaves) = file.octaves {
+            generation.octaves = octaves;
+        }
+        if let Some(scale) = file.scale {
+            generation.scale = scale;
+        }
+        if let Some(water_ratio) = file.water_ratio {
+            generation.water_ratio = Some(water_ratio);
+        }
+        if let Some(weights) = file.weights {
+            generation.weights = TowerArray::default();
+            for (name, weight) in weights {
+                let tower_type: TowerType = name
+                    .parse()
+                    .map_err(|_| format!("unknown tower {name:?}"))?;
+                generation.weights[tower_type] = weight;
+            }
+        }
+        generation.validate()?;
+        Ok(generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generation::{Generation, GenerationFile, GenerationState};
+    use crate::tower::TowerType;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn built_in() {
+        Generation::default().validate().unwrap();
+    }
+
+    #[test]
+    fn size() {
+        for (size, valid) in [
+            (32, false),
+            (64, true),
+            (200, false),
+            (512, true),
+            (2048, false),
+        ] {
+            let generation = Generation {
+                size,
+                ..Generation::default()
+            };
+            assert_eq!(generation.validate().is_ok(), valid, "{size}");
+        }
+    }
+
+    #[test]
+    fn water_ratio() {
+        let state = GenerationState::new(Generation::default());
+        assert_eq!(
+            state.aquatic_threshold,
+            Generation::DEFAULT_AQUATIC_THRESHOLD
+        );
+
+        for water_ratio in [0.0, 0.2, 0.5, 1.0] {
+            let state = GenerationState::new(Generation {
+                water_ratio: Some(water_ratio),
+                ..Generation::default()
+            });
+            let mut aquatic = 0;
+            let mut total = 0;
+            for y in (1..256).step_by(7) {
+                for x in (1..256).step_by(7) {
+                    let position = crate::tower::TowerId::new(x, y).noise_position();
+                    aquatic += state.is_aquatic(state.noise(position)) as usize;
+                    total += 1;
+                }
+            }
+            let actual = aquatic as f32 / total as f32;
+            assert!(
+                (actual - water_ratio).abs() < 0.05,
+                "{actual} {water_ratio}"
+            );
+        }
+    }
+
+    #[test]
+    fn weights() {
+        let generation = Generation {
+            weights: Default::default(),
+            ..Generation::default()
+        };
+        assert!(generation.validate().is_err());
+
+        let file = GenerationFile {
+            version: Generation::VERSION,
+            seed: Some(42),
+            weights: Some(BTreeMap::from([
+                ("Factory".to_owned(), 3),
+                ("Barracks".to_owned(), 1),
+                ("Lighthouse".to_owned(), 1),
+            ])),
+            ..Default::default()
+        };
+        let generation = Generation::try_from(file).unwrap();
+        assert_eq!(generation.seed, 42);
+        assert_eq!(generation.weights[TowerType::Factory], 3);
+        assert_eq!(generation.weights[TowerType::Mine], 0);
+
+        let file = GenerationFile {
+            version: Generation::VERSION,
+            weights: Some(BTreeMap::from([("City".to_owned(), 1)])),
+            ..Default::default()
+        };
+        assert!(Generation::try_from(file).is_err());
+    }
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::tower::TowerId;
-use crate::unit::Unit;
+use crate::unit::{Unit, UnitArray};
 use core_protocol::id::PlayerId;
 use glam::Vec2;
 
@@ -41,6 +41,16 @@ pub enum Info {
         player_id: PlayerId,
         reason: LostTowerReason,
     },
+    /// A force couldn't follow its waypoint order any further, so it stopped at one of its
+    /// player's towers.
+    WaypointsBroken {
+        tower_id: TowerId,
+        player_id: PlayerId,
+        /// Where the waypoint order was going.
+        destination: TowerId,
+        /// Units of the force that stopped, so only they continue.
+        units: UnitArray<u8>,
+    },
     Emp(Option<PlayerId>),
     NuclearExplosion,
     ShellExplosion,
@@ -48,13 +58,18 @@ pub enum Info {
 
 #[derive(Copy, Clone, Debug)]
 pub enum LostRulerReason {
+    /// Killed by a player's unit, or a zombie's if [`None`].
     KilledBy(Option<PlayerId>, Unit),
+    /// Sent to a tower it couldn't enter, such as an ally's.
+    Stranded,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum GainedTowerReason {
     CapturedFrom(Option<PlayerId>),
     Explored,
+    /// An ally gave the tower away.
+    GivenBy(PlayerId),
     Spawned,
 }
 
@@ -62,6 +77,8 @@ pub enum GainedTowerReason {
 pub enum LostTowerReason {
     CapturedBy(Option<PlayerId>),
     DestroyedBy(Option<PlayerId>),
+    /// The owner gave the tower to an ally.
+    GivenTo(PlayerId),
     /// The owner was killed.
     PlayerKilled,
 }
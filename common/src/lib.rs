@@ -19,20 +19,29 @@ extern crate core;
 #[cfg(test)]
 extern crate test;
 
-mod combatants;
 #[macro_use]
 mod macros;
 
 pub mod alerts;
+pub mod alliance;
+pub mod balance;
 pub mod chunk;
+pub mod combatants;
 pub mod death_reason;
 pub mod enum_array;
 pub mod field;
 pub mod force;
+pub mod generation;
 pub mod info;
+pub mod pathfinder;
 pub mod player;
 pub mod protocol;
+pub mod replay;
+pub mod research;
+pub mod round;
+pub mod ruler;
 pub mod singleton;
+pub mod supply_line;
 pub mod ticks;
 pub mod tower;
 pub mod unit;
@@ -0,0 +1,531 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Finding paths for forces and supply lines, shared by clients and the server (including bots).
+
+use crate::tower::{integer_sqrt, Tower, TowerId};
+use crate::world::World;
+use core_protocol::id::PlayerId;
+use fxhash::FxHashMap;
+use strum::{Display, EnumIter, EnumMessage, EnumString, IntoStaticStr};
+
+/// What a path prioritizes, besides being short.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Hash,
+    Display,
+    EnumIter,
+    EnumMessage,
+    EnumString,
+    IntoStaticStr,
+)]
+pub enum PathMode {
+    /// Only distance matters.
+    #[strum(message = "Fastest paths")]
+    Fastest,
+    /// Avoids enemy and zombie towers and forces, more so the more units they have.
+    #[default]
+    #[strum(message = "Safest paths")]
+    Safest,
+    /// Avoids own and teammates' towers, so that as many towers as possible are captured.
+    #[strum(message = "Capturing paths")]
+    Capture,
+}
+
+impl PathMode {
+    /// Units beyond this many don't make a tower any less safe to go through.
+    const MAX_THREAT: u32 = 32;
+
+    /// Cost, in towers, of going through `tower` in addition to the distance. `enemy` tells
+    /// whether a player's forces would fight the path's force.
+    fn penalty(self, tower: &Tower, friendly: bool, enemy: impl Fn(PlayerId) -> bool) -> u32 {
+        match self {
+            Self::Fastest => 0,
+            Self::Safest => {
+                // Forces on their way may arrive first (zombies don't have a player).
+                let inbound: usize = tower
+                    .inbound_forces
+                    .iter()
+                    .filter(|force| force.player_id.map_or(true, &enemy))
+                    .map(|force| force.units.len())
+                    .sum();
+                let hostile = !friendly && (tower.player_id.is_some() || !tower.units.is_empty());
+                if hostile || inbound > 0 {
+                    let garrison = if friendly { 0 } else { tower.units.len() };
+                    4 + ((garrison + inbound) as u32).min(Self::MAX_THREAT)
+                } else {
+                    0
+                }
+            }
+            Self::Capture => {
+                if friendly {
+                    2
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+impl World {
+    /// Finds the best path from `src` to `dst` through towers that pass the `filter`, or
+    /// [`None`] if there isn't one. Ranged units (`max_edge_distance` is [`Some`]) go directly.
+    pub fn find_best_path(
+        &self,
+        src: TowerId,
+        dst: TowerId,
+        max_edge_distance: Option<u32>,
+        player_id: PlayerId,
+        mode: PathMode,
+        filter: impl Fn(TowerId) -> bool,
+    ) -> Option<Vec<TowerId>> {
+        let search = Search::new(self, player_id, mode, &filter, dst);
+        best_path(src, dst, max_edge_distance, &filter, |dst| {
+            search.with_dst(dst).astar(src).map(|(path, _)| path)
+        })
+    }
+
+    /// Like [`Self::find_best_path`], but goes as close as possible to `dst` if it can't be
+    /// reached. The path may be just `src`.
+    pub fn find_best_incomplete_path(
+        &self,
+        src: TowerId,
+        dst: TowerId,
+        max_edge_distance: Option<u32>,
+        player_id: PlayerId,
+        mode: PathMode,
+        filter: impl Fn(TowerId) -> bool,
+    ) -> Vec<TowerId> {
+        let search = Search::new(self, player_id, mode, &filter, dst);
+        best_incomplete_path(src, dst, max_edge_distance, &filter, |dst| {
+            search.with_dst(dst).astar(src).map(|(path, _)| path)
+        })
+    }
+}
+
+/// Remembers the paths of one player between ticks, so that they aren't searched for again
+/// (e.g. every frame while dragging) unless they changed.
+#[derive(Clone, Debug, Default)]
+pub struct PathCache {
+    mode: PathMode,
+    paths: FxHashMap<(TowerId, TowerId), CachedPath>,
+}
+
+#[derive(Clone, Debug)]
+struct CachedPath {
+    path: Vec<TowerId>,
+    cost: u32,
+    /// Ticks since searching.
+    age: u8,
+}
+
+impl PathCache {
+    /// Paths are searched for again after this many ticks, in case better ones opened up.
+    const MAX_AGE: u8 = 4;
+    /// Beyond this many paths, all are forgotten.
+    const MAX_PATHS: usize = 64;
+
+    pub fn new(mode: PathMode) -> Self {
+        Self {
+            mode,
+            paths: Default::default(),
+        }
+    }
+
+    pub fn mode(&self) -> PathMode {
+        self.mode
+    }
+
+    /// Forgets all paths if `mode` is different.
+    pub fn set_mode(&mut self, mode: PathMode) {
+        if mode != self.mode {
+            *self = Self::new(mode);
+        }
+    }
+
+    /// Ages paths, forgetting old ones. Call once per tick.
+    pub fn tick(&mut self) {
+        self.paths.retain(|_, path| {
+            path.age += 1;
+            path.age < Self::MAX_AGE
+        });
+    }
+
+    /// Same as [`World::find_best_path`] (with this cache's [`PathMode`]).
+    pub fn find_best_path(
+        &mut self,
+        world: &World,
+        src: TowerId,
+        dst: TowerId,
+        max_edge_distance: Option<u32>,
+        player_id: PlayerId,
+        filter: impl Fn(TowerId) -> bool,
+    ) -> Option<Vec<TowerId>> {
+        let search = Search::new(world, player_id, self.mode, &filter, dst);
+        best_path(src, dst, max_edge_distance, &filter, |dst| {
+            self.astar(&search.with_dst(dst), src)
+        })
+    }
+
+    /// Same as [`World::find_best_incomplete_path`] (with this cache's [`PathMode`]).
+    pub fn find_best_incomplete_path(
+        &mut self,
+        world: &World,
+        src: TowerId,
+        dst: TowerId,
+        max_edge_distance: Option<u32>,
+        player_id: PlayerId,
+        filter: impl Fn(TowerId) -> bool,
+    ) -> Vec<TowerId> {
+        let search = Search::new(world, player_id, self.mode, &filter, dst);
+        best_incomplete_path(src, dst, max_edge_distance, &filter, |dst| {
+            self.astar(&search.with_dst(dst), src)
+        })
+    }
+
+    /// Reuses the path from `src` to `search.dst` if it still has the same cost, otherwise
+    /// searches for it. Failures aren't cached, since they depend on the filter, which another
+    /// search to the same destination may not share.
+    fn astar(
+        &mut self,
+        search: &Search<impl Fn(TowerId) -> bool>,
+        src: TowerId,
+    ) -> Result<Vec<TowerId>, TowerId> {
+        let key = (src, search.dst);
+        if let Some(cached) = self.paths.get(&key) {
+            if search.cost(&cached.path) == Some(cached.cost) {
+                return Ok(cached.path.clone());
+            }
+        }
+
+        let (path, cost) = search.astar(src)?;
+        if self.paths.len() >= Self::MAX_PATHS {
+            self.paths.clear();
+        }
+        self.paths.insert(
+            key,
+            CachedPath {
+                path: path.clone(),
+                cost,
+                age: 0,
+            },
+        );
+        Ok(path)
+    }
+}
+
+/// Implements [`World::find_best_path`] given an A* search to a destination.
+fn best_path(
+    src: TowerId,
+    dst: TowerId,
+    max_edge_distance: Option<u32>,
+    filter: &impl Fn(TowerId) -> bool,
+    mut astar: impl FnMut(TowerId) -> Result<Vec<TowerId>, TowerId>,
+) -> Option<Vec<TowerId>> {
+    if let Some(d) = max_edge_distance {
+        (src.distance(dst) <= d && filter(dst)).then(|| vec![src, dst])
+    } else {
+        astar(dst).ok().filter(|p| p.len() >= 2)
+    }
+}
+
+/// Implements [`World::find_best_incomplete_path`] given an A* search to a destination.
+fn best_incomplete_path(
+    src: TowerId,
+    dst: TowerId,
+    max_edge_distance: Option<u32>,
+    filter: &impl Fn(TowerId) -> bool,
+    mut astar: impl FnMut(TowerId) -> Result<Vec<TowerId>, TowerId>,
+) -> Vec<TowerId> {
+    if let Some(d) = max_edge_distance {
+        (src.distance(dst) <= d && filter(dst))
+            .then(|| vec![src, dst])
+            .unwrap_or_else(|| vec![src])
+    } else {
+        astar(dst).unwrap_or_else(|reachable| astar(reachable).unwrap_or_default())
+    }
+}
+
+/// A search for paths of a player, to a destination.
+struct Search<'a, F> {
+    world: &'a World,
+    player_id: PlayerId,
+    mode: PathMode,
+    filter: &'a F,
+    dst: TowerId,
+    /// Owner of the original destination, who may be an ally.
+    dst_player_id: Option<PlayerId>,
+}
+
+impl<'a, F: Fn(TowerId) -> bool> Search<'a, F> {
+    // Scale distances squared up to avoid integer rounding errors (basically a fixed point).
+    // Only 33 bits of u64 distance_squared are ever used so a D2_SCALE <= 2^30 is valid.
+    const D2_SCALE: u64 = 1 << 16;
+    const D_SCALE: u32 = 1 << 8; // Must be square root of D2_SCALE;
+    /// Towers that any search may visit, in addition to [`Self::VISITS_PER_TOWER`].
+    const MIN_VISITS: usize = 256;
+    /// Towers that a search may visit per tower of (Manhattan) distance.
+    const VISITS_PER_TOWER: usize = 16;
+    /// Searches run for every bot every tick, and every frame while dragging, so they must give
+    /// up quickly even if the destination is far away or unreachable.
+    const MAX_VISITS: usize = 2048;
+
+    fn new(
+        world: &'a World,
+        player_id: PlayerId,
+        mode: PathMode,
+        filter: &'a F,
+        dst: TowerId,
+    ) -> Self {
+        Self {
+            world,
+            player_id,
+            mode,
+            filter,
+            dst,
+            dst_player_id: world.chunk.get(dst).and_then(|t| t.player_id),
+        }
+    }
+
+    /// Searches to a different destination, without forgetting the original destination's owner.
+    fn with_dst(&self, dst: TowerId) -> Self {
+        Self { dst, ..*self }
+    }
+
+    /// Cost of going from `src` to its neighbor `dst`, or [`None`] if it's impossible.
+    fn edge_cost(&self, src: TowerId, dst: TowerId) -> Option<u32> {
+        let tower = self.world.chunk.get(dst)?;
+        let friendly = tower.player_id.map_or(false, |p| {
+            p == self.player_id || self.world.are_teammates(self.player_id, p)
+        });
+        // Forces can pass through teammates' towers, but would be given to allies.
+        let passes_through_alliance = !friendly
+            && tower.player_id.is_some_and(|p| {
+                Some(p) != self.dst_player_id && self.world.have_alliance(self.player_id, p)
+            });
+        let enemy = |p: PlayerId| {
+            p != self.player_id
+                && !self.world.are_teammates(self.player_id, p)
+                && !self.world.have_alliance(self.player_id, p)
+        };
+        (!passes_through_alliance && (self.filter)(dst)).then(|| {
+            integer_sqrt(src.distance_squared(dst) * Self::D2_SCALE)
+                + self.mode.penalty(tower, friendly, enemy) * Self::D_SCALE
+        })
+    }
+
+    /// Current cost of `path`, or [`None`] if it's no longer possible.
+    fn cost(&self, path: &[TowerId]) -> Option<u32> {
+        path.windows(2)
+            .map(|edge| self.edge_cost(edge[0], edge[1]))
+            .sum()
+    }
+
+    /// Returns the path from `src` to [`Self::dst`] and its cost, or the closest reachable tower
+    /// to [`Self::dst`] if there isn't one.
+    fn astar(&self, src: TowerId) -> Result<(Vec<TowerId>, u32), TowerId> {
+        let dst = self.dst;
+        let heuristic = |pos: TowerId| integer_sqrt(pos.distance_squared(dst) * Self::D2_SCALE);
+        let mut closest = (src, heuristic(src));
+
+        // Don't visit every tower if the destination is unreachable, but allow detours of any
+        // shape, as long as they don't take too many visits to find.
+        let distance = src.manhattan_distance(dst) as usize;
+        let max_visits =
+            (Self::MIN_VISITS + distance * Self::VISITS_PER_TOWER).min(Self::MAX_VISITS);
+        let mut visits = 0;
+
+        pathfinding::directed::astar::astar(
+            &src,
+            |&pos| {
+                visits += 1;
+                let exhausted = visits > max_visits;
+                pos.neighbors()
+                    .filter(move |_| !exhausted)
+                    .filter_map(move |tower_id| {
+                        self.edge_cost(pos, tower_id).map(|cost| (tower_id, cost))
+                    })
+            },
+            |&pos| {
+                let h = heuristic(pos);
+                if h < closest.1 {
+                    closest = (pos, h);
+                }
+                h
+            },
+            |&pos| pos == dst,
+        )
+        .ok_or(closest.0)
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use crate::force::{Force, Path};
+    use crate::pathfinder::{PathCache, PathMode, Search};
+    use crate::tower::{Tower, TowerId, TowerType};
+    use crate::unit::Unit;
+    use crate::units::Units;
+    use crate::world::World;
+    use common_util::storage::Map;
+    use core_protocol::id::PlayerId;
+    use std::cell::Cell;
+
+    #[test]
+    fn cache() {
+        let world = World::new();
+        let mut towers = world.chunk.iter_towers_square(World::center(), 8);
+        let (src, _) = towers.next().unwrap();
+        let (dst, _) = towers.last().unwrap();
+        let player_id = PlayerId::SOLO_OFFLINE;
+
+        for mode in [PathMode::Fastest, PathMode::Safest, PathMode::Capture] {
+            let expected = world.find_best_path(src, dst, None, player_id, mode, |_| true);
+            let mut cache = PathCache::new(mode);
+            for _ in 0..2 {
+                let path = cache.find_best_path(&world, src, dst, None, player_id, |_| true);
+                assert_eq!(path, expected);
+                assert_eq!(cache.paths.len(), 1);
+            }
+            for _ in 0..PathCache::MAX_AGE {
+                cache.tick();
+            }
+            assert!(cache.paths.is_empty());
+        }
+    }
+
+    #[test]
+    fn penalty() {
+        let player_id = PlayerId::SOLO_OFFLINE;
+        let enemy_id = PlayerId::nth_bot(0).unwrap();
+        let enemy = |p: PlayerId| p != player_id;
+        let units = |count| {
+            let mut units = Units::default();
+            units.add(Unit::Soldier, count);
+            units
+        };
+        let force = |player_id, count| {
+            let path = Path::new(vec![TowerId::new(0, 0), TowerId::new(1, 0)]);
+            Force::new_inner(player_id, units(count), path)
+        };
+
+        let empty = Tower::with_type(TowerType::Projector);
+        let mut garrisoned = empty.clone();
+        garrisoned.player_id = Some(enemy_id);
+        garrisoned.units = units(3);
+        let mut overwhelming = garrisoned.clone();
+        overwhelming.units = units(40);
+        let mut zombies_inbound = empty.clone();
+        zombies_inbound.inbound_forces.push(force(None, 5));
+        let mut friends_inbound = empty.clone();
+        friends_inbound
+            .inbound_forces
+            .push(force(Some(player_id), 5));
+        let mut enemies_inbound = garrisoned.clone();
+        enemies_inbound
+            .inbound_forces
+            .push(force(Some(enemy_id), 5));
+
+        let penalty =
+            |mode: PathMode, tower: &Tower, friendly| mode.penalty(tower, friendly, enemy);
+        for (tower, friendly) in [(&empty, false), (&garrisoned, false), (&garrisoned, true)] {
+            assert_eq!(penalty(PathMode::Fastest, tower, friendly), 0);
+        }
+
+        assert_eq!(penalty(PathMode::Safest, &empty, false), 0);
+        assert_eq!(penalty(PathMode::Safest, &garrisoned, false), 4 + 3);
+        assert_eq!(penalty(PathMode::Safest, &garrisoned, true), 0);
+        assert_eq!(
+            penalty(PathMode::Safest, &overwhelming, false),
+            4 + PathMode::MAX_THREAT
+        );
+        assert_eq!(penalty(PathMode::Safest, &zombies_inbound, false), 4 + 5);
+        assert_eq!(penalty(PathMode::Safest, &friends_inbound, false), 0);
+        assert_eq!(
+            penalty(PathMode::Safest, &enemies_inbound, false),
+            4 + 3 + 5
+        );
+        // Friendly towers are only unsafe because of what's coming.
+        assert_eq!(penalty(PathMode::Safest, &enemies_inbound, true), 4 + 5);
+
+        assert_eq!(penalty(PathMode::Capture, &empty, false), 0);
+        assert_eq!(penalty(PathMode::Capture, &garrisoned, false), 0);
+        assert_eq!(penalty(PathMode::Capture, &garrisoned, true), 2);
+    }
+
+    #[test]
+    fn invalidation() {
+        let mut world = World::new();
+        let (src, dst) = {
+            let mut towers = world.chunk.iter_towers_square(World::center(), 8);
+            (towers.next().unwrap().0, towers.last().unwrap().0)
+        };
+        let player_id = PlayerId::SOLO_OFFLINE;
+
+        let mut cache = PathCache::new(PathMode::Capture);
+        let path = cache
+            .find_best_path(&world, src, dst, None, player_id, |_| true)
+            .unwrap();
+        let before = cache.paths[&(src, dst)].clone();
+
+        // Capturing paths avoid own towers, so one on the path changes its cost.
+        let (chunk_id, tower_id) = path[path.len() / 2].split();
+        Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor[tower_id].player_id =
+            Some(player_id);
+
+        let expected = world.find_best_path(src, dst, None, player_id, PathMode::Capture, |_| true);
+        let path = cache.find_best_path(&world, src, dst, None, player_id, |_| true);
+        assert_eq!(path, expected);
+        let after = &cache.paths[&(src, dst)];
+        assert_ne!((&after.path, after.cost), (&before.path, before.cost));
+    }
+
+    #[test]
+    fn unreachable() {
+        let world = World::new();
+        let src = World::center();
+        let (dst, _) = world.chunk.iter_towers_square(src, 100).last().unwrap();
+        let player_id = PlayerId::SOLO_OFFLINE;
+
+        for mode in [PathMode::Fastest, PathMode::Safest, PathMode::Capture] {
+            let edges = Cell::new(0);
+            let path = world.find_best_path(src, dst, None, player_id, mode, |tower_id| {
+                edges.set(edges.get() + 1);
+                tower_id != dst
+            });
+            assert_eq!(path, None);
+            // Each visit considers at most 8 neighbors.
+            assert!(edges.get() <= 8 * Search::<fn(TowerId) -> bool>::MAX_VISITS);
+        }
+    }
+
+    #[test]
+    fn unknown_players() {
+        let mut world = World::new();
+        let (src, dst) = {
+            let mut towers = world.chunk.iter_towers_square(World::center(), 8);
+            (towers.next().unwrap().0, towers.last().unwrap().0)
+        };
+        let player_id = PlayerId::SOLO_OFFLINE;
+        let path = world
+            .find_best_path(src, dst, None, player_id, PathMode::Fastest, |_| true)
+            .unwrap();
+
+        // Clients only know about some players, so towers may be owned by missing ones.
+        let (chunk_id, tower_id) = path[path.len() / 2].split();
+        let tower = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor[tower_id];
+        tower.player_id = PlayerId::nth_bot(0);
+        tower.units.add(Unit::Soldier, 3);
+
+        for mode in [PathMode::Fastest, PathMode::Safest, PathMode::Capture] {
+            let path = world.find_best_path(src, dst, None, player_id, mode, |_| true);
+            assert!(path.is_some());
+        }
+    }
+}
@@ -1,9 +1,13 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::research::{Research, ResearchArray};
+use crate::ruler::{RulerAbility, RulerAbilityArray};
+use crate::ticks::Ticks;
 use crate::world::Apply;
 use common_util::actor2::{Actor, Message};
 use common_util::hash::Hashable;
+use core_protocol::id::TeamId;
 use core_protocol::prelude::*;
 use fxhash::FxHashSet;
 
@@ -13,6 +17,38 @@ pub use core_protocol::PlayerId;
 pub struct Player {
     pub allies: Hashable<FxHashSet<PlayerId>>, // TODO better set/map.
     pub new_alliances: Hashable<FxHashSet<PlayerId>>,
+    /// Teammates are permanent allies, whose towers forces can pass through.
+    pub team_id: Option<TeamId>,
+    /// Time until each [`RulerAbility`] can be used again.
+    pub ability_cooldowns: RulerAbilityArray<Ticks>,
+    /// Level of each [`Research`], which clients need to simulate its effects.
+    pub research: ResearchArray<u8>,
+    /// Produced by labs and spent on [`Self::research`].
+    pub research_points: u16,
+}
+
+impl Player {
+    pub fn is_teammate(&self, other: &Self) -> bool {
+        self.team_id.is_some() && self.team_id == other.team_id
+    }
+
+    /// Returns true if `ability` isn't on cooldown.
+    pub fn can_use_ability(&self, ability: RulerAbility) -> bool {
+        self.ability_cooldowns[ability] == Ticks::ZERO
+    }
+
+    /// Returns true if the next level of `research` exists and is affordable.
+    pub fn can_research(&self, research: Research) -> bool {
+        let level = self.research[research];
+        level < research.max_level() && self.research_points >= research.cost(level)
+    }
+
+    /// Counts down [`Self::ability_cooldowns`]. Called once per tick.
+    pub(crate) fn tick_ability_cooldowns(&mut self) {
+        for (_, cooldown) in self.ability_cooldowns.iter_mut() {
+            *cooldown = cooldown.saturating_sub(Ticks::ONE);
+        }
+    }
 }
 
 impl Actor for Player {
@@ -33,6 +69,12 @@ pub enum PlayerInput {
     NewAlliance(PlayerId),
     /// Cancel signle direction alliance request.
     RemoveAlly(PlayerId),
+    /// Starts the cooldown of a [`RulerAbility`] that was just used.
+    UsedAbility(RulerAbility),
+    /// Points produced by labs.
+    AddResearchPoints(u16),
+    /// Spends points on the next level of a [`Research`].
+    Research(Research),
 }
 
 impl Message for PlayerInput {}
@@ -42,6 +84,9 @@ impl<C> Apply<PlayerInput, C> for Player {
         match u.clone() {
             PlayerInput::Died => {
                 self.allies.clear();
+                self.ability_cooldowns = Default::default();
+                self.research = Default::default();
+                self.research_points = 0;
                 //self.new_alliances.clear();
             }
             PlayerInput::AddAlly(player_id) => {
@@ -55,6 +100,18 @@ impl<C> Apply<PlayerInput, C> for Player {
                 let _removed = self.allies.remove(&player_id);
                 //debug_assert!(_removed);
             }
+            PlayerInput::UsedAbility(ability) => {
+                self.ability_cooldowns[ability] = ability.cooldown();
+            }
+            PlayerInput::AddResearchPoints(points) => {
+                self.research_points = self.research_points.saturating_add(points);
+            }
+            PlayerInput::Research(research) => {
+                debug_assert!(self.can_research(research));
+                let level = &mut self.research[research];
+                self.research_points = self.research_points.saturating_sub(research.cost(*level));
+                *level += 1;
+            }
         }
     }
 }
@@ -63,6 +120,9 @@ impl<C> Apply<PlayerInput, C> for Player {
 pub enum PlayerMaintainance {
     Died,
     RemoveDeadAlly(PlayerId),
+    SetTeam(Option<TeamId>),
+    /// Like [`PlayerInput::NewAlliance`], but for a player that joined the same team.
+    NewTeammate(PlayerId),
 }
 
 impl Message for PlayerMaintainance {}
@@ -72,11 +132,21 @@ impl<C> Apply<PlayerMaintainance, C> for Player {
         match u.clone() {
             PlayerMaintainance::Died => {
                 self.allies.clear();
+                self.team_id = None;
+                self.ability_cooldowns = Default::default();
+                self.research = Default::default();
+                self.research_points = 0;
             }
             PlayerMaintainance::RemoveDeadAlly(player_id) => {
                 let _removed = self.allies.remove(&player_id);
                 //debug_assert!(_removed);
             }
+            PlayerMaintainance::SetTeam(team_id) => {
+                self.team_id = team_id;
+            }
+            PlayerMaintainance::NewTeammate(player_id) => {
+                self.new_alliances.insert(player_id);
+            }
         }
     }
 }
@@ -2,9 +2,16 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::alerts::Alerts;
-use crate::chunk::ChunkRectangle;
+use crate::alliance::AllianceRequests;
+use crate::balance::Balance;
+use crate::chunk::{Chunk, ChunkRectangle};
 use crate::death_reason::OptionDeathReason;
-use crate::force::Path;
+use crate::force::{Deployment, Path};
+use crate::generation::Generation;
+use crate::research::Research;
+use crate::round::Round;
+use crate::ruler::RulerAbility;
+use crate::supply_line::SupplyLine;
 use crate::tower::{TowerArray, TowerId, TowerRectangle, TowerType};
 use core_protocol::prelude::*;
 use core_protocol::PlayerId;
@@ -12,26 +19,92 @@ use serde::{Deserialize, Serialize};
 
 pub use diff::Diff;
 
-#[derive(Clone, Encode, Decode)]
+#[derive(Clone, Serialize, Deserialize, Encode, Decode)]
 pub enum Command {
+    /// Requests (or accepts) an alliance, or breaks it. Breaking an alliance that `with`
+    /// requested declines it.
     Alliance {
         with: PlayerId,
         break_alliance: bool,
+        /// If [`Some`], a requested alliance expires after this many minutes.
+        minutes: Option<u8>,
     },
     DeployForce {
         tower_id: TowerId,
         path: Path,
+        /// Paths to follow after `path`, each starting where the previous one ends (a waypoint
+        /// order).
+        legs: Vec<Path>,
+        /// Which units to deploy.
+        deployment: Deployment,
     },
+    /// Spends research points on the next level of a [`Research`].
+    Research(Research),
+    /// Replaces all of a tower's supply lines with `path`, or removes them if [`None`].
     SetSupplyLine {
         tower_id: TowerId,
         path: Option<Path>,
     },
+    /// Replaces all of a tower's supply lines.
+    SetSupplyLines {
+        tower_id: TowerId,
+        supply_lines: Vec<SupplyLine>,
+    },
     SetViewport(ChunkRectangle),
     Spawn,
+    /// Start (or with [`None`], stop) spectating. Only allowed while dead.
+    Spectate(Option<Spectate>),
+    /// Gives a tower to a player who is allied with the sender.
+    TransferTower {
+        tower_id: TowerId,
+        to: PlayerId,
+    },
     Upgrade {
         tower_id: TowerId,
         tower_type: TowerType,
     },
+    /// Uses an ability of the sender's ruler. Only abilities that
+    /// [`RulerAbility::has_target`] have a `target`.
+    UseRulerAbility {
+        ability: RulerAbility,
+        target: Option<TowerId>,
+    },
+}
+
+/// What a spectator watches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum Spectate {
+    /// Anywhere in the world, as set by [`Command::SetViewport`].
+    Viewport,
+    /// The area around a player's towers.
+    Player(PlayerId),
+}
+
+impl Spectate {
+    /// Most towers a [`Spectate::Viewport`] camera can show across, about as many as the camera
+    /// of a large empire.
+    pub const MAX_CAMERA_TOWERS: u16 = 128;
+    /// Most chunks a [`Spectate::Viewport`] viewport can be across, with room for the camera to
+    /// straddle chunks.
+    pub const MAX_VIEWPORT_CHUNKS: u8 = (Self::MAX_CAMERA_TOWERS / Chunk::SIZE as u16) as u8 + 2;
+}
+
+impl Diff for Spectate {
+    type Repr = Option<Self>;
+
+    fn diff(&self, other: &Self) -> Self::Repr {
+        (self != other).then_some(*other)
+    }
+
+    fn apply(&mut self, diff: &Self::Repr) {
+        if let Some(new) = diff {
+            *self = *new;
+        }
+    }
+
+    fn identity() -> Self {
+        Self::Viewport
+    }
 }
 
 impl Command {
@@ -39,6 +112,8 @@ impl Command {
         Self::DeployForce {
             tower_id: path[0],
             path: Path::new(path),
+            legs: Vec::new(),
+            deployment: Deployment::All,
         }
     }
 }
@@ -57,6 +132,14 @@ pub struct NonActor {
     pub death_reason: OptionDeathReason,
     /// An approximation of inhabited towers.
     pub bounding_rectangle: TowerRectangle,
+    /// Progress of the current round.
+    pub round: Round,
+    /// Alliances other players requested, which haven't been accepted.
+    pub alliance_requests: AllianceRequests,
+    /// A tower to pan the camera to, as requested by the `/tp` chat command.
+    pub focus: Option<TowerId>,
+    /// What is being watched, if spectating.
+    pub spectate: Option<Spectate>,
 }
 
 impl Default for NonActor {
@@ -75,4 +158,8 @@ pub struct Update {
     /// (TODO)
     #[bitcode(with_serde)]
     pub non_actor_diff: NonActorDiff,
+    /// Only sent in the first update, so the client simulates with the server's [`Balance`].
+    pub balance: Option<Box<Balance>>,
+    /// Only sent in the first update, so the client generates the same towers as the server.
+    pub generation: Option<Box<Generation>>,
 }
@@ -0,0 +1,225 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::balance::Balance;
+use crate::generation::Generation;
+use crate::info::InfoEvent;
+use crate::world::{ApplyOwned, Update, World};
+#[cfg(feature = "server")]
+use crate::{
+    singleton::SingletonId,
+    world::{Knowledge, Visibility},
+};
+#[cfg(feature = "server")]
+use common_util::storage::Map;
+use core_protocol::prelude::*;
+#[cfg(feature = "server")]
+use std::fs::File;
+#[cfg(feature = "server")]
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Incremented whenever the format changes, since old replays can't be played.
+const VERSION: u16 = 10;
+
+/// Start of a replay file. It's followed by one [`Update`] per tick.
+#[derive(Encode, Decode)]
+struct ReplayHeader {
+    version: u16,
+    balance: Balance,
+    generation: Generation,
+}
+
+/// Records every tick of a server's [`World`] to a file, so it can be re-simulated by a
+/// [`ReplayPlayer`]. Each tick is recorded as the [`Update`] of a client that sees everything,
+/// so it only contains the inputs dispatched that tick, and which actors were added or removed.
+#[cfg(feature = "server")]
+pub struct ReplayRecorder {
+    knowledge: Knowledge,
+    writer: BufWriter<File>,
+}
+
+#[cfg(feature = "server")]
+impl ReplayRecorder {
+    /// Records the [`Balance`] and [`Generation`] in effect, so they must already be set.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut recorder = Self {
+            knowledge: Knowledge::default(),
+            writer: BufWriter::new(file),
+        };
+        recorder.write(&ReplayHeader {
+            version: VERSION,
+            balance: Balance::get().clone(),
+            generation: Generation::get().clone(),
+        })?;
+        Ok(recorder)
+    }
+
+    /// Must be called at the end of every tick, before [`World::post_update`] clears the inputs.
+    /// The first call records the whole world.
+    pub fn record(&mut self, world: &World) -> Result<(), String> {
+        let update = world.get_update(
+            &mut self.knowledge,
+            Visibility {
+                chunk: |_: &Knowledge| world.chunk.iter_chunks().map(|(chunk_id, _)| chunk_id),
+                player: |_: &Knowledge| Map::keys(&world.player),
+                singleton: |_: &Knowledge| Some(SingletonId),
+            },
+        );
+        self.write(&update)?;
+        // Flush every tick, so the replay is usable even if the server is killed.
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// Writes a length prefixed record.
+    fn write<T: Encode>(&mut self, value: &T) -> Result<(), String> {
+        let bytes = bitcode::encode(value).map_err(|e| e.to_string())?;
+        let len = u32::try_from(bytes.len()).map_err(|_| String::from("record too large"))?;
+        self.writer
+            .write_all(&len.to_le_bytes())
+            .and_then(|_| self.writer.write_all(&bytes))
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Re-simulates a replay file recorded by a [`ReplayRecorder`], the same way clients simulate
+/// [`Update`]s.
+pub struct ReplayPlayer {
+    /// Encoded [`Update`]s, one per tick.
+    frames: Vec<Box<[u8]>>,
+    world: World,
+    /// How many frames were applied to `world`.
+    position: usize,
+}
+
+impl ReplayPlayer {
+    /// Sets the [`Balance`] and [`Generation`] of the replay for the whole process (see
+    /// [`Balance::set`]).
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let mut records = Records(&bytes);
+        let header = records.next().ok_or_else(|| String::from("empty replay"))?;
+        let header: ReplayHeader = bitcode::decode(header).map_err(|e| e.to_string())?;
+        if header.version != VERSION {
+            return Err(format!(
+                "replay version {} is unsupported (expected {})",
+                header.version, VERSION
+            ));
+        }
+        Balance::set(header.balance);
+        Generation::set(header.generation);
+
+        Ok(Self {
+            frames: records.map(Box::from).collect(),
+            world: World::default(),
+            position: 0,
+        })
+    }
+
+    /// Number of recorded ticks.
+    pub fn ticks(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The [`World`] after [`Self::position`] ticks.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Simulates the next tick. Returns false if the replay is over.
+    pub fn step(&mut self, mut on_info: impl FnMut(InfoEvent)) -> Result<bool, String> {
+        let Some(frame) = self.frames.get(self.position) else {
+            return Ok(false);
+        };
+        let update: Update = bitcode::decode(frame)
+            .map_err(|e| format!("tick {} is corrupt: {e}", self.position))?;
+        self.world.apply_owned(update, &mut on_info);
+        self.position += 1;
+        Ok(true)
+    }
+
+    /// Jumps to after `position` ticks (clamped to [`Self::ticks`]). Jumping backwards
+    /// re-simulates from the start.
+    pub fn seek(
+        &mut self,
+        position: usize,
+        mut on_info: impl FnMut(InfoEvent),
+    ) -> Result<(), String> {
+        let position = position.min(self.ticks());
+        if position < self.position {
+            self.world = World::default();
+            self.position = 0;
+        }
+        while self.position < position {
+            self.step(&mut on_info)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterates length prefixed records. Stops at a truncated record, which happens if the server was
+/// killed while writing it.
+struct Records<'a>(&'a [u8]);
+
+impl<'a> Iterator for Records<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.0.get(..4)?;
+        let end = 4 + u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        let record = self.0.get(4..end)?;
+        self.0 = &self.0[end..];
+        Some(record)
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use crate::chunk::{Chunk, ChunkId, ChunkInput, RelativeTowerId};
+    use crate::info::InfoEvent;
+    use crate::replay::{ReplayPlayer, ReplayRecorder};
+    use crate::world::World;
+    use common_util::actor2::WorldTick;
+
+    #[test]
+    fn replay() {
+        let path = std::env::temp_dir().join(format!("replay-{}.bin", std::process::id()));
+        let mut world = World::new();
+        let mut recorder = ReplayRecorder::create(&path).unwrap();
+
+        for tick in 0..20 {
+            world.tick_before_inputs(&mut |_: InfoEvent| {});
+            if tick == 5 {
+                let tower_ids = (0..Chunk::AREA)
+                    .map(|i| RelativeTowerId(i as u8))
+                    .collect();
+                let input = ChunkInput::Generate { tower_ids };
+                world.dispatch_chunk_input(ChunkId::new(1, 1), input, |_| {});
+            }
+            world.tick_after_inputs(&mut |_: InfoEvent| {});
+            recorder.record(&world).unwrap();
+            world.post_update();
+        }
+        drop(recorder);
+
+        let mut replay = ReplayPlayer::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.ticks(), 20);
+
+        for position in [20, 3, 20] {
+            replay.seek(position, |_| {}).unwrap();
+            assert_eq!(replay.position(), position);
+        }
+        assert_eq!(replay.world().singleton().tick, world.singleton().tick);
+        for (chunk_id, chunk) in world.chunk.iter_chunks() {
+            assert_eq!(replay.world().chunk.get_chunk(chunk_id), Some(chunk));
+        }
+        assert!(!replay.step(|_| {}).unwrap());
+    }
+}
@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::enum_array::EnumArray;
+use crate::ticks::Ticks;
+use crate::unit::Unit;
+use core_protocol::prelude::*;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use strum::{EnumIter, IntoEnumIterator};
+
+/// Permanent (until death) improvements a player can buy with points produced by labs.
+#[derive(
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    EnumIter,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum Research {
+    /// Each level lets towers hold 10% more shields, up to [`Unit::max_overflow`] more (see
+    /// [`Research::capacity_bonus`]).
+    ShieldCapacity,
+    /// Each level makes forces one speed tier faster.
+    UnitSpeed,
+}
+
+pub type ResearchArray<V> = EnumArray<Research, V, { std::mem::variant_count::<Research>() }>;
+
+impl Research {
+    /// How often each active lab produces a research point.
+    pub const POINTS_PERIOD: Ticks = Ticks::from_whole_secs(10);
+
+    pub fn iter() -> impl Iterator<Item = Self> + 'static {
+        <Self as IntoEnumIterator>::iter()
+    }
+
+    pub fn max_level(self) -> u8 {
+        match self {
+            Self::ShieldCapacity => 3,
+            Self::UnitSpeed => 1,
+        }
+    }
+
+    /// Points required to research the level after `level`.
+    pub fn cost(self, level: u8) -> u16 {
+        match self {
+            Self::ShieldCapacity => 10 * (level as u16 + 1),
+            Self::UnitSpeed => 40,
+        }
+    }
+
+    /// Extra capacity of `unit` in a tower that has `capacity` of it.
+    ///
+    /// Never more than [`Unit::max_overflow`], because
+    /// [`Units::reconcile`][`crate::units::Units::reconcile`] doesn't know about research and only
+    /// lets a player's tower keep that many units beyond its base capacity. A larger bonus would be
+    /// lost whenever the tower is reconciled, such as when it's upgraded.
+    pub fn capacity_bonus(levels: &ResearchArray<u8>, unit: Unit, capacity: usize) -> usize {
+        if unit != Unit::Shield {
+            return 0;
+        }
+        (capacity * levels[Self::ShieldCapacity] as usize / 10).min(unit.max_overflow())
+    }
+
+    /// Extra progress per tick of forces.
+    pub fn speed_bonus(levels: &ResearchArray<u8>) -> u8 {
+        levels[Self::UnitSpeed]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::research::{Research, ResearchArray};
+    use crate::unit::Unit;
+
+    #[test]
+    fn capacity_bonus() {
+        let mut levels = ResearchArray::<u8>::default();
+        assert_eq!(Research::capacity_bonus(&levels, Unit::Shield, 40), 0);
+        levels[Research::ShieldCapacity] = 2;
+        assert_eq!(Research::capacity_bonus(&levels, Unit::Shield, 40), 8);
+        assert_eq!(Research::capacity_bonus(&levels, Unit::Soldier, 40), 0);
+        assert_eq!(
+            Research::capacity_bonus(&levels, Unit::Shield, 1000),
+            Unit::Shield.max_overflow()
+        );
+    }
+}
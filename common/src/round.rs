@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::tower::TowerId;
+use core_protocol::prelude::*;
+use diff::Diff;
+
+/// Progress of the current round, from the point of view of a player and their allies. All zero
+/// if rounds are disabled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Diff)]
+#[diff(attr(#[derive(Debug, Serialize, Deserialize)]))]
+pub struct Round {
+    /// Seconds until the round ends.
+    pub seconds_remaining: u16,
+    /// Percent of towers controlled.
+    pub tower_percent: u8,
+    /// Percent of towers that wins the round.
+    pub tower_percent_goal: u8,
+    /// Seconds the center has been held.
+    pub center_seconds: u16,
+    /// Seconds of holding the center that wins the round.
+    pub center_seconds_goal: u16,
+}
+
+impl Round {
+    /// Radius of the region around [`World::center`][`crate::world::World::center`] that must be
+    /// held.
+    pub const CENTER_RADIUS: u16 = 8 * TowerId::CONVERSION;
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::enum_array::EnumArray;
+use crate::ticks::Ticks;
+use crate::tower::TowerId;
+use crate::world::World;
+use core_protocol::prelude::*;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use strum::{EnumIter, IntoEnumIterator};
+
+/// Actions a player can take with their ruler, each with its own cooldown.
+#[derive(
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    EnumIter,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum RulerAbility {
+    /// Moves the ruler to an owned tower within [`Self::TELEPORT_DISTANCE`] instantly, so it can't
+    /// be intercepted.
+    Teleport,
+    /// Overfills the ruler's tower with shields, which decay back to capacity.
+    ShieldSurge,
+    /// Deploys the units of owned towers within [`Self::RALLY_DISTANCE`] to the ruler.
+    Rally,
+}
+
+pub type RulerAbilityArray<V> =
+    EnumArray<RulerAbility, V, { std::mem::variant_count::<RulerAbility>() }>;
+
+impl RulerAbility {
+    /// Furthest the ruler can teleport.
+    pub const TELEPORT_DISTANCE: u32 = World::MAX_ROAD_LENGTH * 2;
+    /// Furthest towers that are rallied to the ruler.
+    pub const RALLY_DISTANCE: u32 = World::MAX_ROAD_LENGTH * 3;
+
+    pub fn iter() -> impl Iterator<Item = Self> + 'static {
+        <Self as IntoEnumIterator>::iter()
+    }
+
+    /// How long until the ability can be used again.
+    pub fn cooldown(self) -> Ticks {
+        match self {
+            Self::Teleport => Ticks::from_whole_secs(90),
+            Self::ShieldSurge => Ticks::from_whole_secs(300),
+            Self::Rally => Ticks::from_whole_secs(120),
+        }
+    }
+
+    /// Whether the ability is used on another tower, rather than the ruler's.
+    pub fn has_target(self) -> bool {
+        matches!(self, Self::Teleport)
+    }
+
+    /// Returns true if a ruler at `ruler` can teleport to `destination`, ignoring who owns it.
+    pub fn can_teleport(ruler: TowerId, destination: TowerId) -> bool {
+        ruler != destination && ruler.distance(destination) <= Self::TELEPORT_DISTANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ruler::RulerAbility;
+    use crate::tower::TowerId;
+
+    #[test]
+    fn can_teleport() {
+        let ruler = TowerId::new(10, 10);
+        assert!(!RulerAbility::can_teleport(ruler, ruler));
+        assert!(RulerAbility::can_teleport(ruler, TowerId::new(11, 10)));
+        assert!(RulerAbility::can_teleport(
+            ruler,
+            TowerId::new(10, 10 + RulerAbility::TELEPORT_DISTANCE as u16)
+        ));
+        assert!(!RulerAbility::can_teleport(
+            ruler,
+            TowerId::new(10, 11 + RulerAbility::TELEPORT_DISTANCE as u16)
+        ));
+    }
+}
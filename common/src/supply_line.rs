@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::force::{Deployment, Path};
+use crate::tower::Tower;
+use crate::unit::Unit;
+use crate::units::Units;
+use core_protocol::prelude::*;
+
+/// One of the routes a [`Tower`] sends its units along when it can't generate or is overflowing.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct SupplyLine {
+    pub path: Path,
+    /// Share of the tower's deployments, relative to its other supply lines.
+    pub weight: u8,
+    /// If [`Some`], only this unit is sent.
+    pub unit: Option<Unit>,
+    /// If [`Some`], units are only sent while the destination has less than this percent of its
+    /// capacity for them.
+    pub below_percent: Option<u8>,
+    /// The destination was at least `below_percent` full when last checked.
+    pub paused: bool,
+    /// The path broke, so the line will be removed next tick.
+    pub halted: bool,
+}
+
+impl SupplyLine {
+    pub const MAX_WEIGHT: u8 = 9;
+
+    /// A supply line that sends every unit whenever it can.
+    pub fn new(path: Path) -> Self {
+        Self {
+            path,
+            weight: 1,
+            unit: None,
+            below_percent: None,
+            paused: false,
+            halted: false,
+        }
+    }
+
+    /// Checks everything but the path, which must be validated with [`Path::validate`].
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !(1..=Self::MAX_WEIGHT).contains(&self.weight) {
+            return Err("invalid weight");
+        }
+        if self
+            .below_percent
+            .is_some_and(|percent| !(1..=100).contains(&percent))
+        {
+            return Err("invalid percent");
+        }
+        if self
+            .unit
+            .is_some_and(|unit| !unit.is_mobile(None) || unit == Unit::Ruler)
+        {
+            return Err("invalid unit");
+        }
+        Ok(())
+    }
+
+    /// Returns true if a force of `units` may pass along the line.
+    pub fn carries(&self, units: &Units) -> bool {
+        self.unit
+            .map_or(true, |filter| units.iter().all(|(unit, _)| unit == filter))
+    }
+
+    /// Which of `tower`'s units the line sends.
+    pub fn deployment(&self, tower: &Tower) -> Deployment {
+        if let Some(unit) = self.unit {
+            let mut units = Units::default();
+            units.add(unit, tower.force_units().available(unit));
+            Deployment::Units(units)
+        } else {
+            Deployment::All
+        }
+    }
+
+    /// Returns true if `destination` has reached `below_percent` of its capacity for the units
+    /// the line sends.
+    pub fn is_full(&self, destination: &Tower) -> bool {
+        let Some(below_percent) = self.below_percent else {
+            return false;
+        };
+        let (mut count, mut capacity) = (0, 0);
+        for unit in Unit::iter() {
+            if self
+                .unit
+                .map_or(unit == Unit::Ruler, |filter| unit != filter)
+            {
+                continue;
+            }
+            count += destination.units.available(unit);
+            capacity += destination
+                .units
+                .capacity(unit, Some(destination.tower_type));
+        }
+        count * 100 >= capacity * below_percent as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::force::Path;
+    use crate::supply_line::SupplyLine;
+    use crate::tower::{Tower, TowerId, TowerType};
+    use crate::unit::Unit;
+
+    #[test]
+    fn next_supply_line() {
+        let mut tower = Tower::with_type(TowerType::Barracks);
+        let source = TowerId::new(0, 0);
+        for (i, weight) in [1, 2].into_iter().enumerate() {
+            let path = Path::new(vec![source, TowerId::new(1, i as u16)]);
+            tower.supply_lines.push(SupplyLine {
+                weight,
+                ..SupplyLine::new(path)
+            });
+        }
+
+        let mut picks = [0; 2];
+        for _ in 0..30 {
+            let index = tower.next_supply_line(|_| true).unwrap();
+            picks[index] += 1;
+            tower.supply_turn = tower.supply_turn.wrapping_add(1);
+        }
+        assert_eq!(picks, [10, 20]);
+
+        tower.supply_lines[1].paused = true;
+        assert_eq!(tower.next_supply_line(|_| true), Some(0));
+        tower.supply_lines[0].unit = Some(Unit::Tank);
+        assert_eq!(tower.next_supply_line(|l| l.unit.is_none()), None);
+    }
+}
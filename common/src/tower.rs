@@ -1,10 +1,17 @@
+use crate::balance::Balance;
 use crate::enum_array::EnumArray;
-use crate::force::{Force, Path};
+use crate::force::{Deployment, Force};
+use crate::generation::Generation;
+use crate::research::ResearchArray;
+use crate::supply_line::SupplyLine;
 use crate::ticks::Ticks;
 use crate::unit::Unit;
 use crate::units::Units;
 use core_protocol::id::PlayerId;
 use core_protocol::prelude::*;
+#[cfg(any(test, feature = "server"))]
+pub(crate) use connectivity::ConnectivityTable;
+pub(crate) use id::NeighborTable;
 pub use id::TowerId;
 use macros::TowerTypeData;
 pub use map::TowerMap;
@@ -55,11 +62,14 @@ pub struct Tower {
     /// Mirrors inbound forces of opposing tower. When they would arrive, they are discarded.
     pub outbound_forces: Vec<Force>,
     /// Where the tower will send its units when it can't generate or is overflowing.
-    pub supply_line: Option<Path>,
+    pub supply_lines: Vec<SupplyLine>,
+    /// Advances whenever a supply line is used, to share units between them.
+    pub supply_turn: u8,
 }
 
 impl Tower {
     pub const RULER_SHIELD_BOOST: usize = 10;
+    pub const MAX_SUPPLY_LINES: usize = 4;
 
     pub fn new(tower_id: TowerId) -> Self {
         Self::with_type(tower_id.tower_type())
@@ -73,7 +83,8 @@ impl Tower {
             delay: None,
             inbound_forces: Vec::new(),
             outbound_forces: Vec::new(),
-            supply_line: None,
+            supply_lines: Vec::new(),
+            supply_turn: 0,
         }
     }
 
@@ -112,9 +123,44 @@ impl Tower {
         ret
     }
 
-    /// Takes all units that can be deployed in a force.
-    pub fn take_force_units(&mut self) -> Units {
-        let ret = self.force_units();
+    /// Gets the [`Self::force_units`] selected by `deployment`, or an error if it selects nothing
+    /// or more than is available.
+    pub fn deployment_units(&self, deployment: &Deployment) -> Result<Units, &'static str> {
+        let available = self.force_units();
+        let units = match deployment {
+            Deployment::All => available,
+            &Deployment::Percent(percent) => {
+                if !(1..=100).contains(&percent) {
+                    return Err("invalid percent");
+                }
+                let mut units = Units::default();
+                for (unit, count) in available.iter() {
+                    if unit != Unit::Ruler {
+                        units.add(unit, count * percent as usize / 100);
+                    }
+                }
+                units
+            }
+            Deployment::Units(units) => {
+                if units
+                    .iter()
+                    .any(|(unit, count)| count > available.available(unit))
+                {
+                    return Err("units not available");
+                }
+                units.clone()
+            }
+        };
+        if units.is_empty() {
+            return Err("empty force");
+        }
+        Ok(units)
+    }
+
+    /// Takes the units selected by `deployment` (see [`Self::deployment_units`]), or none if it
+    /// is invalid.
+    pub fn take_force_units(&mut self, deployment: &Deployment) -> Units {
+        let ret = self.deployment_units(deployment).unwrap_or_default();
         for (unit, count) in ret.iter() {
             debug_assert!(unit.is_mobile(Some(self.tower_type)));
 
@@ -125,11 +171,17 @@ impl Tower {
     }
 
     /// Returns the amount of mobile units diminished.
-    pub(crate) fn diminish_units_if_dead_or_overflow(&mut self) -> usize {
+    pub(crate) fn diminish_units_if_dead_or_overflow(
+        &mut self,
+        research: &ResearchArray<u8>,
+    ) -> usize {
         let mut units = 0;
         for unit in Unit::iter() {
             if self.player_id.is_none()
-                || self.units.available(unit) > self.units.capacity(unit, Some(self.tower_type))
+                || self.units.available(unit)
+                    > self
+                        .units
+                        .researched_capacity(unit, Some(self.tower_type), research)
             {
                 let subtracted = self.units.subtract(unit, 1);
                 if unit.is_mobile(Some(self.tower_type)) {
@@ -162,6 +214,27 @@ impl Tower {
         false
     }
 
+    /// Picks the index of the supply line to use next by weighted round robin, skipping paused
+    /// lines and those that aren't `usable`. Advance [`Self::supply_turn`] after using it.
+    pub fn next_supply_line(&self, usable: impl Fn(&SupplyLine) -> bool) -> Option<usize> {
+        let is_usable = |line: &SupplyLine| !line.paused && !line.halted && usable(line);
+        let total: u32 = self
+            .supply_lines
+            .iter()
+            .filter(|line| is_usable(line))
+            .map(|line| line.weight as u32)
+            .sum();
+        let mut slot = (self.supply_turn as u32).checked_rem(total)?;
+        self.supply_lines.iter().position(|line| {
+            if !is_usable(line) {
+                return false;
+            }
+            let found = slot < line.weight as u32;
+            slot = slot.saturating_sub(line.weight as u32);
+            found
+        })
+    }
+
     pub fn reconcile_units(&mut self) {
         self.units
             .reconcile(self.tower_type, self.player_id.is_some());
@@ -173,7 +246,7 @@ impl Tower {
         Self::set_player_id_inner(
             &mut self.player_id,
             &self.units,
-            &mut self.supply_line,
+            &mut self.supply_lines,
             player_id,
         )
     }
@@ -182,18 +255,18 @@ impl Tower {
     pub fn set_player_id_inner(
         current: &mut Option<PlayerId>,
         units: &Units,
-        supply: &mut Option<Path>,
+        supply: &mut Vec<SupplyLine>,
         next: Option<PlayerId>,
     ) {
         debug_assert_ne!(*current, next);
         match (*current, next) {
             (None, Some(_)) => {
-                debug_assert!(supply.is_none());
+                debug_assert!(supply.is_empty());
                 debug_assert!(!units.contains(Unit::Ruler));
                 debug_assert!(!units.contains(Unit::Shield));
             }
             (Some(_), _) => {
-                *supply = None;
+                supply.clear();
                 debug_assert!(!units.contains(Unit::Ruler));
                 debug_assert!(!units.contains(Unit::Shield));
             }
@@ -375,6 +448,37 @@ pub enum TowerType {
 pub type TowerArray<V> = EnumArray<TowerType, V, { std::mem::variant_count::<TowerType>() }>;
 
 impl TowerType {
+    /// Doesn't count ruler boost.
+    pub fn raw_unit_capacity(self, unit: Unit) -> usize {
+        Balance::get().tower(self).capacity[unit] as usize
+    }
+
+    pub fn unit_generation(self, unit: Unit) -> Option<Ticks> {
+        Balance::get().tower(self).generate[unit]
+    }
+
+    /// Upgrading to this tower requires this much of this other tower.
+    pub fn prerequisite(self, tower_type: TowerType) -> u8 {
+        Balance::get().tower(self).prerequisites[tower_type]
+    }
+
+    /// How long it should take to upgrade/downgrade to this tower.
+    pub fn delay(self) -> Ticks {
+        Balance::get().tower(self).delay
+    }
+
+    pub fn sensor_radius(self) -> u16 {
+        Balance::get().tower(self).sensor_radius
+    }
+
+    pub fn score_weight(self) -> u32 {
+        Balance::get().tower(self).score_weight
+    }
+
+    pub fn is_spawnable(self) -> bool {
+        Balance::get().tower(self).spawnable
+    }
+
     pub fn is_large(self) -> bool {
         //false
         matches!(
@@ -485,29 +589,29 @@ impl TowerType {
         <Self as IntoEnumIterator>::iter()
     }
 
-    pub(crate) fn generate(hash: u8, noise_value: f64) -> Self {
-        const AQUATIC_THRESHOLD: f64 = -0.25;
-        let is_aquatic = noise_value < AQUATIC_THRESHOLD;
-    
-        let filtered_items: Vec<_> = TowerType::iter()
-            .filter(|t| t.downgrade().is_none())
-            .filter(|t| t.is_aquatic() == is_aquatic)
-            .collect();
-    
-        let count = filtered_items.len();
-        if count == 0 {
-            panic!("Filtered iterator is empty");
+    /// Picks a weighted random aquatic or land tower type according to [`Generation::weights`].
+    pub(crate) fn generate(hash: u8, is_aquatic: bool, generation: &Generation) -> Self {
+        // Validation guarantees a nonzero total weight.
+        let mut index = hash as u32 % generation.total_weight(is_aquatic);
+        for (tower_type, &weight) in generation.weights.iter() {
+            if tower_type.is_aquatic() != is_aquatic {
+                continue;
+            }
+            match index.checked_sub(weight as u32) {
+                Some(remaining) => index = remaining,
+                None => return tower_type,
+            }
         }
-    
-        let index = hash as usize % count;
-        filtered_items[index].clone()
-    }    
+        unreachable!("index < total weight")
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::force::Deployment;
     use crate::tower::{fast_integer_sqrt, integer_sqrt, Tower, TowerId, TowerType};
     use crate::unit::Unit;
+    use crate::units::Units;
     use rand::{thread_rng, Rng};
     use test::{black_box, Bencher};
 
@@ -548,6 +652,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deployment_units() {
+        let mut tower = Tower::with_type(TowerType::Barracks);
+        tower.units.add(Unit::Soldier, 8);
+        let soldiers = |count| {
+            let mut units = Units::default();
+            units.add(Unit::Soldier, count);
+            units
+        };
+
+        assert_eq!(tower.deployment_units(&Deployment::All), Ok(soldiers(8)));
+        assert_eq!(
+            tower.deployment_units(&Deployment::Percent(25)),
+            Ok(soldiers(2))
+        );
+        assert_eq!(
+            tower.deployment_units(&Deployment::Percent(20)),
+            Ok(soldiers(1))
+        );
+        assert!(tower.deployment_units(&Deployment::Percent(10)).is_err());
+        assert!(tower.deployment_units(&Deployment::Percent(0)).is_err());
+        assert!(tower.deployment_units(&Deployment::Percent(101)).is_err());
+        let three = Deployment::Units(soldiers(3));
+        assert_eq!(tower.deployment_units(&three), Ok(soldiers(3)));
+        let nine = Deployment::Units(soldiers(9));
+        assert!(tower.deployment_units(&nine).is_err());
+        assert!(tower
+            .deployment_units(&Deployment::Units(Units::default()))
+            .is_err());
+
+        assert_eq!(
+            tower.take_force_units(&Deployment::Percent(50)),
+            soldiers(4)
+        );
+        assert_eq!(tower.units.available(Unit::Soldier), 4);
+        let clamped = nine.clamp(&tower.force_units());
+        assert_eq!(tower.deployment_units(&clamped), Ok(soldiers(4)));
+    }
+
+    #[test]
+    fn deployment_percent_with_ruler() {
+        let mut tower = Tower::with_type(TowerType::Barracks);
+        tower.units.add(Unit::Ruler, 1);
+        tower.units.add(Unit::Soldier, 50);
+        let soldiers = |count| {
+            let mut units = Units::default();
+            units.add(Unit::Soldier, count);
+            units
+        };
+
+        assert!(tower.deployment_units(&Deployment::Percent(0)).is_err());
+        // Rounds down, so 1% of 50 is nothing.
+        assert!(tower.deployment_units(&Deployment::Percent(1)).is_err());
+        assert_eq!(
+            tower.deployment_units(&Deployment::Percent(2)),
+            Ok(soldiers(1))
+        );
+        assert_eq!(
+            tower.deployment_units(&Deployment::Percent(100)),
+            Ok(soldiers(50))
+        );
+
+        // Only soldiers are taken, so the ruler stays.
+        tower.take_force_units(&Deployment::Percent(100));
+        assert!(tower.units.has_ruler());
+        assert!(tower.deployment_units(&Deployment::Percent(100)).is_err());
+    }
+
     #[test]
     fn test_integer_sqrt() {
         assert_eq!(integer_sqrt(u64::MAX), u32::MAX);
@@ -1,23 +1,30 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::generation::GenerationState;
 use crate::tower::id::TowerNeighbor;
 use crate::tower::TowerId;
 use crate::world::{World, WorldChunks};
 use std::collections::VecDeque;
-use std::sync::LazyLock;
 
-static CONNECTIVITY_TABLE: LazyLock<Box<ConnectivityTable>> = LazyLock::new(ConnectivityTable::new);
-
-struct ConnectivityTable([[Option<TowerNeighbor>; WorldChunks::SIZE]; WorldChunks::SIZE]);
+/// Depends on neighbors, so is owned by [`GenerationState`].
+pub(crate) struct ConnectivityTable {
+    /// Row major, [`WorldChunks::size`] by [`WorldChunks::size`].
+    table: Box<[Option<TowerNeighbor>]>,
+    size: usize,
+}
 
 impl ConnectivityTable {
-    fn new() -> Box<Self> {
-        let mut me = Box::new(Self([[None; WorldChunks::SIZE]; WorldChunks::SIZE]));
+    pub(crate) fn new() -> Self {
+        let size = WorldChunks::size();
+        let mut me = Self {
+            table: vec![None; size * size].into_boxed_slice(),
+            size,
+        };
         let mut frontier = VecDeque::with_capacity(2048);
 
         // The world center connects to one of its neighbors arbitrarily for simplicity.
-        let tower_id = World::CENTER;
+        let tower_id = World::center();
         let neighbor = tower_id.neighbor_to_unchecked(tower_id.neighbors().next().unwrap());
         *me.get_mut(tower_id) = Some(neighbor);
         frontier.push_back(tower_id);
@@ -38,17 +45,20 @@ impl ConnectivityTable {
     }
 
     fn get(&self, tower_id: TowerId) -> Option<TowerNeighbor> {
-        self.0[tower_id.y as usize][tower_id.x as usize]
+        let (x, y) = (tower_id.x as usize, tower_id.y as usize);
+        (x < self.size && y < self.size)
+            .then(|| self.table[x + y * self.size])
+            .flatten()
     }
 
     fn get_mut(&mut self, tower_id: TowerId) -> &mut Option<TowerNeighbor> {
-        &mut self.0[tower_id.y as usize][tower_id.x as usize]
+        &mut self.table[tower_id.x as usize + tower_id.y as usize * self.size]
     }
 }
 
 impl TowerId {
     pub fn connectivity(self) -> Option<TowerNeighbor> {
-        CONNECTIVITY_TABLE.get(self)
+        GenerationState::get().connectivity().get(self)
     }
 
     pub fn connectivity_id(self) -> Option<TowerId> {
@@ -70,7 +80,7 @@ mod tests {
 
         for _ in 0..1000 {
             let mut tower_id = TowerId(
-                (World::CENTER.0.as_i16vec2()
+                (World::center().0.as_i16vec2()
                     + I16Vec2::new(rng.gen_range(-100..100), rng.gen_range(-100..100)))
                 .as_u16vec2(),
             );
@@ -84,7 +94,7 @@ mod tests {
                     panic!("cycle {} {tower_id:?}", tower_ids.len());
                 }
 
-                if tower_id == World::CENTER {
+                if tower_id == World::center() {
                     break; // We've reached the center.
                 }
 
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::chunk::{ChunkId, RelativeTowerId};
+use crate::generation::{Generation, GenerationState};
 use crate::tower::{integer_sqrt, TowerRectangle, TowerType};
 use crate::world::{World, WorldChunks};
 use common_util::x_vec2::{I16Vec2, U16Vec2, U8Vec2};
@@ -9,7 +10,6 @@ use core_protocol::prelude::*;
 use glam::Vec2;
 use std::ops::{Deref, DerefMut};
 use std::sync::LazyLock;
-use noise::{NoiseFn, Perlin};
 
 // Use 32 bit fnv hash because it's fast.
 const FNV_OFFSET: u32 = 2166316261;
@@ -41,12 +41,6 @@ pub struct TowerId(
     /*#[bitcode_hint(expected_range = "0..511")]*/ pub U16Vec2,
 );
 
-fn simple_noise(x: i32, y: i32) -> f32 {
-    let perlin = Perlin::new(314159);
-    let noise_value = perlin.get([x as f64 * 0.15, y as f64 * 0.15]);
-    noise_value as f32
-}
-
 impl TowerId {
     pub const CONVERSION: u16 = 5;
 
@@ -66,15 +60,20 @@ impl TowerId {
             write_u8(bytes[0]);
             write_u8(bytes[1]);
         };
-        let c = self.0.wrapping_add(U16Vec2::splat(27182));
+        let c = self.noise_position();
         write_u16(c.x);
         write_u16(c.y);
-    
-        let noise_value = simple_noise(c.x as i32, c.y as i32);
-    
+
+        let state = GenerationState::get();
+        let is_aquatic = state.is_aquatic(state.noise(c));
         let hash = condense!(condense!(hash, u16), u8);
-    
-        TowerType::generate(hash, noise_value.into())
+
+        TowerType::generate(hash, is_aquatic, state.generation())
+    }
+
+    /// Position that is hashed and sampled from noise to determine [`Self::tower_type`].
+    pub(crate) fn noise_position(self) -> U16Vec2 {
+        self.0.wrapping_add(U16Vec2::splat(27182))
     }
 
     #[inline]
@@ -83,7 +82,7 @@ impl TowerId {
     }
 
     pub fn is_valid(self) -> bool {
-        self.x <= WorldChunks::SIZE as u16 && self.y <= WorldChunks::SIZE as u16
+        self.x < WorldChunks::size() as u16 && self.y < WorldChunks::size() as u16
     }
 
     pub fn offset(self) -> U16Vec2 {
@@ -169,7 +168,7 @@ impl TowerId {
 
     /// Same as [`Self::neighbors`], but provides [`TowerNeighbor`] as well.
     pub fn neighbors_enumerated(self) -> impl Iterator<Item = (TowerNeighbor, TowerId)> + 'static {
-        let mut bits = NEIGHBOR_TABLE.neighbors(self);
+        let mut bits = GenerationState::get().neighbors().neighbors(self);
         std::iter::from_fn(move || {
             (bits != 0).then(|| {
                 let i = bits.trailing_zeros();
@@ -207,7 +206,9 @@ impl TowerId {
     pub fn neighbor_to(self, other_id: TowerId) -> Option<TowerNeighbor> {
         TowerNeighbor::try_from(other_id.0.as_i16vec2() - self.0.as_i16vec2())
             .ok()
-            .filter(|&n| NEIGHBOR_TABLE.neighbors(self) & (1 << n as u8) != 0)
+            .filter(|&n| {
+                GenerationState::get().neighbors().neighbors(self) & (1 << n as u8) != 0
+            })
     }
 
     /// Faster than [`neighbor_to`][`Self::neighbor_to`], but assumes that `self` and `other_id` are
@@ -302,7 +303,9 @@ impl diff::Diff for TowerId {
     }
 
     fn identity() -> Self {
-        World::CENTER
+        // Must not depend on the world size, which the client may not know yet.
+        let center = Generation::DEFAULT_SIZE / 2;
+        Self::new(center, center)
     }
 }
 
@@ -391,14 +394,17 @@ impl From<TowerNeighbor> for I16Vec2 {
     }
 }
 
+/// Offsets repeat beyond this, so they don't depend on the world size.
+const OFFSET_TABLE_SIZE: usize = 256;
+
 struct OffsetTable {
-    offsets: [[u8; WorldChunks::SIZE]; WorldChunks::SIZE],
+    offsets: [[u8; OFFSET_TABLE_SIZE]; OFFSET_TABLE_SIZE],
 }
 static OFFSET_TABLE: LazyLock<Box<OffsetTable>> = LazyLock::new(OffsetTable::new);
 impl OffsetTable {
     fn new() -> Box<Self> {
         let mut me = Box::new(OffsetTable {
-            offsets: [[0; WorldChunks::SIZE]; WorldChunks::SIZE],
+            offsets: [[0; OFFSET_TABLE_SIZE]; OFFSET_TABLE_SIZE],
         });
         for (y, v) in me.offsets.iter_mut().enumerate() {
             for (x, v) in v.iter_mut().enumerate() {
@@ -425,28 +431,29 @@ impl OffsetTable {
     }
 
     fn offset(&self, tower_id: TowerId) -> U16Vec2 {
-        // Mask for now to avoid out of bounds (TODO don't create out of bounds TowerIds).
-        const MASK: usize = WorldChunks::SIZE - 1;
+        const MASK: usize = OFFSET_TABLE_SIZE - 1;
         const _: () = assert!((MASK + 1).is_power_of_two());
         let offset = self.offsets[tower_id.y as usize & MASK][tower_id.x as usize & MASK];
         U16Vec2::new((offset & 15) as u16, (offset >> 4) as u16)
     }
 }
 
-struct NeighborTable {
-    neighbors: [[u8; WorldChunks::SIZE]; WorldChunks::SIZE],
+/// Depends on [`TowerId::tower_type`] and [`WorldChunks::size`], so is owned by
+/// [`GenerationState`].
+pub(crate) struct NeighborTable {
+    /// Row major, [`WorldChunks::size`] by [`WorldChunks::size`].
+    neighbors: Box<[u8]>,
+    size: usize,
 }
-static NEIGHBOR_TABLE: LazyLock<Box<NeighborTable>> = LazyLock::new(NeighborTable::new);
 impl NeighborTable {
-    fn new() -> Box<Self> {
-        let mut me = Box::new(NeighborTable {
-            neighbors: [[0; WorldChunks::SIZE]; WorldChunks::SIZE],
-        });
+    pub(crate) fn new() -> Self {
+        let size = WorldChunks::size();
+        let rectangle = WorldChunks::rectangle();
 
         // This might return Some for towers outside 8 surrounding tower ids, but we only call
         // it on the 8 possible neighbors.
-        fn are_neighbors(a: TowerId, b: TowerId) -> bool {
-            if a == b || !WorldChunks::RECTANGLE.contains(b) {
+        let are_neighbors = |a: TowerId, b: TowerId| -> bool {
+            if a == b || !rectangle.contains(b) {
                 return false; // Same or outside world.
             }
             let distance = a.distance_squared(b);
@@ -477,11 +484,11 @@ impl NeighborTable {
                 }
             }
             true
-        }
+        };
 
-        for (y, v) in me.neighbors.iter_mut().enumerate() {
-            for (x, v) in v.iter_mut().enumerate() {
-                let tower_id = TowerId::new(x as u16, y as u16);
+        let neighbors = (0..size * size)
+            .map(|i| {
+                let tower_id = TowerId::new((i % size) as u16, (i / size) as u16);
                 let mut bits = 0;
 
                 for n in TowerNeighbor::iter() {
@@ -492,22 +499,29 @@ impl NeighborTable {
                     let are_neighbors = are_neighbors(tower_id, other_id);
                     bits |= (are_neighbors as u8) << n as u8;
                 }
-                *v = bits;
-            }
-        }
-        me
+                bits
+            })
+            .collect();
+        Self { neighbors, size }
     }
 
-    fn neighbors(&self, tower_id: TowerId) -> u8 {
-        self.neighbors[tower_id.y as usize][tower_id.x as usize]
+    /// Towers outside the world have no neighbors.
+    pub(crate) fn neighbors(&self, tower_id: TowerId) -> u8 {
+        let (x, y) = (tower_id.x as usize, tower_id.y as usize);
+        if x < self.size && y < self.size {
+            self.neighbors[x + y * self.size]
+        } else {
+            0
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::generation::Generation;
     use crate::tower::id::{NeighborTable, OffsetTable, TowerNeighbor};
     use crate::tower::TowerId;
-    use crate::world::World;
+    use crate::world::{World, WorldChunks};
     use std::hint::black_box;
     use test::Bencher;
 
@@ -523,8 +537,23 @@ mod tests {
         test(TowerNeighbor::SE, TowerNeighbor::NW);
     }
 
+    #[test]
+    fn is_valid() {
+        let generation = Generation {
+            size: Generation::DEFAULT_SIZE * 2,
+            ..Default::default()
+        };
+        Generation::with(generation, || {
+            let size = WorldChunks::size() as u16;
+            assert_eq!(size, Generation::DEFAULT_SIZE * 2);
+            assert!(TowerId::new(size - 1, size - 1).is_valid());
+            assert!(!TowerId::new(size, 0).is_valid());
+            assert!(!TowerId::new(0, size).is_valid());
+        });
+    }
+
     fn neighbor_pair() -> (TowerId, TowerId) {
-        let tower_id = World::CENTER;
+        let tower_id = World::center();
         (tower_id, tower_id.neighbors().next().unwrap())
     }
 
@@ -540,7 +569,7 @@ mod tests {
 
     #[bench]
     fn bench_neighbors(b: &mut Bencher) {
-        let tower_id = World::CENTER;
+        let tower_id = World::center();
         b.iter(|| {
             for other_id in black_box(tower_id).neighbors() {
                 black_box(other_id);
@@ -67,7 +67,10 @@ impl TowerRectangle {
     // TODO only allow 1 invalid rect?
     pub fn invalid() -> Self {
         Self {
-            bottom_left: TowerId::new(WorldChunks::SIZE as u16 - 1, WorldChunks::SIZE as u16 - 1),
+            bottom_left: TowerId::new(
+                WorldChunks::size() as u16 - 1,
+                WorldChunks::size() as u16 - 1,
+            ),
             top_right: TowerId::new(0, 0),
         }
     }
@@ -1,13 +1,14 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::balance::Balance;
 use crate::enum_array::EnumArray;
 use crate::field::Field;
 use crate::tower::TowerType;
 use crate::world::World;
 use core_protocol::prelude::*;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use strum::{EnumIter, IntoEnumIterator};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
 /// In priority order.
 /// Divided into unit categories.
@@ -20,6 +21,8 @@ use strum::{EnumIter, IntoEnumIterator};
     PartialOrd,
     Hash,
     Debug,
+    Display,
+    EnumString,
     Serialize,
     Deserialize,
     Encode,
@@ -82,7 +85,9 @@ impl UnitCategory {
 
 pub type UnitArray<V> = EnumArray<Unit, V, { std::mem::variant_count::<Unit>() }>;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Encode, Decode,
+)]
 pub enum Speed {
     Immobile,
     Slow,
@@ -131,6 +136,11 @@ impl Unit {
 
     /// Can this unit overflow a tower's capacity (temporarily).
     pub fn max_overflow(self) -> usize {
+        Balance::get().unit(self).max_overflow as usize
+    }
+
+    /// Built-in default of [`Self::max_overflow`].
+    pub(crate) fn default_max_overflow(self) -> usize {
         match self {
             // For after upgrades and shield generator.
             Self::Shield => 15,
@@ -145,21 +155,50 @@ impl Unit {
         }
     }
 
+    /// Damage done while fighting `target` in `field`, from the damage matrix of the current
+    /// [`Balance`]. Doesn't apply to units that are parked (see [`Self::parked_damage`]).
+    ///
     /// Must be in the range 1..=30 or [`Unit::INFINITE_DAMAGE`].
     /// If equal to [`Unit::INFINITE_DAMAGE`] it signifies infinite damage.
     /// TODO maybe make a custom damage type.
-    pub fn damage(self, field: Field, enemy_field: Field) -> u8 {
+    pub fn damage(self, target: Unit, field: Field) -> u8 {
+        Balance::get().unit(self).damage[target][field]
+    }
+
+    /// Damage done by a unit that is on the surface in a tower, despite being in the air in
+    /// forces (e.g. a fighter on its airfield).
+    pub fn parked_damage(self) -> u8 {
+        Balance::get().unit(self).parked_damage
+    }
+
+    /// Returns true if the unit is on the surface in `unit_field`, despite being in the air in
+    /// forces.
+    pub fn is_parked(self, unit_field: Field) -> bool {
+        unit_field < self.field(false, true, false)
+    }
+
+    /// Most damage done against any unit in `field`.
+    pub fn max_damage(self, field: Field) -> u8 {
+        Unit::iter()
+            .map(|target| self.damage(target, field))
+            .max()
+            .unwrap()
+    }
+
+    /// Built-in default of [`Self::damage`], by the unit's own field and the field of the fight.
+    /// Same against every unit.
+    pub(crate) fn default_damage(self, field: Field, enemy_field: Field) -> u8 {
         match self {
             Self::Tank => 3,
             Self::Fighter if field == Field::Air => 3,
             Self::Bomber if field == Field::Air && enemy_field == Field::Surface => 5,
-            // TODO: Should only do 2 air damage
+            // TODO: Should only do 2 damage against aircraft
             // (https://discord.com/channels/847143438939717663/933850279537967204/1018971807979688078)
             Self::Chopper if field == Field::Air => 3,
             Self::Frigate => 4,
             Self::Submarine => 7,
             Self::Nuke => Self::INFINITE_DAMAGE,
-            Self::Shell => 3, // TODO shell shouldn't hit regular units.
+            Self::Shell => 3, // TODO shell shouldn't hit regular units (only shields).
             _ => 1,
         }
     }
@@ -167,7 +206,7 @@ impl Unit {
     /// Returns how much damage a unit would do in a force to ground targets.
     /// If equal to [`Unit::INFINITE_DAMAGE`] it signifies infinite damage.
     pub fn force_ground_damage(self) -> u8 {
-        self.damage(self.field(false, true, false), Field::Surface)
+        self.max_damage(Field::Surface)
     }
 
     /// Converts a damage [`u8`] to an [`u32`] where infinity is represented as [`i32::MAX`].
@@ -243,16 +282,19 @@ impl Unit {
 
     /// 0 means immobile.
     pub fn speed(self, tower_type: Option<TowerType>) -> Speed {
+        // Shields can only leave projectors, regardless of balance.
+        if self == Self::Shield && !matches!(tower_type, None | Some(TowerType::Projector)) {
+            Speed::Immobile
+        } else {
+            Balance::get().unit(self).speed
+        }
+    }
+
+    /// Built-in default of [`Self::speed`] while in a force.
+    pub(crate) fn default_speed(self) -> Speed {
         match self {
-            Self::Bomber | Self::Fighter | Self::Chopper | Self::Shell => Speed::Fast,
+            Self::Bomber | Self::Fighter | Self::Chopper | Self::Shell | Self::Shield => Speed::Fast,
             Self::Nuke | Self::Tank => Speed::Slow,
-            Self::Shield => {
-                if matches!(tower_type, None | Some(TowerType::Projector)) {
-                    Speed::Fast
-                } else {
-                    Speed::Immobile
-                }
-            }
             _ => Speed::Normal,
         }
     }
@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::research::{Research, ResearchArray};
 use crate::tower::{Tower, TowerType};
 use crate::unit::{Unit, UnitCategory};
 use core_protocol::prelude::*;
@@ -168,6 +169,17 @@ impl Units {
         )
     }
 
+    /// Like [`Self::capacity`], but also takes into account the owner's [`Research`].
+    pub fn researched_capacity(
+        &self,
+        unit: Unit,
+        tower_type: Option<TowerType>,
+        research: &ResearchArray<u8>,
+    ) -> usize {
+        let capacity = self.capacity(unit, tower_type);
+        capacity + Research::capacity_bonus(research, unit, capacity)
+    }
+
     /// How much space is remaining for this unit type.
     fn space_remaining(&self, unit: Unit, tower_type: Option<TowerType>, overflow: bool) -> usize {
         match unit.category() {
@@ -4,8 +4,10 @@
 use crate::chunk::*;
 use crate::info::*;
 use crate::player::*;
+#[cfg(feature = "server")]
+use crate::ruler::RulerAbility;
 use crate::singleton::*;
-use crate::tower::{integer_sqrt, TowerId};
+use crate::tower::TowerId;
 use common_util::actor2::*;
 use core_protocol::prelude::*;
 use std::collections::BTreeMap;
@@ -48,11 +50,10 @@ impl<C: OnInfo> WorldTick<C> for World {
 
             for (upstream_tower_id, upstream_tower) in upstream_chunk.iter(upstream_chunk_id) {
                 for (i, force) in upstream_tower.inbound_forces.iter().enumerate() {
-                    let Some(upstream_player_id) = force.player_id else {
-                        debug_assert!(false, "todo zombies?");
-                        continue;
-                    };
-                    let upstream_player = Self::player_inner(&self.player, upstream_player_id);
+                    // Zombies have no player, so they can't have new alliances.
+                    let upstream_player = force
+                        .player_id
+                        .map(|player_id| Self::player_inner(&self.player, player_id));
 
                     let remaining_path = force.path().iter().skip(2);
                     for downstream_chunk_id in self.halt_path(remaining_path, upstream_player) {
@@ -66,24 +67,54 @@ impl<C: OnInfo> WorldTick<C> for World {
                     }
                 }
 
-                // Check supply line.
-                let Some(supply_line) = &upstream_tower.supply_line else {
+                // Check supply lines.
+                if upstream_tower.supply_lines.is_empty() {
                     continue;
-                };
+                }
                 let Some(player_id) = upstream_tower.player_id else {
                     debug_assert!(false, "supply line without player");
                     continue;
                 };
                 let upstream_player = Self::player_inner(&self.player, player_id);
 
-                for downstream_chunk_id in self.halt_path(supply_line.iter(), upstream_player) {
-                    halt_events.push((
-                        upstream_chunk_id,
-                        (
-                            downstream_chunk_id,
-                            ChunkHaltEvent::SupplyLine(upstream_tower_id.into()),
-                        ),
-                    ));
+                for (i, supply_line) in upstream_tower.supply_lines.iter().enumerate() {
+                    for downstream_chunk_id in
+                        self.halt_path(supply_line.path.iter(), Some(upstream_player))
+                    {
+                        halt_events.push((
+                            upstream_chunk_id,
+                            (
+                                downstream_chunk_id,
+                                ChunkHaltEvent::SupplyLine(upstream_tower_id.into(), i as u8),
+                            ),
+                        ));
+                    }
+
+                    if supply_line.below_percent.is_none() {
+                        continue;
+                    }
+                    let (downstream_chunk_id, downstream_tower_id) =
+                        supply_line.path.destination().split();
+                    let Some(downstream_tower) = Map::get(&self.chunk, downstream_chunk_id)
+                        .and_then(|chunk| chunk.actor.get(downstream_tower_id))
+                    else {
+                        // Chunk not visible.
+                        continue;
+                    };
+                    let full = supply_line.is_full(downstream_tower);
+                    if full != supply_line.paused {
+                        halt_events.push((
+                            upstream_chunk_id,
+                            (
+                                downstream_chunk_id,
+                                ChunkHaltEvent::PauseSupplyLine(
+                                    upstream_tower_id.into(),
+                                    i as u8,
+                                    full,
+                                ),
+                            ),
+                        ));
+                    }
                 }
             }
         }
@@ -93,6 +124,7 @@ impl<C: OnInfo> WorldTick<C> for World {
 
         for (_, state) in Map::iter_mut(&mut self.player) {
             state.actor.new_alliances.clear();
+            state.actor.tick_ability_cooldowns();
         }
 
         let singleton = singleton!(self).unwrap();
@@ -138,17 +170,22 @@ impl World {
     pub const MAX_ROAD_LENGTH: u32 = 5;
     pub const MAX_ROAD_LENGTH_SQUARED: u64 = (Self::MAX_ROAD_LENGTH as u64 + 1).pow(2) - 1;
     pub const MAX_PATH_ROADS: usize = 16;
+    /// Maximum number of [`Path`][`crate::force::Path`]s in a waypoint order.
+    pub const MAX_PATH_LEGS: usize = 8;
 
-    pub const CENTER: TowerId =
-        TowerId::new(WorldChunks::SIZE as u16 / 2, WorldChunks::SIZE as u16 / 2);
+    /// Center of the world, which depends on [`WorldChunks::size`].
+    pub fn center() -> TowerId {
+        let center = WorldChunks::size() as u16 / 2;
+        TowerId::new(center, center)
+    }
 
     /// Returns an iterator of chunks that send halt events to `path`.
     fn halt_path<'a>(
         &'a self,
         path: impl Iterator<Item = TowerId> + 'a,
-        player: &'a Player,
+        player: Option<&'a Player>,
     ) -> impl Iterator<Item = ChunkId> + 'a {
-        let no_new_alliances = player.new_alliances.is_empty();
+        let no_new_alliances = player.map_or(true, |p| p.new_alliances.is_empty());
         let mut dedup = vec![];
 
         // TODO optimization (not supply line):
@@ -169,7 +206,7 @@ impl World {
                 let Some(tower_player) = tower.player_id else {
                     return None;
                 };
-                if !player.new_alliances.contains(&tower_player) {
+                if !player.map_or(false, |p| p.new_alliances.contains(&tower_player)) {
                     return None;
                 }
                 // Halt because of alliance.
@@ -190,6 +227,7 @@ impl World {
         singleton!(self).expect("no singleton")
     }
 
+    /// Players missing from the world (e.g. unknown to a client) have no allies.
     pub fn have_alliance(&self, a: PlayerId, b: PlayerId) -> bool {
         Self::have_alliance_inner(&self.player, a, b)
     }
@@ -199,8 +237,30 @@ impl World {
         a: PlayerId,
         b: PlayerId,
     ) -> bool {
-        Self::player_inner(players, a).allies.contains(&b)
-            && Self::player_inner(players, b).allies.contains(&a)
+        let player = |player_id: PlayerId| Map::get(players, player_id).map(|p| &p.actor);
+        player(a)
+            .zip(player(b))
+            .map_or(false, |(a_player, b_player)| {
+                a_player.is_teammate(b_player)
+                    || (a_player.allies.contains(&b) && b_player.allies.contains(&a))
+            })
+    }
+
+    /// Players missing from the world (e.g. unknown to a client) have no teammates.
+    pub fn are_teammates(&self, a: PlayerId, b: PlayerId) -> bool {
+        let player = |player_id: PlayerId| Map::get(&self.player, player_id).map(|p| &p.actor);
+        player(a)
+            .zip(player(b))
+            .map_or(false, |(a_player, b_player)| a_player.is_teammate(b_player))
+    }
+
+    /// Other players on the same team as `player_id`, if it exists.
+    pub fn teammates(&self, player_id: PlayerId) -> impl Iterator<Item = PlayerId> + '_ {
+        let team_id = Map::get(&self.player, player_id).and_then(|p| p.actor.team_id);
+        Map::iter(&self.player).filter_map(move |(other_id, other)| {
+            (team_id.is_some() && other_id != player_id && other.actor.team_id == team_id)
+                .then_some(other_id)
+        })
     }
 
     pub fn player(&self, player_id: PlayerId) -> &Player {
@@ -218,6 +278,8 @@ impl World {
             .actor
     }
 
+    /// Creates a world of [`WorldChunks::size`], so the
+    /// [`Generation`][`crate::generation::Generation`] must be set first.
     #[cfg(feature = "server")]
     pub fn new() -> Self {
         Self {
@@ -263,6 +325,44 @@ impl World {
         self.extend(context.events);
     }
 
+    /// Moves `player_id`'s ruler from `source` to `destination` (see [`RulerAbility::Teleport`]),
+    /// if it's still there and `destination` is still theirs and in range. Both towers change at
+    /// once, so nothing on the way can intercept the ruler.
+    #[cfg(feature = "server")]
+    pub fn teleport_ruler(
+        &mut self,
+        player_id: PlayerId,
+        source: TowerId,
+        destination: TowerId,
+        mut on_info: impl FnMut(InfoEvent),
+    ) -> Result<(), &'static str> {
+        let ruler = self.chunk.get(source).ok_or("no tower")?;
+        if ruler.player_id != Some(player_id) || !ruler.units.has_ruler() {
+            return Err("ruler not in tower");
+        }
+        let tower = self.chunk.get(destination).ok_or("no tower")?;
+        if tower.player_id != Some(player_id) {
+            return Err("destination not under player's control");
+        }
+        if !RulerAbility::can_teleport(source, destination) {
+            return Err("destination out of range");
+        }
+
+        let (chunk_id, tower_id) = source.split();
+        self.dispatch_chunk_input(chunk_id, ChunkInput::RulerDepart { tower_id }, &mut on_info);
+        let (chunk_id, tower_id) = destination.split();
+        self.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::RulerArrive {
+                tower_id,
+                player_id,
+                source,
+            },
+            &mut on_info,
+        );
+        Ok(())
+    }
+
     #[cfg(feature = "server")]
     pub fn dispatch_player_maintenance(
         &mut self,
@@ -298,103 +398,9 @@ impl World {
             .apply_owned(input, &mut on_info)
     }
 
-    pub fn find_best_path(
-        &self,
-        src: TowerId,
-        dst: TowerId,
-        max_edge_distance: Option<u32>,
-        player_id: PlayerId,
-        filter: impl Fn(TowerId) -> bool,
-    ) -> Option<Vec<TowerId>> {
-        if let Some(d) = max_edge_distance {
-            (src.distance(dst) <= d && filter(dst)).then(|| vec![src, dst])
-        } else {
-            self.astar(src, dst, player_id, &filter)
-                .ok()
-                .filter(|p| p.len() >= 2)
-        }
-    }
-
-    pub fn find_best_incomplete_path(
-        &self,
-        src: TowerId,
-        dst: TowerId,
-        max_edge_distance: Option<u32>,
-        player_id: PlayerId,
-        filter: impl Fn(TowerId) -> bool,
-    ) -> Vec<TowerId> {
-        if let Some(d) = max_edge_distance {
-            (src.distance(dst) <= d && filter(dst))
-                .then(|| vec![src, dst])
-                .unwrap_or_else(|| vec![src])
-        } else {
-            self.astar(src, dst, player_id, &filter)
-                .unwrap_or_else(|reachable| {
-                    self.astar(src, reachable, player_id, &filter)
-                        .unwrap_or_default()
-                })
-        }
-    }
-
-    fn astar(
-        &self,
-        src: TowerId,
-        dst: TowerId,
-        player_id: PlayerId,
-        filter: &impl Fn(TowerId) -> bool,
-    ) -> Result<Vec<TowerId>, TowerId> {
-        // Scale distances squared up to avoid integer rounding errors (basically a fixed point).
-        // Only 33 bits of u64 distance_squared are ever used so a D2_SCALE <= 2^30 is valid.
-        const D2_SCALE: u64 = 1 << 16;
-        const D_SCALE: u32 = 1 << 8; // Must be square root of D2_SCALE;
-
-        let dst_player_id = self.chunk.get(dst).and_then(|t| t.player_id);
-        let mut shortest = (src, u32::MAX);
-
-        // Don't visit every tower if a path isn't found in a reasonable time.
-        let mut emergency_stop: u16 = 128 + src.distance(dst).max(2048) as u16;
-
-        pathfinding::directed::astar::astar(
-            &src,
-            |&pos| {
-                pos.neighbors().filter_map(move |tower_id| {
-                    self.chunk.get(tower_id).and_then(|t| {
-                        let passes_through_allicance = t.player_id.is_some_and(|p| {
-                            Some(p) != dst_player_id && self.have_alliance(player_id, p)
-                        });
-                        (!passes_through_allicance && filter(tower_id)).then(|| {
-                            let d2 = pos.distance_squared(tower_id);
-                            (tower_id, integer_sqrt(d2 * D2_SCALE))
-                        })
-                    })
-                })
-            },
-            |&pos| {
-                let mut heuristic = integer_sqrt(pos.distance_squared(dst) * D2_SCALE);
-                let tower = self.chunk.get(pos).unwrap();
-                if tower.player_id == Some(player_id) {
-                    heuristic += 2 * D_SCALE; // Prioritize claiming new towers.
-                } else if tower.player_id.is_some() || !tower.units.is_empty() {
-                    heuristic += 32 * D_SCALE; // Deprioritize going through enemy/zombies.
-                }
-                if heuristic < shortest.1 {
-                    shortest = (pos, heuristic);
-                }
-                heuristic
-            },
-            |pos| {
-                emergency_stop -= 1;
-                pos == &dst || emergency_stop == 0
-            },
-        )
-        .filter(|_| emergency_stop > 0)
-        .map(|(path, _)| path)
-        .ok_or(shortest.0)
-    }
-
     #[inline]
     pub fn distance_squared_to_center(tower_id: TowerId) -> u64 {
-        Self::CENTER.distance_squared(tower_id)
+        Self::center().distance_squared(tower_id)
     }
 }
 
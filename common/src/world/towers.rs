@@ -2,31 +2,66 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::chunk::{Chunk, ChunkId};
+use crate::generation::Generation;
 use crate::tower::{Tower, TowerId, TowerRectangle};
 use crate::world::ChunkState;
 use common_util::storage::*;
 use common_util::x_vec2::U16Vec2;
-use std::array;
-
-const SIZE: usize = 256;
-const SIZE_CHUNKS: usize = SIZE / Chunk::SIZE;
 
+/// Row major, so iteration is ordered by y first.
 #[derive(Debug)]
 pub struct ChunkMap<T> {
-    chunks: [[Option<T>; SIZE_CHUNKS]; SIZE_CHUNKS],
+    chunks: Vec<Option<T>>,
+    /// Width and height in chunks. Grows to accommodate insertions.
+    size_chunks: usize,
 }
 
 impl<T> ChunkMap<T> {
+    /// Calls `f` for every [`ChunkId`] in the world, which is [`WorldChunks::size_chunks`] wide.
     pub fn from_fn(mut f: impl FnMut(ChunkId) -> Option<T>) -> Self {
+        let size_chunks = WorldChunks::size_chunks();
         Self {
-            chunks: array::from_fn(|y| array::from_fn(|x| f(ChunkId::new(x as u8, y as u8)))),
+            chunks: (0..size_chunks * size_chunks)
+                .map(|i| f(Self::chunk_id(size_chunks, i)))
+                .collect(),
+            size_chunks,
         }
     }
+
+    fn chunk_id(size_chunks: usize, index: usize) -> ChunkId {
+        ChunkId::new((index % size_chunks) as u8, (index / size_chunks) as u8)
+    }
+
+    fn index(&self, id: ChunkId) -> Option<usize> {
+        let (x, y) = (id.x as usize, id.y as usize);
+        (x < self.size_chunks && y < self.size_chunks).then_some(x + y * self.size_chunks)
+    }
+
+    /// Like [`Self::index`] but grows to fit `id`, which may be necessary if the world size
+    /// changed after creation (e.g. a client receiving a [`Generation`]).
+    fn index_or_grow(&mut self, id: ChunkId) -> usize {
+        if let Some(index) = self.index(id) {
+            return index;
+        }
+        let size_chunks = (id.x.max(id.y) as usize + 1).max(WorldChunks::size_chunks());
+        let mut chunks: Vec<_> = (0..size_chunks * size_chunks).map(|_| None).collect();
+        for (i, chunk) in std::mem::take(&mut self.chunks).into_iter().enumerate() {
+            let old = Self::chunk_id(self.size_chunks, i);
+            chunks[old.x as usize + old.y as usize * size_chunks] = chunk;
+        }
+        self.chunks = chunks;
+        self.size_chunks = size_chunks;
+        self.index(id).unwrap()
+    }
 }
 
 impl<T> Default for ChunkMap<T> {
+    /// Doesn't allocate until a chunk is inserted.
     fn default() -> Self {
-        Self::from_fn(|_| None)
+        Self {
+            chunks: Vec::new(),
+            size_chunks: 0,
+        }
     }
 }
 
@@ -35,14 +70,11 @@ impl<T> IntoIterator for ChunkMap<T> {
     type IntoIter = impl Iterator<Item = Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
+        let size_chunks = self.size_chunks;
         self.chunks
             .into_iter()
             .enumerate()
-            .flat_map(move |(y, ts)| {
-                ts.into_iter().enumerate().filter_map(move |(x, chunk)| {
-                    chunk.map(move |c| (ChunkId::new(x as u8, y as u8), c))
-                })
-            })
+            .filter_map(move |(i, chunk)| chunk.map(|c| (Self::chunk_id(size_chunks, i), c)))
     }
 }
 
@@ -51,40 +83,34 @@ impl<T> Map<ChunkId, T> for ChunkMap<T> {
     type IterMut<'a> = impl Iterator<Item = (ChunkId, &'a mut T)> where T: 'a;
 
     fn get(&self, id: ChunkId) -> Option<&T> {
-        self.chunks
-            .get(id.y as usize)? // TODO remove ? (no invalid ChunkIds).
-            .get(id.x as usize)?
-            .as_ref()
+        self.chunks[self.index(id)?].as_ref()
     }
 
     fn get_mut(&mut self, id: ChunkId) -> Option<&mut T> {
-        self.chunks
-            .get_mut(id.y as usize)? // TODO remove ? (no invalid ChunkIds).
-            .get_mut(id.x as usize)?
-            .as_mut()
+        let index = self.index(id)?;
+        self.chunks[index].as_mut()
     }
 
     fn insert(&mut self, id: ChunkId, v: T) -> Option<T> {
-        std::mem::replace(&mut self.chunks[id.y as usize][id.x as usize], Some(v))
+        let index = self.index_or_grow(id);
+        std::mem::replace(&mut self.chunks[index], Some(v))
     }
 
     fn iter(&self) -> Self::Iter<'_> {
-        self.chunks.iter().enumerate().flat_map(move |(y, ts)| {
-            ts.iter().enumerate().filter_map(move |(x, chunk)| {
-                chunk
-                    .as_ref()
-                    .map(move |c| (ChunkId::new(x as u8, y as u8), c))
-            })
+        let size_chunks = self.size_chunks;
+        self.chunks.iter().enumerate().filter_map(move |(i, chunk)| {
+            chunk
+                .as_ref()
+                .map(move |c| (Self::chunk_id(size_chunks, i), c))
         })
     }
 
     fn iter_mut(&mut self) -> Self::IterMut<'_> {
-        self.chunks.iter_mut().enumerate().flat_map(move |(y, ts)| {
-            ts.iter_mut().enumerate().filter_map(move |(x, chunk)| {
-                chunk
-                    .as_mut()
-                    .map(move |c| (ChunkId::new(x as u8, y as u8), c))
-            })
+        let size_chunks = self.size_chunks;
+        self.chunks.iter_mut().enumerate().filter_map(move |(i, chunk)| {
+            chunk
+                .as_mut()
+                .map(move |c| (Self::chunk_id(size_chunks, i), c))
         })
     }
 
@@ -96,21 +122,21 @@ impl<T> Map<ChunkId, T> for ChunkMap<T> {
     where
         T: Default,
     {
-        self.chunks[id.y as usize][id.x as usize].get_or_insert_default()
+        let index = self.index_or_grow(id);
+        self.chunks[index].get_or_insert_default()
     }
 
     fn remove(&mut self, id: ChunkId) -> Option<T> {
-        std::mem::replace(&mut self.chunks[id.y as usize][id.x as usize], None)
+        let index = self.index(id)?;
+        self.chunks[index].take()
     }
 
     fn retain(&mut self, mut f: impl FnMut(ChunkId, &mut T) -> bool) {
-        for (y, ts) in self.chunks.iter_mut().enumerate() {
-            for (x, chunk) in ts.iter_mut().enumerate() {
-                if let Some(c) = chunk {
-                    let chunk_id = ChunkId::new(x as u8, y as u8);
-                    if !f(chunk_id, c) {
-                        *chunk = None
-                    }
+        let size_chunks = self.size_chunks;
+        for (i, chunk) in self.chunks.iter_mut().enumerate() {
+            if let Some(c) = chunk {
+                if !f(Self::chunk_id(size_chunks, i), c) {
+                    *chunk = None
                 }
             }
         }
@@ -122,13 +148,31 @@ impl<T> Efficient for ChunkMap<T> {}
 
 pub type WorldChunks = ChunkMap<ChunkState>;
 
+const _: () = assert!(WorldChunks::MAX_SIZE / Chunk::SIZE <= u8::MAX as usize + 1);
+
 impl WorldChunks {
-    pub const SIZE: usize = SIZE;
-    pub const SIZE_CHUNKS: usize = SIZE_CHUNKS;
-    pub const RECTANGLE: TowerRectangle = TowerRectangle::new(
-        TowerId(U16Vec2::ZERO),
-        TowerId(U16Vec2::splat(Self::SIZE as u16 - 1)),
-    );
+    /// Maximum [`Generation::size`], limited by the range of [`ChunkId`].
+    pub const MAX_SIZE: usize = 1024;
+
+    /// Width and height of the world in towers, according to the current [`Generation`].
+    #[inline]
+    pub fn size() -> usize {
+        Generation::get().size as usize
+    }
+
+    /// Width and height of the world in chunks.
+    #[inline]
+    pub fn size_chunks() -> usize {
+        Self::size() / Chunk::SIZE
+    }
+
+    /// All valid [`TowerId`]s.
+    pub fn rectangle() -> TowerRectangle {
+        TowerRectangle::new(
+            TowerId(U16Vec2::ZERO),
+            TowerId(U16Vec2::splat(Self::size() as u16 - 1)),
+        )
+    }
 
     pub fn contains(&self, tower_id: TowerId) -> bool {
         self.get(tower_id).is_some()
@@ -144,13 +188,7 @@ impl WorldChunks {
     }
 
     pub fn iter_chunks(&self) -> impl Iterator<Item = (ChunkId, &Chunk)> + Clone {
-        self.chunks.iter().enumerate().flat_map(move |(y, ts)| {
-            ts.iter().enumerate().filter_map(move |(x, chunk_state)| {
-                chunk_state
-                    .as_ref()
-                    .map(|chunk_state| (ChunkId::new(x as u8, y as u8), &chunk_state.actor))
-            })
-        })
+        Map::iter(self).map(|(chunk_id, chunk_state)| (chunk_id, &chunk_state.actor))
     }
 
     pub fn iter_towers(&self) -> impl Iterator<Item = (TowerId, &Tower)> + Clone {
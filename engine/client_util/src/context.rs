@@ -15,11 +15,12 @@ use core_protocol::dto::{
     LeaderboardScoreDto, LiveboardDto, MessageDto, PlayerDto, ServerDto, TeamDto, YourScoreDto,
 };
 use core_protocol::id::{CohortId, InvitationId, PeriodId, PlayerId, TeamId};
-use core_protocol::name::PlayerAlias;
+use core_protocol::name::{PlayerAlias, RealmName};
 use core_protocol::owned::{dedup_into_inner, owned_into_box, owned_into_iter};
 use core_protocol::rpc::{
-    ChatUpdate, ClientRequest, ClientUpdate, InvitationUpdate, LeaderboardUpdate, LiveboardUpdate,
-    PlayerUpdate, Request, SystemUpdate, TeamUpdate, Update, WebSocketQuery,
+    ChatUpdate, ClientRequest, ClientUpdate, InvitationRequest, InvitationUpdate,
+    LeaderboardUpdate, LiveboardUpdate, PlayerUpdate, Request, SystemUpdate, TeamUpdate, Update,
+    WebSocketQuery,
 };
 use core_protocol::ServerNumber;
 use heapless::HistoryBuffer;
@@ -359,6 +360,7 @@ impl<G: GameClient> Context<G> {
             token: common_settings.token,
             session_token: common_settings.session_token,
             invitation_id: invitation_id(),
+            realm_name: None,
             date_created: common_settings.date_created,
             cohort_id: common_settings.cohort_id,
             referrer: frontend.get_real_referrer(),
@@ -395,6 +397,16 @@ impl<G: GameClient> Context<G> {
         self.send_to_server(Request::Client(ClientRequest::SetAlias(alias)));
     }
 
+    /// Send a request to create a private realm. The invitation to it will be in
+    /// [`CoreState::created_invitation_id`].
+    pub fn send_create_realm(&mut self, realm_name: RealmName, bots: u8, bot_difficulty: u8) {
+        self.send_to_server(Request::Invitation(InvitationRequest::CreateRealm {
+            realm_name,
+            bots,
+            bot_difficulty,
+        }));
+    }
+
     /// Send a request to log an error message.
     pub fn send_trace(&mut self, message: String) {
         self.send_to_server(Request::Client(ClientRequest::Trace { message }));
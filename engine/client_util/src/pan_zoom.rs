@@ -6,6 +6,8 @@ pub struct PanZoom {
     aspect_ratio: f32,
     bottom_left: Vec2,
     top_right: Vec2,
+    /// Maximum zoom regardless of bounds, if any.
+    zoom_limit: Option<f32>,
     debug: bool,
     ready: bool,
 }
@@ -24,6 +26,7 @@ impl PanZoom {
             aspect_ratio: 1.0,
             bottom_left: Vec2::splat(-1.0),
             top_right: Vec2::splat(1.0),
+            zoom_limit: None,
             debug: false,
             ready: false,
         }
@@ -111,6 +114,13 @@ impl PanZoom {
         }
     }
 
+    /// Limits zoom in world space, even if the bounds are larger.
+    pub fn set_zoom_limit(&mut self, zoom_limit: Option<f32>) {
+        debug_assert!(zoom_limit.map_or(true, f32::is_finite));
+        self.zoom_limit = zoom_limit;
+        self.zoom = self.zoom.min(self.max_zoom());
+    }
+
     /// Sets center to that of bounds.
     pub fn reset_center(&mut self) {
         self.center = (self.bottom_left + self.top_right) * 0.5;
@@ -131,6 +141,8 @@ impl PanZoom {
 
     fn max_zoom(&self) -> f32 {
         let span = self.top_right - self.bottom_left;
-        span.max_element() * 0.75
+        let max_zoom = span.max_element() * 0.75;
+        self.zoom_limit
+            .map_or(max_zoom, |limit| max_zoom.min(limit))
     }
 }
@@ -72,6 +72,9 @@ pub struct WebSocketQuery {
     pub session_token: Option<SessionToken>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub invitation_id: Option<InvitationId>,
+    /// Private realm to join, instead of the main arena. Also implied by an invitation to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realm_name: Option<RealmName>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub referrer: Option<Referrer>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -220,6 +223,14 @@ pub enum LiveboardUpdate {
 pub enum InvitationRequest {
     Create,
     Accept(InvitationId),
+    /// Create a private realm with its own world and this many bots. Responds with an invitation
+    /// to join it, which lasts as long as the realm.
+    CreateRealm {
+        realm_name: RealmName,
+        bots: u8,
+        /// How well the bots play, from 0 (the easiest) up to however many levels the game has.
+        bot_difficulty: u8,
+    },
 }
 
 /// Invitation related update from server to client.
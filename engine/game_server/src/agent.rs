@@ -0,0 +1,339 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Agents are bots that run in another process, and play over a local (Unix) socket.
+//!
+//! Every message, in either direction, is one line of JSON (ending in `\n`), in `serde`'s
+//! default representation. Once connected, the agent is sent its [`PlayerId`] (a number), followed
+//! by a [`GameArenaService::AgentUpdate`] every tick that there is one. The agent may send a
+//! [`GameArenaService::GameRequest`] at any time, for example `"Spawn"`. Up to
+//! [`AgentRepo::MAX_COMMANDS_PER_TICK`] commands are applied per tick. Agents that send more than
+//! [`AgentRepo::MAX_PENDING_COMMANDS`] commands ahead, send invalid or too long lines, or fall more
+//! than [`AgentRepo::MAX_PENDING_MESSAGES`] messages behind are disconnected. Closing the
+//! connection removes the agent's player from the game.
+
+use crate::game_service::GameArenaService;
+use crate::player::{PlayerData, PlayerRepo, PlayerTuple};
+use core_protocol::id::PlayerId;
+use core_protocol::prelude::*;
+use log::{info, warn};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::error::{TryRecvError, TrySendError};
+use tokio::sync::mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender};
+
+/// A new connection, until it joins the game.
+struct AgentConnection<G: GameArenaService> {
+    commands: Receiver<G::GameRequest>,
+    messages: Sender<Vec<u8>>,
+}
+
+/// Data stored per agent.
+struct AgentData<G: GameArenaService> {
+    player_tuple: Arc<PlayerTuple<G>>,
+    /// Agents aren't clients, so they keep their own.
+    client_data: G::ClientData,
+    /// Decoded commands from the agent.
+    commands: Receiver<G::GameRequest>,
+    /// Encoded messages to the agent.
+    messages: Sender<Vec<u8>>,
+    /// The agent fell too far behind, so it will be removed.
+    behind: bool,
+}
+
+/// Manages the connections and players of agents.
+pub struct AgentRepo<G: GameArenaService> {
+    /// Connections accepted by [`Self::listen`], if it was called.
+    connections: Option<UnboundedReceiver<AgentConnection<G>>>,
+    agents: Vec<AgentData<G>>,
+    /// Bot numbers of agents that left, to reuse.
+    vacant: Vec<usize>,
+}
+
+impl<G: GameArenaService> Default for AgentRepo<G> {
+    fn default() -> Self {
+        Self {
+            connections: None,
+            agents: Vec::new(),
+            vacant: Vec::new(),
+        }
+    }
+}
+
+impl<G: GameArenaService> AgentRepo<G> {
+    /// Bot numbers of agents start here, far above those of [`BotRepo`](crate::bot::BotRepo).
+    const FIRST_BOT_NUMBER: usize = 1 << 21;
+    /// Maximum number of agents at once.
+    const MAX_AGENTS: usize = 64;
+    /// Commands beyond this many per tick wait until the next tick.
+    pub const MAX_COMMANDS_PER_TICK: usize = 8;
+    /// Agents that send this many commands ahead of those applied are disconnected.
+    pub const MAX_PENDING_COMMANDS: usize = 64;
+    /// Maximum size of a command, including its newline.
+    pub const MAX_COMMAND_BYTES: usize = 1 << 16;
+    /// Agents that fall this many messages behind are disconnected, since updates can't be
+    /// skipped.
+    pub const MAX_PENDING_MESSAGES: usize = 64;
+
+    /// Starts accepting agents on a Unix socket at `path`, replacing a socket left there by a
+    /// previous server. Anything else at `path` is an error, rather than deleted.
+    #[cfg(unix)]
+    pub(crate) fn listen(&mut self, path: &Path) -> io::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "not a socket")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = tokio::net::UnixListener::bind(path)?;
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.connections = Some(receiver);
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        if sender.is_closed() {
+                            break;
+                        }
+                        Self::serve(stream, sender.clone());
+                    }
+                    Err(e) => warn!("could not accept agent: {e}"),
+                }
+            }
+        });
+        info!("listening for agents on {}", path.display());
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn listen(&mut self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "agents require Unix sockets",
+        ))
+    }
+
+    /// Encodes a message to an agent, as a line of JSON.
+    fn encode(message: &impl Serialize) -> Vec<u8> {
+        let mut line = serde_json::to_vec(message).unwrap();
+        line.push(b'\n');
+        line
+    }
+
+    /// Reads commands from and writes messages to an agent's `stream`.
+    #[cfg(unix)]
+    fn serve(stream: tokio::net::UnixStream, connections: UnboundedSender<AgentConnection<G>>) {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let (command_sender, commands) = tokio::sync::mpsc::channel(Self::MAX_PENDING_COMMANDS);
+        let (messages, mut message_receiver) =
+            tokio::sync::mpsc::channel::<Vec<u8>>(Self::MAX_PENDING_MESSAGES);
+        if connections
+            .send(AgentConnection { commands, messages })
+            .is_err()
+        {
+            return;
+        }
+        let (reader, mut writer) = stream.into_split();
+
+        tokio::spawn(async move {
+            while let Some(message) = message_receiver.recv().await {
+                if writer.write_all(&message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                let limit = Self::MAX_COMMAND_BYTES as u64;
+                match (&mut reader).take(limit).read_until(b'\n', &mut line).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                if line.last() != Some(&b'\n') {
+                    warn!("agent command too large or incomplete");
+                    break;
+                }
+                match serde_json::from_slice(&line) {
+                    Ok(command) => match command_sender.try_send(command) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => {
+                            warn!("agent sent too many commands");
+                            break;
+                        }
+                        Err(TrySendError::Closed(_)) => break,
+                    },
+                    Err(e) => {
+                        warn!("invalid agent command: {e}");
+                        break;
+                    }
+                }
+            }
+            // Dropping `command_sender` removes the agent.
+        });
+    }
+
+    /// Sends every agent its update. Call when clients are updated.
+    pub(crate) fn update(&mut self, service: &G, players: &PlayerRepo<G>) {
+        for agent in &mut self.agents {
+            if let Some(update) =
+                service.get_agent_update(&agent.player_tuple, &mut agent.client_data, players)
+            {
+                let message = Self::encode(&update);
+                if let Err(TrySendError::Full(_)) = agent.messages.try_send(message) {
+                    warn!("agent fell behind");
+                    agent.behind = true;
+                }
+            }
+        }
+    }
+
+    /// Applies commands, removes agents that disconnected and adds new ones. Like bot commands,
+    /// call after [`GameArenaService::post_update`].
+    pub(crate) fn post_update(&mut self, service: &mut G, players: &mut PlayerRepo<G>) {
+        let Self {
+            connections,
+            agents,
+            vacant,
+        } = self;
+
+        let players_ref = &*players;
+        agents.retain_mut(|agent| {
+            let mut connected = !agent.behind && !agent.messages.is_closed();
+            for _ in 0..Self::MAX_COMMANDS_PER_TICK {
+                match agent.commands.try_recv() {
+                    Ok(command) => {
+                        let _ = service.agent_command(
+                            command,
+                            &agent.player_tuple,
+                            &mut agent.client_data,
+                            players_ref,
+                        );
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        connected = false;
+                        break;
+                    }
+                }
+            }
+            if !connected {
+                let player_id = agent.player_tuple.borrow_player().player_id;
+                info!("agent {player_id:?} left");
+                service.player_left(&agent.player_tuple, players_ref);
+                // Like bots, the player is never forgotten, and its id is reused.
+                vacant.extend(player_id.bot_number());
+            }
+            connected
+        });
+
+        while let Some(receiver) = connections {
+            let AgentConnection { commands, messages } = match receiver.try_recv() {
+                Ok(connection) => connection,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    *connections = None;
+                    break;
+                }
+            };
+            if agents.len() >= Self::MAX_AGENTS {
+                warn!("too many agents");
+                continue;
+            }
+            let number = vacant
+                .pop()
+                .unwrap_or(Self::FIRST_BOT_NUMBER + agents.len());
+            let Some(player_id) = PlayerId::nth_bot(number) else {
+                debug_assert!(false, "should not run out of ids");
+                continue;
+            };
+            let player_tuple = Arc::new(PlayerTuple::new(PlayerData::new(player_id, None)));
+            players.insert(player_id, Arc::clone(&player_tuple));
+            service.player_joined(&player_tuple, &*players);
+
+            let _ = messages.try_send(Self::encode(&player_id));
+            info!("agent {player_id:?} joined");
+            agents.push(AgentData {
+                player_tuple,
+                client_data: Default::default(),
+                commands,
+                messages,
+                behind: false,
+            });
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::agent::AgentRepo;
+    use crate::game_service::{GameArenaService, MockGame, MockGameOptions};
+    use crate::player::PlayerRepo;
+    use core_protocol::id::PlayerId;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    #[test]
+    fn protocol() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("agent-{}.sock", std::process::id()));
+        let not_socket = dir.join(format!("agent-{}.txt", std::process::id()));
+        std::fs::write(&not_socket, "keep").unwrap();
+
+        runtime.block_on(async {
+            let mut agents = AgentRepo::<MockGame>::default();
+            assert!(agents.listen(&not_socket).is_err());
+            assert_eq!(std::fs::read_to_string(&not_socket).unwrap(), "keep");
+            agents.listen(&path).unwrap();
+
+            let mut service = MockGame::new(0, &MockGameOptions {});
+            let mut players = PlayerRepo::default();
+            let stream = UnixStream::connect(&path).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            // Joins once the connection is accepted.
+            for _ in 0..1000 {
+                if !agents.agents.is_empty() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+                agents.post_update(&mut service, &mut players);
+            }
+            let player_id = PlayerId::nth_bot(AgentRepo::<MockGame>::FIRST_BOT_NUMBER).unwrap();
+            assert!(players.contains(player_id));
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert_eq!(line, player_id.0.to_string());
+
+            agents.update(&service, &players);
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "null");
+
+            // A valid command, followed by an invalid one, which disconnects the agent.
+            writer.write_all(b"null\nnonsense\n").await.unwrap();
+            for _ in 0..1000 {
+                if agents.agents.is_empty() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+                agents.post_update(&mut service, &mut players);
+            }
+            assert!(agents.agents.is_empty());
+            assert_eq!(lines.next_line().await.unwrap(), None);
+        });
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&not_socket).unwrap();
+    }
+}
@@ -1,18 +1,32 @@
 use crate::{context_service::ContextService, game_service::GameArenaService};
 use core_protocol::RealmName;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 pub(crate) struct ArenaRepo<G: GameArenaService> {
     main: ContextService<G>,
     realms: HashMap<RealmName, ContextService<G>>,
+    /// When each realm last had real players, for expiring it.
+    realms_active: HashMap<RealmName, Instant>,
+    /// Realms whose worlds are still being generated.
+    realms_pending: HashSet<RealmName>,
 }
 
 #[allow(unused)]
 impl<G: GameArenaService> ArenaRepo<G> {
+    /// Maximum number of private realms at once.
+    pub(crate) const REALMS_MAX: usize = 16;
+    /// Maximum number of bots in a private realm.
+    pub(crate) const REALM_BOTS_MAX: usize = 64;
+    /// How long a private realm can go without real players before it is removed.
+    const REALM_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
     pub(crate) fn new(main: ContextService<G>) -> Self {
         Self {
             main,
             realms: HashMap::new(),
+            realms_active: HashMap::new(),
+            realms_pending: HashSet::new(),
         }
     }
 
@@ -43,6 +57,65 @@ impl<G: GameArenaService> ArenaRepo<G> {
         }
     }
 
+    /// Reserves the name of a private realm, if it is valid and available, until
+    /// [`Self::insert_realm`] or [`Self::cancel_realm`].
+    pub(crate) fn reserve_realm(&mut self, realm_name: RealmName) -> Result<(), &'static str> {
+        // Realm names may be used as subdomains.
+        if realm_name.is_empty()
+            || !realm_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            || realm_name.parse::<usize>().is_ok()
+        {
+            return Err("invalid realm name");
+        }
+        if self.realms.contains_key(&realm_name) || self.realms_pending.contains(&realm_name) {
+            return Err("realm already exists");
+        }
+        if self.realms.len() + self.realms_pending.len() >= Self::REALMS_MAX {
+            return Err("too many realms");
+        }
+        self.realms_pending.insert(realm_name);
+        Ok(())
+    }
+
+    /// Adds a private realm that was reserved with [`Self::reserve_realm`].
+    pub(crate) fn insert_realm(
+        &mut self,
+        realm_name: RealmName,
+        context_service: ContextService<G>,
+    ) {
+        let reserved = self.realms_pending.remove(&realm_name);
+        debug_assert!(reserved, "realm wasn't reserved");
+        self.realms.insert(realm_name, context_service);
+        self.realms_active.insert(realm_name, Instant::now());
+    }
+
+    /// Frees the name of a private realm that couldn't be created.
+    pub(crate) fn cancel_realm(&mut self, realm_name: RealmName) {
+        self.realms_pending.remove(&realm_name);
+    }
+
+    /// Removes private realms that have been without real players for too long, returning their
+    /// names.
+    pub(crate) fn prune_realms(&mut self) -> Vec<RealmName> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        for (&realm_name, context_service) in &self.realms {
+            let active = self.realms_active.entry(realm_name).or_insert(now);
+            if context_service.context.players.real_players > 0 {
+                *active = now;
+            } else if now.duration_since(*active) >= Self::REALM_EXPIRY {
+                expired.push(realm_name);
+            }
+        }
+        for realm_name in &expired {
+            self.realms.remove(realm_name);
+            self.realms_active.remove(realm_name);
+        }
+        expired
+    }
+
     pub(crate) fn iter(&self) -> impl Iterator<Item = (Option<RealmName>, &ContextService<G>)> {
         std::iter::once((None, &self.main))
             .chain(self.realms.iter().map(|(id, cs)| (Some(*id), cs)))
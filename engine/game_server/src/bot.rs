@@ -61,6 +61,13 @@ impl<G: GameArenaService> BotRepo<G> {
         )
     }
 
+    /// Changes the minimum number of bots, which is clamped to the maximum. Returns the new
+    /// minimum.
+    pub fn set_min_bots(&mut self, min_bots: usize) -> usize {
+        self.min_bots = min_bots.min(self.max_bots);
+        self.min_bots
+    }
+
     /// Updates all bots.
     pub fn update(&mut self, service: &G, players: &PlayerRepo<G>) {
         self.bots
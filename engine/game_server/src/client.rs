@@ -17,8 +17,8 @@ use core_protocol::dto::{InvitationDto, ServerDto};
 use core_protocol::id::{CohortId, InvitationId, PlayerId, ServerId, UserAgentId};
 use core_protocol::name::{PlayerAlias, Referrer};
 use core_protocol::rpc::{
-    AdType, ClientRequest, ClientUpdate, LeaderboardUpdate, LiveboardUpdate, PlayerUpdate, Request,
-    SystemUpdate, Update,
+    AdType, ClientRequest, ClientUpdate, InvitationRequest, LeaderboardUpdate, LiveboardUpdate,
+    PlayerUpdate, Request, SystemUpdate, Update,
 };
 use core_protocol::{
     get_unix_time_now, ArenaToken, NickName, PlasmaRequestV1, RealmName, ServerNumber,
@@ -38,7 +38,7 @@ use std::collections::{HashMap, HashSet};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::marker::PhantomData;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::str::{self, FromStr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -949,6 +949,28 @@ impl<G: GameArenaService> PlayerClientData<G> {
         }
     }
 
+    /// Recreates a client that was saved before the server restarted. It starts in limbo, until
+    /// the real client reconnects with the same [`Token`].
+    pub(crate) fn restored(token: Token, alias: PlayerAlias) -> Self {
+        let mut client = Self::new(
+            ClientMetricData::restored(),
+            None,
+            None,
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        );
+        client.token = token;
+        client.alias = alias;
+        client.status = ClientStatus::Limbo {
+            expiry: Instant::now() + G::RESTORE_LIMBO,
+        };
+        client
+    }
+
+    /// Credential the client must present to reconnect.
+    pub fn token(&self) -> Token {
+        self.token
+    }
+
     /// Requires mutable self, but as a result, guaranteed not to panic.
     pub fn data(&mut self) -> &G::ClientData {
         &*self.data.get_mut()
@@ -969,8 +991,32 @@ impl<G: GameArenaService> Handler<ObserverMessage<Request<G::GameRequest>, Updat
     fn handle(
         &mut self,
         msg: ObserverMessage<Request<G::GameRequest>, Update<G::GameUpdate>>,
-        _ctx: &mut Self::Context,
+        ctx: &mut Self::Context,
     ) {
+        if let ObserverMessageBody::Request {
+            player_id,
+            request:
+                Request::Invitation(InvitationRequest::CreateRealm {
+                    realm_name,
+                    bots,
+                    bot_difficulty,
+                }),
+        } = msg.body
+        {
+            // Concerns every arena, so it can't be handled by the player's arena.
+            if let Err(s) = self.create_realm(
+                player_id,
+                msg.realm_name,
+                realm_name,
+                bots,
+                bot_difficulty,
+                ctx,
+            ) {
+                warn!("create realm request resulted in {}", s);
+            }
+            return;
+        }
+
         let Some(context_service) = self.arenas.get_mut(msg.realm_name) else {
             match msg.body {
                 ObserverMessageBody::Register { observer, .. } => {
@@ -1101,16 +1147,19 @@ impl<G: GameArenaService> Handler<Authenticate> for Infrastructure<G> {
             return Err("rate limit exceeded");
         }
 
-        let realm_name = msg.realm_name;
-        let Some(context_service) = self.arenas.get_mut(realm_name) else {
-            return Err("no such arena");
-        };
-        let arena_token = context_service.context.token;
-
         let invitations = &self.invitations;
         let invitation = msg
             .invitation_id
             .and_then(|id| invitations.get(id).cloned());
+
+        // Private realms are joined through invitations to them.
+        let realm_name = msg
+            .realm_name
+            .or_else(|| invitation.as_ref().and_then(|i| i.realm_name));
+        let Some(context_service) = self.arenas.get_mut(realm_name) else {
+            return Err("no such arena");
+        };
+        let arena_token = context_service.context.token;
         let invitation_dto = invitation.map(|i| InvitationDto {
             player_id: i.player_id,
         });
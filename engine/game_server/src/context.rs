@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2021 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::agent::AgentRepo;
 use crate::bot::BotRepo;
 use crate::chat::ChatRepo;
 use crate::game_service::GameArenaService;
@@ -8,18 +9,28 @@ use crate::liveboard::LiveboardRepo;
 use crate::player::PlayerRepo;
 #[cfg(feature = "teams")]
 use crate::team::TeamRepo;
+use core_protocol::dto::MessageDto;
+use core_protocol::get_unix_time_now;
+use core_protocol::id::PlayerId;
+use core_protocol::name::PlayerAlias;
 use core_protocol::ArenaToken;
 use rand::{thread_rng, Rng};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
 
 /// Things that go along with every instance of a [`GameArenaService`].
 pub struct Context<G: GameArenaService> {
     pub token: ArenaToken,
     pub players: PlayerRepo<G>,
     pub(crate) bots: BotRepo<G>,
+    /// Bots in other processes, if listening for them.
+    pub(crate) agents: AgentRepo<G>,
     pub(crate) chat: ChatRepo<G>,
     #[cfg(feature = "teams")]
     pub teams: TeamRepo<G>,
     pub(crate) liveboard: LiveboardRepo<G>,
+    /// Scores from [`Self::record_score`], until they are given to the leaderboard.
+    pub(crate) recorded_scores: Vec<(PlayerAlias, u32)>,
 }
 
 impl<G: GameArenaService> Context<G> {
@@ -27,11 +38,49 @@ impl<G: GameArenaService> Context<G> {
         Context {
             token: ArenaToken(thread_rng().gen()),
             bots,
+            agents: AgentRepo::default(),
             players: PlayerRepo::default(),
             #[cfg(feature = "teams")]
             teams: TeamRepo::default(),
             chat: ChatRepo::new(chat_log),
             liveboard: LiveboardRepo::default(),
+            recorded_scores: Vec::new(),
         }
     }
+
+    /// Sends a chat message from [`GameArenaService::authority_alias`] to every player.
+    pub fn announce(&mut self, text: String) {
+        let alias = G::authority_alias();
+        self.chat
+            .log_chat(IpAddr::V4(Ipv4Addr::UNSPECIFIED), alias, &text, false, "ok");
+        let message = MessageDto {
+            alias,
+            date_sent: get_unix_time_now(),
+            player_id: None,
+            user_id: None,
+            team_captain: false,
+            team_name: None,
+            text,
+            authentic: true,
+            whisper: false,
+        };
+        self.chat
+            .broadcast_message(Arc::new(message), &mut self.players);
+    }
+
+    /// Records a score to the leaderboard, even if it isn't on the liveboard (e.g. for winning a
+    /// round). Bots and unranked arenas are ignored.
+    pub fn record_score(&mut self, player_id: PlayerId, score: u32) {
+        if let Some(player) = self.players.borrow_player(player_id) {
+            if !player.is_bot() {
+                self.recorded_scores.push((player.alias(), score));
+            }
+        }
+    }
+
+    /// Changes the minimum number of bots, which takes effect gradually. Returns the new minimum,
+    /// which may be less than requested.
+    pub fn set_min_bots(&mut self, min_bots: usize) -> usize {
+        self.bots.set_min_bots(min_bots)
+    }
 }
@@ -2,15 +2,17 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::bot::BotRepo;
-use crate::client::ClientRepo;
+use crate::client::{ClientRepo, PlayerClientData};
 use crate::context::Context;
-use crate::game_service::GameArenaService;
+use crate::game_service::{GameArenaService, RestoredPlayer};
 use crate::invitation::InvitationRepo;
 use crate::leaderboard::LeaderboardRepo;
 use crate::metric::MetricRepo;
 use crate::plasma::PlasmaClient;
+use crate::player::{PlayerData, PlayerTuple};
 use core_protocol::dto::ServerDto;
 use core_protocol::id::ServerId;
+use core_protocol::name::PlayerAlias;
 use core_protocol::ServerNumber;
 use std::sync::Arc;
 
@@ -26,18 +28,37 @@ impl<G: GameArenaService> ContextService<G> {
         max_bots: Option<usize>,
         bot_percent: Option<usize>,
         chat_log: Option<String>,
+        options: &G::Options,
     ) -> Self {
         let bots = BotRepo::new_from_options(min_bots, max_bots, bot_percent);
+        let mut service = G::new(bots.min_bots, options);
+        let mut context = Context::new(bots, chat_log);
 
-        Self {
-            service: G::new(bots.min_bots),
-            context: Context::new(bots, chat_log),
+        for RestoredPlayer {
+            player_id,
+            token,
+            alias,
+            data,
+        } in service.restored_players()
+        {
+            let client = PlayerClientData::restored(token, alias);
+            let mut player = PlayerData::new(player_id, Some(Box::new(client)));
+            player.data = data;
+            context
+                .players
+                .players
+                .insert(player_id, Arc::new(PlayerTuple::new(player)));
         }
+
+        Self { service, context }
     }
 
+    /// Scores only count towards the `leaderboard` if `ranked`.
     #[allow(clippy::type_complexity)]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn update(
         &mut self,
+        ranked: bool,
         clients: &mut ClientRepo<G>,
         leaderboard: &mut LeaderboardRepo<G>,
         invitations: &mut InvitationRepo<G>,
@@ -46,7 +67,7 @@ impl<G: GameArenaService> ContextService<G> {
         server_id: ServerId,
         plasma: &PlasmaClient,
     ) {
-        // Spawn/de-spawn clients and bots.
+        // Spawn/de-spawn clients.
         clients.prune(
             &mut self.service,
             &mut self.context.players,
@@ -58,6 +79,35 @@ impl<G: GameArenaService> ContextService<G> {
             self.context.token,
             plasma,
         );
+
+        self.tick(metrics, |service, context, recorded_scores| {
+            clients.update(
+                service,
+                &mut context.players,
+                #[cfg(feature = "teams")]
+                &mut context.teams,
+                &mut context.liveboard,
+                leaderboard,
+                server_delta,
+            );
+
+            if ranked {
+                leaderboard.process(&context.liveboard, &context.players);
+                for (alias, score) in recorded_scores {
+                    leaderboard.record(alias, score);
+                }
+            }
+        });
+    }
+
+    /// Everything [`Self::update`] does except for clients, which `update_clients` updates
+    /// along with bots and agents, given the scores recorded this tick.
+    pub(crate) fn tick(
+        &mut self,
+        metrics: &mut MetricRepo<G>,
+        update_clients: impl FnOnce(&G, &mut Context<G>, Vec<(PlayerAlias, u32)>),
+    ) {
+        // Spawn/de-spawn bots.
         self.context
             .bots
             .update_count(&mut self.service, &mut self.context.players);
@@ -71,21 +121,15 @@ impl<G: GameArenaService> ContextService<G> {
             metrics,
         );
 
-        // Update clients and bots.
-        clients.update(
-            &self.service,
-            &mut self.context.players,
-            #[cfg(feature = "teams")]
-            &mut self.context.teams,
-            &mut self.context.liveboard,
-            leaderboard,
-            server_delta,
-        );
+        // Update clients, bots and agents.
+        let recorded_scores = std::mem::take(&mut self.context.recorded_scores);
+        update_clients(&self.service, &mut self.context, recorded_scores);
         self.context
             .bots
             .update(&self.service, &self.context.players);
-
-        leaderboard.process(&self.context.liveboard, &self.context.players);
+        self.context
+            .agents
+            .update(&self.service, &self.context.players);
 
         // Post-update game logic.
         self.service.post_update(&mut self.context);
@@ -95,5 +139,8 @@ impl<G: GameArenaService> ContextService<G> {
         self.context
             .bots
             .post_update(&mut self.service, &self.context.players);
+        self.context
+            .agents
+            .post_update(&mut self.service, &mut self.context.players);
     }
 }
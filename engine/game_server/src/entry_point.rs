@@ -46,7 +46,7 @@ use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
-use structopt::StructOpt;
+use structopt::{StructOpt, StructOptInternal};
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 
@@ -126,7 +126,10 @@ where
             thread_rng().gen::<NonZeroU64>().get()
         }, Ordering::Relaxed);
 
-        let options = Options::from_args();
+        // Game specific options are parsed alongside the engine's options.
+        let matches = G::Options::augment_clap(Options::clap()).get_matches();
+        let options = Options::from_clap(&matches);
+        let game_options = G::Options::from_clap(&matches);
 
         crate::log::init_logger(&options);
 
@@ -184,6 +187,7 @@ where
                 options.min_bots,
                 options.max_bots,
                 options.bot_percent,
+                options.agent_socket,
                 options.chat_log,
                 options.trace_log,
                 Arc::clone(&game_client),
@@ -192,6 +196,7 @@ where
                     Duration::from_secs(options.client_authenticate_rate_limit),
                     options.client_authenticate_burst,
                 ),
+                game_options,
             )
             .await,
         );
@@ -263,7 +268,7 @@ where
                     ip_address: addr.ip(),
                     referrer: query.referrer,
                     user_agent_id,
-                    realm_name: None, // TODO
+                    realm_name: query.realm_name,
                     player_id_token: query.player_id.zip(query.token),
                     session_token: query.session_token,
                     date_created: query.date_created.filter(|&d| d > 1680570365768 && d <= now).unwrap_or(now),
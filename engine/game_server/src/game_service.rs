@@ -3,13 +3,14 @@
 
 use crate::context::Context;
 use crate::player::{PlayerRepo, PlayerTuple};
-use core_protocol::id::{GameId, PlayerId, TeamId};
+use core_protocol::id::{GameId, PlayerId, TeamId, Token};
 use core_protocol::name::PlayerAlias;
 use core_protocol::prelude::*;
 use std::fmt::Debug;
 use std::marker::Send;
 use std::sync::Arc;
 use std::time::Duration;
+use structopt::{StructOpt, StructOptInternal};
 
 /// A modular game service (representing one arena).
 pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
@@ -18,6 +19,9 @@ pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
     const TICK_PERIOD_SECS: f32;
     /// How long a player can remain in limbo after they lose connection.
     const LIMBO: Duration = Duration::from_secs(6);
+    /// How long a restored player (see [`Self::restored_players`]) can remain in limbo before
+    /// their client reconnects.
+    const RESTORE_LIMBO: Duration = Duration::from_secs(120);
     /// Start player score at this.
     const DEFAULT_SCORE: u32 = 0;
     /// Minimum score to report another player, to slow report-abuse.
@@ -36,13 +40,33 @@ pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
     const TEAM_JOINS_MAX: usize = 3;
 
     type Bot: 'static + Bot<Self>;
+    /// Game specific server options, parsed along with the engine's options.
+    type Options: 'static + StructOpt + StructOptInternal + Clone + Debug + Send + Sync;
     type ClientData: 'static + Default + Debug + Unpin + Send + Sync;
     type GameUpdate: 'static + Sync + Send + Encode + Decode;
-    type GameRequest: 'static + Decode + Send + Unpin;
+    /// Agents (see [`crate::agent`]) send these as JSON, so they must be deserializable.
+    type GameRequest: 'static + Decode + DeserializeOwned + Send + Unpin;
+    /// What agents are sent instead of [`Self::GameUpdate`]s, as JSON.
+    type AgentUpdate: 'static + Serialize;
     type PlayerData: 'static + Default + Unpin + Send + Sync + Debug;
     type PlayerExtension: 'static + Default + Unpin + Send + Sync;
 
-    fn new(min_players: usize) -> Self;
+    fn new(min_players: usize, options: &Self::Options) -> Self;
+
+    /// Called once after [`Self::new`] to recreate players that were saved before the server
+    /// restarted. They are considered to have joined the game, and remain in limbo until their
+    /// client reconnects with the same [`Token`].
+    fn restored_players(&mut self) -> Vec<RestoredPlayer<Self>> {
+        Vec::new()
+    }
+
+    /// Options for a private realm, based on the main arena's `options` and the `bot_difficulty`
+    /// its creator chose. Anything that can't be shared by multiple arenas, such as files, must be
+    /// disabled. Returning [`None`] disables private realms.
+    fn realm_options(options: &Self::Options, bot_difficulty: u8) -> Option<Self::Options> {
+        let _ = (options, bot_difficulty);
+        None
+    }
 
     /// Get alias of authority figure (that, for example, sends chat moderation warnings).
     fn authority_alias() -> PlayerAlias {
@@ -89,6 +113,19 @@ pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
         _players: &PlayerRepo<Self>,
     ) -> Option<Self::GameUpdate>;
 
+    /// Called when an agent (a bot in another process, see [`crate::agent`]) issues a command.
+    /// Agents aren't clients, so they have their own `client_data`.
+    fn agent_command(
+        &mut self,
+        command: Self::GameRequest,
+        player_tuple: &Arc<PlayerTuple<Self>>,
+        client_data: &mut Self::ClientData,
+        players: &PlayerRepo<Self>,
+    ) -> Option<Self::GameUpdate> {
+        let _ = client_data;
+        self.player_command(command, player_tuple, players)
+    }
+
     /// Called when a player's [`TeamId`] changes.
     #[cfg(feature = "teams")]
     fn player_changed_team(
@@ -127,8 +164,27 @@ pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
         _players: &PlayerRepo<Self>,
     ) -> Option<Self::GameUpdate>;
 
+    /// Gets an agent's update (see [`crate::agent`]) for the current tick. Agents that get
+    /// [`None`] aren't sent anything.
+    fn get_agent_update(
+        &self,
+        player_tuple: &Arc<PlayerTuple<Self>>,
+        client_data: &mut Self::ClientData,
+        players: &PlayerRepo<Self>,
+    ) -> Option<Self::AgentUpdate> {
+        let _ = (player_tuple, client_data, players);
+        None
+    }
+
     /// Returns true iff the player is considered to be "alive" i.e. they cannot change their alias.
     fn is_alive(&self, player_tuple: &Arc<PlayerTuple<Self>>) -> bool;
+
+    /// Returns true iff the player is only watching. Spectators are considered out of the game, so
+    /// they don't count towards live players (or the number of bots).
+    fn is_spectator(&self, player_tuple: &Arc<PlayerTuple<Self>>) -> bool {
+        let _ = player_tuple;
+        false
+    }
     /// Before sending.
     fn tick(&mut self, context: &mut Context<Self>);
     /// After sending.
@@ -142,6 +198,15 @@ pub trait GameArenaService: 'static + Unpin + Sized + Send + Sync {
     fn world_size(&self) -> f32;
 }
 
+/// A real player to recreate on startup. See [`GameArenaService::restored_players`].
+pub struct RestoredPlayer<G: GameArenaService> {
+    pub player_id: PlayerId,
+    /// Must match the token the client reconnects with.
+    pub token: Token,
+    pub alias: PlayerAlias,
+    pub data: G::PlayerData,
+}
+
 /// Implemented by game bots.
 pub trait Bot<G: GameArenaService>: Default + Unpin + Sized + Send {
     /// See bot.rs for explanation.
@@ -212,6 +277,10 @@ impl<G: GameArenaService> Bot<G> for () {
 #[cfg(test)]
 pub struct MockGame;
 
+#[cfg(test)]
+#[derive(Clone, Debug, StructOpt)]
+pub struct MockGameOptions {}
+
 #[cfg(test)]
 #[derive(Default)]
 pub struct MockGameBot;
@@ -248,13 +317,15 @@ impl GameArenaService for MockGame {
     const TEAM_JOINS_MAX: usize = 2;
 
     type Bot = MockGameBot;
+    type Options = MockGameOptions;
     type ClientData = ();
     type GameUpdate = ();
     type GameRequest = ();
+    type AgentUpdate = ();
     type PlayerData = ();
     type PlayerExtension = ();
 
-    fn new(_min_players: usize) -> Self {
+    fn new(_min_players: usize, _options: &Self::Options) -> Self {
         Self
     }
 
@@ -281,6 +352,15 @@ impl GameArenaService for MockGame {
         Some(())
     }
 
+    fn get_agent_update(
+        &self,
+        _player_tuple: &Arc<PlayerTuple<Self>>,
+        _client_data: &mut Self::ClientData,
+        _players: &PlayerRepo<Self>,
+    ) -> Option<Self::AgentUpdate> {
+        Some(())
+    }
+
     fn is_alive(&self, _player_tuple: &Arc<PlayerTuple<Self>>) -> bool {
         false
     }
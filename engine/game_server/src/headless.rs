@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::client::PlayerClientData;
+use crate::context_service::ContextService;
+use crate::game_service::GameArenaService;
+use crate::metric::MetricRepo;
+use crate::player::{PlayerData, PlayerRepo, PlayerTuple};
+use core_protocol::id::PlayerId;
+use core_protocol::name::PlayerAlias;
+#[cfg(feature = "teams")]
+use core_protocol::rpc::{TeamRequest, TeamUpdate};
+use rand::{thread_rng, Rng};
+use server_util::generate_id::generate_id;
+use std::sync::Arc;
+
+/// An arena of only bots, without any networking, which ticks as fast as it is told to (e.g. for
+/// bot tournaments and balance testing).
+pub struct Headless<G: GameArenaService> {
+    context_service: ContextService<G>,
+    /// Unused, since bots have no metrics, but required to update players.
+    metrics: MetricRepo<G>,
+}
+
+impl<G: GameArenaService> Headless<G> {
+    /// Creates an arena with exactly `bots` bots, which join over the first few ticks.
+    pub fn new(bots: usize, options: &G::Options) -> Self {
+        Self {
+            context_service: ContextService::new(Some(bots), Some(bots), Some(0), None, options),
+            metrics: MetricRepo::new(),
+        }
+    }
+
+    /// Does the same as [`ContextService::update`], minus clients. There is no leaderboard, so
+    /// recorded scores are discarded.
+    pub fn tick(&mut self) {
+        self.context_service.tick(&mut self.metrics, |_, _, _| {});
+    }
+
+    /// Adds a real player, whose client never connects, for testing what bots can't do (e.g.
+    /// chatting or being saved). Returns its id.
+    pub fn join(&mut self, alias: PlayerAlias) -> PlayerId {
+        let ContextService { context, service } = &mut self.context_service;
+        let player_id = loop {
+            let player_id = PlayerId(generate_id());
+            if !context.players.contains(player_id) {
+                break player_id;
+            }
+        };
+        let client = PlayerClientData::restored(thread_rng().gen(), alias);
+        let player_tuple = Arc::new(PlayerTuple::new(PlayerData::new(
+            player_id,
+            Some(Box::new(client)),
+        )));
+        context.players.insert(player_id, Arc::clone(&player_tuple));
+        service.player_joined(&player_tuple, &context.players);
+        player_id
+    }
+
+    /// Issues a command as if a player's client sent it.
+    pub fn command(&mut self, player_id: PlayerId, command: G::GameRequest) {
+        let ContextService { context, service } = &mut self.context_service;
+        if let Some(player_tuple) = context.players.get(player_id) {
+            let _ = service.player_command(command, player_tuple, &context.players);
+        }
+    }
+
+    /// Issues a chat command (without the leading slash) as a player, returning the reply.
+    pub fn chat_command(&mut self, player_id: PlayerId, command: &str) -> Option<String> {
+        let ContextService { context, service } = &mut self.context_service;
+        service.chat_command(command, player_id, &context.players)
+    }
+
+    /// Issues a team request as if a player's client sent it.
+    #[cfg(feature = "teams")]
+    pub fn team_request(
+        &mut self,
+        player_id: PlayerId,
+        request: TeamRequest,
+    ) -> Result<TeamUpdate, &'static str> {
+        let context = &mut self.context_service.context;
+        context
+            .teams
+            .handle_team_request(player_id, request, &mut context.players)
+    }
+
+    pub fn service(&self) -> &G {
+        &self.context_service.service
+    }
+
+    pub fn service_mut(&mut self) -> &mut G {
+        &mut self.context_service.service
+    }
+
+    pub fn players(&self) -> &PlayerRepo<G> {
+        &self.context_service.context.players
+    }
+}
@@ -3,25 +3,28 @@
 
 use crate::admin::AdminRepo;
 use crate::arena::ArenaRepo;
-use crate::client::ClientRepo;
+use crate::client::{ClientRepo, ClientStatus};
 use crate::context_service::ContextService;
-use crate::game_service::GameArenaService;
+use crate::game_service::{Bot, GameArenaService};
 use crate::invitation::InvitationRepo;
 use crate::leaderboard::LeaderboardRepo;
 use crate::metric::MetricRepo;
 use crate::plasma::PlasmaClient;
 use crate::system::SystemRepo;
 use actix::AsyncContext;
-use actix::{Actor, Context as ActorContext};
-use core_protocol::id::{ClientHash, RegionId, ServerId};
-use core_protocol::{PlasmaRequestV1, PlasmaUpdate};
+use actix::{Actor, ActorFutureExt, Context as ActorContext, ContextFutureSpawner, WrapFuture};
+use core_protocol::id::{ClientHash, PlayerId, RegionId, ServerId};
+use core_protocol::rpc::{InvitationUpdate, Update};
+use core_protocol::{PlasmaRequestV1, PlasmaUpdate, RealmName};
 use futures::stream::FuturesUnordered;
 use log::{error, info};
 use minicdn::MiniCdn;
 use server_util::health::Health;
+use server_util::observer::ObserverUpdate;
 use server_util::rate_limiter::RateLimiterProps;
 use std::future::Future;
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, AtomicU8};
 use std::sync::{Arc, RwLock};
@@ -40,6 +43,8 @@ pub struct Infrastructure<G: GameArenaService> {
 
     /// Game specific stuff.
     pub(crate) arenas: ArenaRepo<G>,
+    /// Options of the main arena, which private realms are based on.
+    game_options: G::Options,
     /// Game client information.
     pub(crate) clients: ClientRepo<G>,
     /// Shared invitations.
@@ -98,6 +103,10 @@ impl<G: GameArenaService> Actor for Infrastructure<G> {
 }
 
 impl<G: GameArenaService> Infrastructure<G> {
+    /// How often each player can create a private realm.
+    const CREATE_REALM_RATE: RateLimiterProps =
+        RateLimiterProps::const_new(Duration::from_secs(60), 2);
+
     /// new returns a game server with the specified parameters.
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
@@ -109,12 +118,23 @@ impl<G: GameArenaService> Infrastructure<G> {
         min_bots: Option<usize>,
         max_bots: Option<usize>,
         bot_percent: Option<usize>,
+        agent_socket: Option<PathBuf>,
         chat_log: Option<String>,
         trace_log: Option<String>,
         game_client: Arc<RwLock<MiniCdn>>,
         server_token: &'static AtomicU64,
         client_authenticate: RateLimiterProps,
+        game_options: G::Options,
     ) -> Self {
+        let mut main =
+            ContextService::new(min_bots, max_bots, bot_percent, chat_log, &game_options);
+        if let Some(path) = agent_socket {
+            // Only the main arena has agents.
+            main.context.agents.listen(&path).unwrap_or_else(|e| {
+                panic!("could not listen for agents on {}: {e}", path.display())
+            });
+        }
+
         Self {
             server_id,
             ipv4_address,
@@ -123,12 +143,8 @@ impl<G: GameArenaService> Infrastructure<G> {
             plasma: PlasmaClient::new(redirect_server_number, server_token),
             system: SystemRepo::new(),
             admin: AdminRepo::new(game_client, client_hash),
-            arenas: ArenaRepo::new(ContextService::new(
-                min_bots,
-                max_bots,
-                bot_percent,
-                chat_log,
-            )),
+            arenas: ArenaRepo::new(main),
+            game_options,
             health: Health::default(),
             invitations: InvitationRepo::default(),
             leaderboard: LeaderboardRepo::default(),
@@ -148,8 +164,9 @@ impl<G: GameArenaService> Infrastructure<G> {
         self.last_update = now;
 
         let server_delta = self.system.delta();
-        for (_, context_service) in self.arenas.iter_mut() {
+        for (realm_name, context_service) in self.arenas.iter_mut() {
             context_service.update(
+                realm_name.is_none(),
                 &mut self.clients,
                 &mut self.leaderboard,
                 &mut self.invitations,
@@ -160,6 +177,10 @@ impl<G: GameArenaService> Infrastructure<G> {
             );
         }
         self.leaderboard.clear_deltas();
+        for realm_name in self.arenas.prune_realms() {
+            info!("removed expired realm {realm_name:?}");
+            self.invitations.forget_realm(realm_name);
+        }
         self.health.record_tick(G::TICK_PERIOD_SECS);
 
         // These are all rate-limited internally.
@@ -178,4 +199,89 @@ impl<G: GameArenaService> Infrastructure<G> {
             self.ipv4_address,
         );
     }
+
+    /// Starts creating a private realm for a player in `sender_realm`. Once its world is
+    /// generated, the player is sent an invitation to it.
+    pub(crate) fn create_realm(
+        &mut self,
+        player_id: PlayerId,
+        sender_realm: Option<RealmName>,
+        realm_name: RealmName,
+        bots: u8,
+        bot_difficulty: u8,
+        ctx: &mut <Self as Actor>::Context,
+    ) -> Result<(), &'static str> {
+        let options =
+            G::realm_options(&self.game_options, bot_difficulty).ok_or("realms are disabled")?;
+        {
+            let mut player = self
+                .arenas
+                .get(sender_realm)
+                .and_then(|context_service| {
+                    context_service.context.players.borrow_player_mut(player_id)
+                })
+                .ok_or("nonexistent player")?;
+            let client = player
+                .client_mut()
+                .ok_or("only clients can create realms")?;
+            if client
+                .invitation
+                .create_realm_rate_limit
+                .should_limit_rate(&Self::CREATE_REALM_RATE)
+            {
+                return Err("rate limit exceeded");
+            }
+        }
+        self.arenas.reserve_realm(realm_name)?;
+
+        let bots = (bots as usize)
+            .min(ArenaRepo::<G>::REALM_BOTS_MAX)
+            .min(G::Bot::DEFAULT_MAX_BOTS);
+        // Generating a world takes a while, so don't block the other arenas.
+        tokio::task::spawn_blocking(move || {
+            ContextService::new(Some(bots), Some(bots), Some(0), None, &options)
+        })
+        .into_actor(self)
+        .map(move |result, act, _ctx| match result {
+            Ok(context_service) => {
+                info!("created realm {realm_name:?} with {bots} bots");
+                act.arenas.insert_realm(realm_name, context_service);
+                act.invite_to_realm(player_id, sender_realm, realm_name);
+            }
+            Err(e) => {
+                error!("could not create realm {realm_name:?}: {e}");
+                act.arenas.cancel_realm(realm_name);
+            }
+        })
+        .spawn(ctx);
+        Ok(())
+    }
+
+    /// Sends the creator of a private realm an invitation to it.
+    fn invite_to_realm(
+        &mut self,
+        player_id: PlayerId,
+        sender_realm: Option<RealmName>,
+        realm_name: RealmName,
+    ) {
+        let invitation_id = self
+            .invitations
+            .create_realm(player_id, realm_name, self.server_id);
+
+        // The player may have left while the realm was being created.
+        let Some(player) = self
+            .arenas
+            .get(sender_realm)
+            .and_then(|context_service| context_service.context.players.borrow_player(player_id))
+        else {
+            return;
+        };
+        if let Some(ClientStatus::Connected { observer }) =
+            player.client().map(|client| &client.status)
+        {
+            let _ = observer.send(ObserverUpdate::Send {
+                message: Update::Invitation(InvitationUpdate::Created(invitation_id)),
+            });
+        }
+    }
 }
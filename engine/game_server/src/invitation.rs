@@ -9,6 +9,7 @@ use core_protocol::dto::InvitationDto;
 use core_protocol::id::{InvitationId, PlayerId, ServerId};
 use core_protocol::rpc::{InvitationRequest, InvitationUpdate};
 use core_protocol::RealmName;
+use server_util::rate_limiter::RateLimiterState;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::marker::PhantomData;
@@ -36,6 +37,8 @@ pub struct ClientInvitationData {
     pub invitation_accepted: Option<InvitationDto>,
     /// Outgoing invitation created by player.
     pub invitation_created: Option<InvitationId>,
+    /// Limits how often the player can create private realms.
+    pub create_realm_rate_limit: RateLimiterState,
 }
 
 impl ClientInvitationData {
@@ -43,6 +46,7 @@ impl ClientInvitationData {
         Self {
             invitation_accepted,
             invitation_created: None,
+            create_realm_rate_limit: RateLimiterState::default(),
         }
     }
 }
@@ -141,6 +145,31 @@ impl<G: GameArenaService> InvitationRepo<G> {
         Ok(InvitationUpdate::Created(invitation_id))
     }
 
+    /// Creates an invitation to a private realm, which lasts until [`Self::forget_realm`].
+    pub(crate) fn create_realm(
+        &mut self,
+        req_player_id: PlayerId,
+        realm_name: RealmName,
+        server_id: ServerId,
+    ) -> InvitationId {
+        loop {
+            let invitation_id = InvitationId::generate(server_id.cloud_server_number());
+            if let Entry::Vacant(entry) = self.invitations.entry(invitation_id) {
+                entry.insert(Invitation {
+                    realm_name: Some(realm_name),
+                    player_id: req_player_id,
+                });
+                break invitation_id;
+            }
+        }
+    }
+
+    /// Forgets every invitation to a private realm that was removed.
+    pub(crate) fn forget_realm(&mut self, realm_name: RealmName) {
+        self.invitations
+            .retain(|_, invitation| invitation.realm_name != Some(realm_name));
+    }
+
     pub fn handle_invitation_request(
         &mut self,
         player_id: PlayerId,
@@ -154,6 +183,8 @@ impl<G: GameArenaService> InvitationRepo<G> {
                 self.accept(player_id, invitation_id, players)
             }
             InvitationRequest::Create => self.create(player_id, realm_name, server_id, players),
+            // Handled by the infrastructure, since it concerns every arena.
+            InvitationRequest::CreateRealm { .. } => Err("unhandled realm request"),
         }
     }
 }
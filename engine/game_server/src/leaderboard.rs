@@ -101,6 +101,12 @@ impl<G: GameArenaService> LeaderboardRepo<G> {
         }
     }
 
+    /// Records a score regardless of the liveboard.
+    pub(crate) fn record(&mut self, alias: PlayerAlias, score: u32) {
+        let entry = self.pending.entry(alias).or_insert(0);
+        *entry = score.max(*entry);
+    }
+
     /// Returns scores pending database commit, draining them in the process. Rate limited.
     pub fn take_pending(&mut self) -> Option<Box<[LeaderboardScoreDto]>> {
         if self.pending.is_empty() || self.take_pending_rate_limit.should_limit_rate() {
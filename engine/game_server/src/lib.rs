@@ -14,6 +14,7 @@
 #![feature(let_chains)]
 
 pub mod admin;
+pub mod agent;
 pub mod arena;
 pub mod bot;
 pub mod chat;
@@ -22,6 +23,7 @@ pub mod context;
 pub mod context_service;
 pub mod entry_point;
 pub mod game_service;
+pub mod headless;
 pub mod infrastructure;
 pub mod invitation;
 pub mod leaderboard;
@@ -90,6 +90,30 @@ impl<G: GameArenaService> ClientMetricData<G> {
             _spooky: PhantomData,
         }
     }
+
+    /// For a client restored from before the server restarted, whose visit is considered to be
+    /// in progress.
+    pub(crate) fn restored() -> Self {
+        let now = Instant::now();
+        Self {
+            cohort_id: thread_rng().gen(),
+            user_agent_id: None,
+            referrer: None,
+            region_id: None,
+            fps: None,
+            rtt: None,
+            date_created: get_unix_time_now(),
+            renewed: true,
+            created: now,
+            play_started: None,
+            play_stopped: None,
+            visit_started: Some(now),
+            visit_stopped: None,
+            plays: 0,
+            visit_plays: 0,
+            _spooky: PhantomData,
+        }
+    }
 }
 
 /// Stores a T for each of several queries, and an aggregate.
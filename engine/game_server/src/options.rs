@@ -3,6 +3,7 @@
 
 use core_protocol::id::RegionId;
 use log::LevelFilter;
+use std::path::PathBuf;
 use std::{net::IpAddr, sync::Arc};
 use structopt::StructOpt;
 
@@ -18,6 +19,9 @@ pub struct Options {
     /// This percent of real players will help determine number of bots.
     #[structopt(long)]
     pub bot_percent: Option<usize>,
+    /// Accept agents (bots in other processes) on a Unix socket at this path.
+    #[structopt(long, parse(from_os_str))]
+    pub agent_socket: Option<PathBuf>,
     /// Log incoming HTTP requests
     #[cfg_attr(debug_assertions, structopt(long, default_value = "warn"))]
     #[cfg_attr(not(debug_assertions), structopt(long, default_value = "error"))]
@@ -180,8 +180,10 @@ impl<G: GameArenaService> PlayerRepo<G> {
     ) {
         for pt in self.iter() {
             let is_alive = service.is_alive(pt);
+            let is_spectator = service.is_spectator(pt);
             let mut p = pt.borrow_player_mut();
             let player_id = p.player_id;
+            p.was_spectator = is_spectator;
 
             if is_alive != p.was_alive {
                 if is_alive {
@@ -433,6 +435,8 @@ pub struct PlayerData<G: GameArenaService> {
     pub(crate) was_alive: bool,
     /// Whether the player was out of game last time we checked.
     pub(crate) was_out_of_game: bool,
+    /// Whether the player was a spectator last time we checked.
+    was_spectator: bool,
     /// Whether the player was *ever* alive.
     was_ever_alive: bool,
     /// When was_alive was set to its current value.
@@ -450,6 +454,7 @@ impl<G: GameArenaService> PlayerData<G> {
             score: G::DEFAULT_SCORE,
             was_alive: false,
             was_out_of_game: false,
+            was_spectator: false,
             was_ever_alive: false,
             was_alive_timestamp: Instant::now(),
             client,
@@ -512,10 +517,12 @@ impl<G: GameArenaService> PlayerData<G> {
         self.player_id.is_bot()
     }
 
-    /// Returns true iff the player 1) never played yet 2) stopped playing over half a minute ago.
+    /// Returns true iff the player 1) never played yet 2) stopped playing over half a minute ago
+    /// 3) is a spectator.
     pub fn is_out_of_game(&self) -> bool {
         !self.was_ever_alive
             || self.not_alive_duration().unwrap_or(Duration::ZERO) > Duration::from_secs(30)
+            || self.was_spectator
     }
 }
 
@@ -184,29 +184,30 @@ pub fn derive_tower_type_data(input: TokenStream) -> TokenStream {
 
         let output: proc_macro2::TokenStream = quote! {
             impl #ident {
-                /// Doesn't count ruler boost.
-                pub fn raw_unit_capacity(self, unit: Unit) -> usize {
+                /// Built-in default of [`Self::raw_unit_capacity`].
+                pub(crate) fn default_raw_unit_capacity(self, unit: Unit) -> usize {
                     match self {
                         #(#tower_capacities,)*
                     }
                 }
 
-                pub fn unit_generation(self, unit: Unit) -> Option<Ticks> {
+                /// Built-in default of [`Self::unit_generation`].
+                pub(crate) fn default_unit_generation(self, unit: Unit) -> Option<Ticks> {
                     match self {
                         #(#tower_generations,)*
                     }
                 }
 
-                /// Upgrading to this tower requires this much of this other tower.
-                pub fn prerequisite(self, tower_type: TowerType) -> u8 {
+                /// Built-in default of [`Self::prerequisite`].
+                pub(crate) fn default_prerequisite(self, tower_type: TowerType) -> u8 {
                     match self {
                         #(#tower_prerequisites,)*
                         _ => 0
                     }
                 }
 
-                /// How long it should take to upgrade/downgrade to this tower.
-                pub fn delay(self) -> Ticks {
+                /// Built-in default of [`Self::delay`].
+                pub(crate) fn default_delay(self) -> Ticks {
                     match self {
                         #(#tower_delays,)*
                         _ => Ticks::ZERO
@@ -221,20 +222,23 @@ pub fn derive_tower_type_data(input: TokenStream) -> TokenStream {
                     })
                 }
 
-                pub fn sensor_radius(self) -> u16 {
+                /// Built-in default of [`Self::sensor_radius`].
+                pub(crate) fn default_sensor_radius(self) -> u16 {
                     match self {
                         #(#sensor_radii,)*
                     }
                 }
 
-                pub fn score_weight(self) -> u32 {
+                /// Built-in default of [`Self::score_weight`].
+                pub(crate) fn default_score_weight(self) -> u32 {
                     match self {
                         #(#score_weights,)*
                         _ => 1
                     }
                 }
 
-                pub fn is_spawnable(self) -> bool {
+                /// Built-in default of [`Self::is_spawnable`].
+                pub(crate) fn default_is_spawnable(self) -> bool {
                     match self {
                         #(#spawnables,)*
                         _ => false
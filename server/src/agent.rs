@@ -0,0 +1,75 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! What agents (bots in other processes, see [`game_server::agent`]) are sent every tick. Tower
+//! types and units are numbers, in the order they are declared in.
+
+use crate::service::PlayerData;
+use common::tower::{Tower, TowerId, TowerType};
+use common::unit::Unit;
+use common::world::World;
+use core_protocol::id::PlayerId;
+use core_protocol::prelude::*;
+use fxhash::FxHashSet;
+
+/// A tower, as agents see it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentTower {
+    pub tower_id: TowerId,
+    pub tower_type: TowerType,
+    pub player_id: Option<PlayerId>,
+    /// How many of each unit are in the tower.
+    pub units: Vec<(Unit, usize)>,
+    /// Towers with a road to this one.
+    pub neighbors: Vec<TowerId>,
+}
+
+impl AgentTower {
+    fn new(tower_id: TowerId, tower: &Tower) -> Self {
+        Self {
+            tower_id,
+            tower_type: tower.tower_type,
+            player_id: tower.player_id,
+            units: tower.units.iter().collect(),
+            neighbors: tower_id.neighbors().collect(),
+        }
+    }
+}
+
+/// Sent to an agent every tick.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentUpdate {
+    pub alive: bool,
+    /// The agent's towers.
+    pub towers: Vec<AgentTower>,
+    /// Towers next to the agent's towers that it doesn't own, where it could send forces.
+    pub frontier: Vec<AgentTower>,
+}
+
+impl AgentUpdate {
+    pub(crate) fn new(world: &World, player_id: PlayerId, player: &PlayerData) -> Self {
+        let tower = |tower_id: TowerId| {
+            world
+                .chunk
+                .get(tower_id)
+                .map(|tower| AgentTower::new(tower_id, tower))
+        };
+        let frontier: FxHashSet<_> = player
+            .towers
+            .iter()
+            .flat_map(|tower_id| tower_id.neighbors())
+            .filter(|&tower_id| {
+                world
+                    .chunk
+                    .get(tower_id)
+                    .map_or(false, |tower| tower.player_id != Some(player_id))
+            })
+            .collect();
+
+        Self {
+            alive: player.alive,
+            towers: player.towers.iter().filter_map(|&t| tower(t)).collect(),
+            frontier: frontier.into_iter().filter_map(tower).collect(),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::force::{Deployment, Path};
+use common::protocol::Command;
+use common::unit::Unit;
+use core_protocol::id::PlayerId;
+use server::agent::{AgentTower, AgentUpdate};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// An example agent, which connects to a server started with `--agent-socket`, spawns and sends
+/// soldiers to capture the towers next to its own, one force per tick.
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// The server's agent socket.
+    #[structopt(parse(from_os_str))]
+    socket: PathBuf,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let stream = UnixStream::connect(&options.socket)
+        .unwrap_or_else(|e| panic!("could not connect to {}: {e}", options.socket.display()));
+    let mut writer = stream.try_clone().unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    let mut send = |command: &Command| {
+        let mut line = serde_json::to_vec(command).unwrap();
+        line.push(b'\n');
+        writer.write_all(&line).expect("disconnected");
+    };
+
+    let line = lines.next().expect("disconnected").unwrap();
+    let player_id: PlayerId = serde_json::from_str(&line).unwrap();
+    println!("playing as {player_id:?}");
+
+    let count = |tower: &AgentTower, filter: fn(Unit) -> bool| -> usize {
+        tower
+            .units
+            .iter()
+            .filter(|&&(unit, _)| filter(unit))
+            .map(|&(_, count)| count)
+            .sum()
+    };
+    for line in lines {
+        let update: AgentUpdate = serde_json::from_str(&line.unwrap()).unwrap();
+        if !update.alive {
+            send(&Command::Spawn);
+            continue;
+        }
+
+        // Half of a tower's soldiers attack a neighbor with fewer defenders.
+        let attack = update.towers.iter().find_map(|tower| {
+            let attackers = count(tower, |unit| unit == Unit::Soldier) / 2;
+            update
+                .frontier
+                .iter()
+                .find(|target| {
+                    tower.neighbors.contains(&target.tower_id)
+                        && count(target, |_| true) < attackers
+                })
+                .map(|target| (tower.tower_id, target.tower_id))
+        });
+        if let Some((tower_id, target_id)) = attack {
+            send(&Command::DeployForce {
+                tower_id,
+                path: Path::new(vec![tower_id, target_id]),
+                legs: Vec::new(),
+                deployment: Deployment::Percent(50),
+            });
+        }
+    }
+    println!("disconnected");
+}
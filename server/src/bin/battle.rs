@@ -0,0 +1,121 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::balance::{Balance, BalanceFile};
+use common::combatants::{CombatSide, Combatants};
+use common::tower::TowerType;
+use common::unit::Unit;
+use common::units::Units;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Fights two compositions of units against each other, and prints the outcome.
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Attacking force, as comma separated units and counts (e.g. `Soldier:10,Tank:2`).
+    #[structopt(default_value = "")]
+    attacker: String,
+    /// Defending force (or tower's units with `--tower`), in the same format as the attacker.
+    #[structopt(default_value = "")]
+    defender: String,
+    /// The defender is a tower of this type, whose capacity limits its units.
+    #[structopt(long)]
+    tower: Option<TowerType>,
+    /// Load tower and unit balance overrides from this TOML file.
+    #[structopt(long, parse(from_os_str))]
+    balance: Option<PathBuf>,
+    /// Print the damage matrix instead of fighting.
+    #[structopt(long)]
+    matrix: bool,
+}
+
+fn main() {
+    let options = Options::from_args();
+    if let Some(path) = &options.balance {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("could not read balance {}: {e}", path.display()));
+        let file: BalanceFile = toml::from_str(&contents)
+            .unwrap_or_else(|e| panic!("could not parse balance {}: {e}", path.display()));
+        Balance::set(
+            Balance::try_from(file)
+                .unwrap_or_else(|e| panic!("invalid balance {}: {e}", path.display())),
+        );
+    }
+
+    if options.matrix {
+        println!("Units fight in this order (per field, air first):");
+        let order: Vec<_> = Unit::iter().map(|unit| unit.to_string()).collect();
+        println!("  {}", order.join(", "));
+        println!();
+        print!("{}", Balance::get().damage_table());
+        return;
+    }
+
+    let mut attacker = parse_units(&options.attacker, None);
+    let mut defender = parse_units(&options.defender, options.tower);
+    if attacker.is_empty() || (defender.is_empty() && options.tower.is_none()) {
+        eprintln!("both sides need units (e.g. `battle Soldier:10 Shield:5 --tower Village`)");
+        std::process::exit(1);
+    }
+    println!("attacker: {}", format_units(&attacker));
+    match options.tower {
+        Some(tower_type) => println!("defender: {tower_type} {}", format_units(&defender)),
+        None => println!("defender: {}", format_units(&defender)),
+    }
+
+    let mut attacker_combatants = Combatants::force(&mut attacker);
+    let mut defender_combatants = match options.tower {
+        Some(tower_type) => Combatants::tower(tower_type, &mut defender),
+        None => Combatants::force(&mut defender),
+    };
+    let winner = Combatants::fight(&mut attacker_combatants, &mut defender_combatants, |info| {
+        println!("  {info:?}")
+    });
+
+    println!(
+        "winner: {}",
+        match winner {
+            Some(CombatSide::Attacker) => "attacker",
+            Some(CombatSide::Defender) => "defender",
+            None => "stalemate",
+        }
+    );
+    println!("attacker remaining: {}", format_units(&attacker));
+    println!("defender remaining: {}", format_units(&defender));
+}
+
+/// Parses units such as `Soldier:10,Tank:2`, which are limited by the capacity of `tower_type`
+/// if it is [`Some`].
+fn parse_units(s: &str, tower_type: Option<TowerType>) -> Units {
+    let mut units = Units::default();
+    for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (unit, count) = part.split_once(':').unwrap_or((part, "1"));
+        let unit = Unit::iter()
+            .find(|u| u.to_string().eq_ignore_ascii_case(unit.trim()))
+            .unwrap_or_else(|| panic!("unknown unit {unit:?}"));
+        let count: usize = count
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid count {count:?}"));
+        let added = if let Some(tower_type) = tower_type {
+            units.add_to_tower(unit, count, tower_type, false)
+        } else {
+            units.add(unit, count)
+        };
+        if added < count {
+            eprintln!("only {added} of {count} {unit} fit");
+        }
+    }
+    units
+}
+
+fn format_units(units: &Units) -> String {
+    if units.is_empty() {
+        return String::from("nothing");
+    }
+    units
+        .iter()
+        .map(|(unit, count)| format!("{unit}:{count}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
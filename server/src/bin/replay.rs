@@ -0,0 +1,82 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::replay::ReplayPlayer;
+use common::ticks::Ticks;
+use common_util::storage::Map;
+use core_protocol::id::PlayerId;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Re-simulates a replay recorded with the server's `--record` option, and summarizes the world.
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Replay file.
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    /// Jump to this many ticks after the start of the recording, instead of the end.
+    #[structopt(long)]
+    tick: Option<usize>,
+    /// Also summarize every this many ticks along the way.
+    #[structopt(long)]
+    every: Option<usize>,
+    /// How many of the largest players to list.
+    #[structopt(long, default_value = "10")]
+    top: usize,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let mut replay = ReplayPlayer::load(&options.path)
+        .unwrap_or_else(|e| panic!("could not load {}: {e}", options.path.display()));
+    println!(
+        "{} ticks ({:.0}s) recorded",
+        replay.ticks(),
+        replay.ticks() as f32 * Ticks::PERIOD_SECS
+    );
+
+    let end = options.tick.unwrap_or(usize::MAX).min(replay.ticks());
+    let every = options.every.unwrap_or(usize::MAX).max(1);
+    loop {
+        let position = replay.position().saturating_add(every).min(end);
+        if let Err(e) = replay.seek(position, |_| {}) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        summarize(&replay, options.top);
+        if position == end {
+            break;
+        }
+    }
+}
+
+fn summarize(replay: &ReplayPlayer, top: usize) {
+    let world = replay.world();
+    let mut towers = 0;
+    let mut forces = 0;
+    let mut owned = BTreeMap::<PlayerId, usize>::new();
+    for (_, tower) in world.chunk.iter_towers() {
+        towers += 1;
+        forces += tower.inbound_forces.len() + tower.outbound_forces.len();
+        if let Some(player_id) = tower.player_id {
+            *owned.entry(player_id).or_default() += 1;
+        }
+    }
+
+    println!(
+        "tick {} ({:.0}s): {} players, {towers} towers ({} owned), {} forces",
+        replay.position(),
+        replay.position() as f32 * Ticks::PERIOD_SECS,
+        Map::len(&world.player),
+        owned.values().sum::<usize>(),
+        forces / 2
+    );
+
+    let mut owned: Vec<_> = owned.into_iter().collect();
+    owned.sort_by_key(|&(player_id, count)| (Reverse(count), player_id));
+    for (player_id, count) in owned.into_iter().take(top) {
+        println!("  {player_id:?}: {count} towers");
+    }
+}
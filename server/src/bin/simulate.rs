@@ -0,0 +1,113 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::ticks::Ticks;
+use server::options::Options as GameOptions;
+use server::simulation::{BotReport, Simulation};
+use server::strategy::Personality;
+use std::time::Instant;
+use structopt::StructOpt;
+
+/// Pits bots against each other without any clients, as fast as possible, and reports how each
+/// bot did.
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// Bots, as comma separated personalities and counts (e.g. `turtle:4,nuker:4`).
+    #[structopt(default_value = "turtle:4,expansionist:4,naval:4,nuker:4")]
+    bots: String,
+    /// How many ticks to simulate (4 per second).
+    #[structopt(long, default_value = "2400")]
+    ticks: usize,
+    /// Server options, such as `--seed`, `--world-size`, `--balance` and `--bot-difficulty`.
+    #[structopt(flatten)]
+    game: GameOptions,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let personalities = parse_bots(&options.bots);
+    if personalities.is_empty() {
+        eprintln!("need at least one bot (e.g. `simulate turtle:4,nuker:4`)");
+        std::process::exit(1);
+    }
+
+    let mut simulation = Simulation::new(&personalities, &options.game);
+    let start = Instant::now();
+    for _ in 0..options.ticks {
+        simulation.tick();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "simulated {} ticks in {:.1}s ({:.0} ticks per second)",
+        options.ticks,
+        elapsed.as_secs_f32(),
+        options.ticks as f32 / elapsed.as_secs_f32().max(f32::EPSILON)
+    );
+    println!();
+
+    println!(
+        "{:<20} {:<13} {:>9} {:>7} {:>7} {:>5} {:>7}",
+        "bot", "personality", "survived", "deaths", "towers", "peak", "score"
+    );
+    let report = simulation.report();
+    for bot in &report {
+        println!(
+            "{:<20} {:<13} {:>8}s {:>7} {:>7} {:>5} {:>7}",
+            bot.alias.as_str(),
+            bot.personality.to_string(),
+            secs(bot.alive_ticks),
+            bot.deaths,
+            bot.towers,
+            bot.peak_towers,
+            bot.score
+        );
+    }
+    println!();
+
+    println!(
+        "{:<13} {:>4} {:>13} {:>11} {:>10}",
+        "personality", "bots", "mean survived", "mean peak", "mean score"
+    );
+    for personality in Personality::ALL {
+        let bots: Vec<_> = report
+            .iter()
+            .filter(|bot| bot.personality == personality)
+            .collect();
+        if bots.is_empty() {
+            continue;
+        }
+        let mean = |f: &dyn Fn(&BotReport) -> usize| {
+            bots.iter().map(|&bot| f(bot)).sum::<usize>() / bots.len()
+        };
+        println!(
+            "{:<13} {:>4} {:>12}s {:>11} {:>10}",
+            personality.to_string(),
+            bots.len(),
+            secs(mean(&|bot| bot.alive_ticks)),
+            mean(&|bot| bot.peak_towers),
+            mean(&|bot| bot.score as usize)
+        );
+    }
+}
+
+/// Parses bots such as `turtle:4,nuker:4` into one personality per bot.
+fn parse_bots(s: &str) -> Vec<Personality> {
+    let mut personalities = Vec::new();
+    for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (personality, count) = part.split_once(':').unwrap_or((part, "1"));
+        let personality: Personality = personality
+            .trim()
+            .parse()
+            .unwrap_or_else(|e| panic!("{e}: {personality:?}"));
+        let count: usize = count
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid count {count:?}"));
+        personalities.extend(std::iter::repeat(personality).take(count));
+    }
+    personalities
+}
+
+fn secs(ticks: usize) -> u32 {
+    (ticks as f32 * Ticks::PERIOD_SECS) as u32
+}
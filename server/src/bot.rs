@@ -2,29 +2,39 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::service::TowerService;
-use common::field::Field;
+use crate::strategy::{Difficulty, Personality};
+use common::alerts::AlertFlag;
 use common::force::Path;
+use common::pathfinder::PathCache;
 use common::protocol::Command;
+use common::research::Research;
+use common::ruler::RulerAbility;
+use common::supply_line::SupplyLine;
 use common::ticks::Ticks;
-use common::tower::TowerType;
+use common::tower::{TowerId, TowerType};
 use common::unit::Unit;
 use common::world::World;
 use core_protocol::id::PlayerId;
 use core_protocol::name::PlayerAlias;
 use game_server::game_service::{Bot, BotAction, GameArenaService};
 use game_server::player::{PlayerRepo, PlayerTuple};
-use rand::prelude::{IteratorRandom, ThreadRng};
-use rand::{thread_rng, Rng};
+use rand::prelude::{IteratorRandom, SliceRandom};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::cmp::Ordering;
 use std::sync::Arc;
 
 pub struct TowerBot {
+    /// Decides what the bot prioritizes, chosen when spawning.
+    personality: Personality,
     /// Bot will try to accumulate this many towers.
     territorial_ambition: u8,
     /// Time until quit.
     before_quit: Ticks,
     /// War against player, and time remaining.
     war: Option<War>,
+    /// Paths found with the personality's [`PathMode`](common::pathfinder::PathMode).
+    paths: PathCache,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -34,7 +44,7 @@ struct War {
 }
 
 impl TowerBot {
-    fn random_before_quit(rng: &mut ThreadRng) -> Ticks {
+    fn random_before_quit(rng: &mut impl Rng) -> Ticks {
         Ticks::from_whole_secs(if false {
             rng.gen_range(0..=5)
         } else if cfg!(debug_assertions) && rng.gen_bool(0.1) {
@@ -43,21 +53,60 @@ impl TowerBot {
             rng.gen_range(1800..=5400)
         })
     }
+
+    /// Returns true if every neighbor of `tower_id` is owned by `player_id`.
+    fn is_interior(world: &World, tower_id: TowerId, player_id: PlayerId) -> bool {
+        tower_id.neighbors().all(|neighbor_id| {
+            world
+                .chunk
+                .get(neighbor_id)
+                .map_or(true, |neighbor| neighbor.player_id == Some(player_id))
+        })
+    }
+
+    /// Finds a player near `tower_id` who an allied bot is attacking. Unlike declaring war, this
+    /// includes bots, since the allied bot already picked the fight.
+    fn ally_war(world: &World, tower_id: TowerId, player_id: PlayerId) -> Option<PlayerId> {
+        world
+            .chunk
+            .iter_towers_square(tower_id, 5)
+            .find_map(|(_, tower)| {
+                let enemy_id = tower.player_id.filter(|&enemy_id| {
+                    enemy_id != player_id && !world.have_alliance(player_id, enemy_id)
+                })?;
+                tower
+                    .inbound_forces
+                    .iter()
+                    .any(|force| {
+                        force.player_id.map_or(false, |p| {
+                            p != player_id && p.is_bot() && world.have_alliance(player_id, p)
+                        })
+                    })
+                    .then_some(enemy_id)
+            })
+    }
 }
 
 impl Default for TowerBot {
+    /// Bots start dead, and are randomized when spawning.
     fn default() -> Self {
-        let mut rng = thread_rng();
         Self {
-            territorial_ambition: rng.gen_range(8..=12),
-            before_quit: Self::random_before_quit(&mut rng),
+            personality: Personality::ALL[0],
+            territorial_ambition: 0,
+            before_quit: Ticks::ZERO,
             war: None,
+            paths: Default::default(),
         }
     }
 }
 
 pub struct Input<'a> {
     world: &'a World,
+    difficulty: Difficulty,
+    /// Personalities to choose from when spawning, or any if empty.
+    personalities: &'a [Personality],
+    /// Seeded from the world's RNG, so that bots are reproducible.
+    rng: StdRng,
 }
 
 impl Bot<TowerService> for TowerBot {
@@ -71,12 +120,14 @@ impl Bot<TowerService> for TowerBot {
         player_tuple: &'a Arc<PlayerTuple<TowerService>>,
         _players: &'a PlayerRepo<TowerService>,
     ) -> Self::Input<'a> {
-        service
-            .regulator
-            .active(player_tuple.borrow_player().player_id)
-            .then_some(Input {
-                world: &service.world,
-            })
+        let player_id = player_tuple.borrow_player().player_id;
+        service.regulator.active(player_id).then(|| Input {
+            world: &service.world,
+            difficulty: service.bot_difficulty,
+            personalities: &service.bot_personalities,
+            // Bots update in parallel, so each needs its own RNG.
+            rng: StdRng::seed_from_u64(service.bot_seed ^ player_id.0.get() as u64),
+        })
     }
 
     fn update<'a>(
@@ -93,22 +144,44 @@ impl Bot<TowerService> for TowerBot {
             None => return BotAction::Quit,
         };
 
-        let mut rng = thread_rng();
+        let mut rng = input.rng;
 
         if !player.alive {
             self.war = None;
             self.before_quit = Self::random_before_quit(&mut rng);
+            self.personality = Personality::assigned(input.personalities, player_id)
+                .unwrap_or_else(|| *Personality::ALL.choose(&mut rng).unwrap());
+            self.territorial_ambition =
+                rng.gen_range(self.personality.strategy().territorial_ambition());
+            self.paths = PathCache::new(self.personality.strategy().path_mode());
             return BotAction::Some(Command::Spawn);
         }
+        self.paths.tick();
 
         // Don't crash if ruler is on the run and enemy is hot on it's tail.
         if player.towers.is_empty() {
             return BotAction::None;
         }
 
+        let strategy = self.personality.strategy();
         let world_player = input.world.player(player_id);
 
-        let random_tower_id = *player.towers.iter().choose(&mut rng).unwrap();
+        if input.difficulty.uses_abilities() {
+            // Surge shields when the ruler is attacked.
+            if player.alerts.flags().contains(AlertFlag::RulerUnderAttack)
+                && world_player.can_use_ability(RulerAbility::ShieldSurge)
+            {
+                return BotAction::Some(Command::UseRulerAbility {
+                    ability: RulerAbility::ShieldSurge,
+                    target: None,
+                });
+            }
+
+            // Spend research points as soon as possible.
+            if let Some(research) = Research::iter().find(|&r| world_player.can_research(r)) {
+                return BotAction::Some(Command::Research(research));
+            }
+        }
 
         self.before_quit = if let Some(before_quit) = self.before_quit.checked_sub(Ticks::ONE) {
             before_quit
@@ -133,20 +206,43 @@ impl Bot<TowerService> for TowerBot {
                 })
         });
 
+        if !rng.gen_bool(input.difficulty.act_chance()) {
+            return BotAction::None;
+        }
+
+        // Act with the tower that has the most units, of those considered.
+        let random_tower_id = player
+            .towers
+            .iter()
+            .copied()
+            .choose_multiple(&mut rng, input.difficulty.towers_considered())
+            .into_iter()
+            .max_by_key(|&tower_id| {
+                input
+                    .world
+                    .chunk
+                    .get(tower_id)
+                    .map_or(0, |tower| tower.force_units().len())
+            })
+            .unwrap();
+
         if let Some(random_tower) = input.world.chunk.get(random_tower_id) {
             // Check if can upgrade. Require more shield if in war.
             let min_shield = random_tower.tower_type.raw_unit_capacity(Unit::Shield)
-                / (1 + self.war.is_none() as usize);
+                * strategy.upgrade_shield_percent(self.war.is_some())
+                / 100;
 
             if random_tower.units.available(Unit::Shield) >= min_shield {
-                if let Some(tower_type) = random_tower
+                let upgrades: Vec<TowerType> = random_tower
                     .tower_type
                     .upgrades()
                     .filter(|u| {
                         u.has_prerequisites(&player.tower_counts)
                             && !matches!(u, TowerType::Helipad)
                     })
-                    .choose(&mut rng)
+                    .collect();
+                if let Ok(&tower_type) =
+                    upgrades.choose_weighted(&mut rng, |&u| strategy.upgrade_weight(u))
                 {
                     return BotAction::Some(Command::Upgrade {
                         tower_id: random_tower_id,
@@ -155,8 +251,18 @@ impl Bot<TowerService> for TowerBot {
                 }
             }
 
+            // Join the wars of allied bots, so adversaries are fought together.
+            if self.war.is_none() && input.difficulty.coordinates() {
+                if let Some(against) = Self::ally_war(input.world, random_tower_id, player_id) {
+                    self.war = Some(War {
+                        against,
+                        remaining: Ticks::from_whole_secs(180),
+                    });
+                }
+            }
+
             // Recompute war.
-            if self.war.is_none() && rng.gen_bool(0.005) {
+            if self.war.is_none() && rng.gen_bool(strategy.war_chance()) {
                 #[derive(PartialEq)]
                 struct WarTarget {
                     player_id: PlayerId,
@@ -214,13 +320,14 @@ impl Bot<TowerService> for TowerBot {
                         return BotAction::Some(Command::Alliance {
                             with: best_target.player_id,
                             break_alliance: true,
+                            minutes: None,
                         });
                     }
                 }
             }
 
             // Contemplate entering an alliance.
-            if rng.gen_bool(0.0025) {
+            if rng.gen_bool(strategy.alliance_chance()) {
                 let with = input
                     .world
                     .chunk
@@ -242,6 +349,58 @@ impl Bot<TowerService> for TowerBot {
                     return BotAction::Some(Command::Alliance {
                         with,
                         break_alliance: false,
+                        minutes: None,
+                    });
+                }
+            }
+
+            // Keep the frontier supplied from the interior.
+            if let Some(below_percent) = strategy.supply_percent().filter(|_| {
+                random_tower.generates_mobile_units() && !random_tower.units.contains(Unit::Ruler)
+            }) {
+                let interior = Self::is_interior(input.world, random_tower_id, player_id);
+                if random_tower.supply_lines.is_empty() {
+                    let frontier = interior
+                        .then(|| {
+                            input
+                                .world
+                                .chunk
+                                .iter_towers_square(random_tower_id, 5)
+                                .filter(|&(tower_id, tower)| {
+                                    tower.player_id == Some(player_id)
+                                        && !Self::is_interior(input.world, tower_id, player_id)
+                                })
+                                .choose(&mut rng)
+                        })
+                        .flatten();
+                    let path = frontier.and_then(|(frontier_id, _)| {
+                        self.paths.find_best_path(
+                            input.world,
+                            random_tower_id,
+                            frontier_id,
+                            random_tower.tower_type.ranged_distance(),
+                            player_id,
+                            |_| true,
+                        )
+                    });
+                    if let Some(path) = path {
+                        return BotAction::Some(Command::SetSupplyLines {
+                            tower_id: random_tower_id,
+                            supply_lines: vec![SupplyLine {
+                                below_percent: Some(below_percent),
+                                ..SupplyLine::new(Path::new(path))
+                            }],
+                        });
+                    }
+                } else if !interior
+                    || random_tower.supply_lines.iter().any(|supply_line| {
+                        Self::is_interior(input.world, supply_line.path.destination(), player_id)
+                    })
+                {
+                    // This tower is the frontier now, or it supplies one that no longer is.
+                    return BotAction::Some(Command::SetSupplyLine {
+                        tower_id: random_tower_id,
+                        path: None,
                     });
                 }
             }
@@ -256,14 +415,12 @@ impl Bot<TowerService> for TowerBot {
                 let formidable = {
                     let mut total_damage = 0u32;
                     for unit_damage in strength.iter().map(|(unit, count)| {
-                        Unit::damage_to_finite(
-                            unit.damage(unit.field(false, true, false), Field::Surface),
-                        )
-                        .saturating_mul(count as u32)
+                        Unit::damage_to_finite(unit.force_ground_damage())
+                            .saturating_mul(count as u32)
                     }) {
                         total_damage = total_damage.saturating_add(unit_damage);
                     }
-                    total_damage >= 5
+                    total_damage >= input.difficulty.formidable_damage()
                 };
 
                 let destination = input
@@ -282,46 +439,43 @@ impl Bot<TowerService> for TowerBot {
                                                 .tower_type
                                                 .max_ranged_damage(),
                                         ) as usize)
-                        } else if sending_ruler
-                            || candidate_destination_tower
-                                .player_id
-                                .map(|p|
-                                    input
-                                        .world
-                                        .player(p)
-                                        .allies
-                                        .contains(&player_id)
-                                    && world_player.allies.contains(&p)
-                                ).unwrap_or(false) {
-                            // Cannot send ruler to an unowned tower or forces to an allied tower.
+                        } else if sending_ruler {
+                            // Cannot send ruler to an unowned tower.
                             false
+                        } else if candidate_destination_tower
+                            .player_id
+                            .map(|p| input.world.have_alliance(player_id, p))
+                            .unwrap_or(false)
+                        {
+                            // Reinforce allies that are under attack, unless busy with a war.
+                            self.war.is_none()
+                                && candidate_destination_tower.inbound_forces.iter().any(|f| {
+                                    f.player_id.map_or(true, |p| {
+                                        !input.world.have_alliance(player_id, p) && p != player_id
+                                    })
+                                })
                         } else if let Some(War { against, .. }) = self.war {
                             // Focus on the adversary (only).
                             formidable && candidate_destination_tower.player_id == Some(against)
                         } else {
-                            candidate_destination_tower
+                            let enemy = candidate_destination_tower
                                 .player_id
                                 .and_then(|player_id| players.borrow_player(player_id))
-                                .map_or(
-                                    true, // player.towers.len() < self.territorial_ambition as usize,
-                                    |enemy| {
-                                        // They're big; get em!
-                                        enemy.towers.len() / 4 > self.territorial_ambition as usize
-                                            // They're big; get em!
-                                            || enemy.score > 1000
-                                            // Don't do too much damage to smol's.
-                                            || !formidable
-                                            // Recently changed hands?
-                                            || candidate_destination_tower.units.available(Unit::Shield) < 5
-                                    },
-                                )
+                                .map(|enemy| (enemy.towers.len(), enemy.score));
+                            strategy.raid(
+                                candidate_destination_tower,
+                                enemy,
+                                self.territorial_ambition,
+                                formidable,
+                            )
                         }
                     })
                     .choose(&mut rng);
 
                 if let Some((destination, _)) = destination {
                     let max_edge_distance = strength.max_edge_distance();
-                    let path = input.world.find_best_path(
+                    let path = self.paths.find_best_path(
+                        input.world,
                         random_tower_id,
                         destination,
                         max_edge_distance,
@@ -330,14 +484,7 @@ impl Bot<TowerService> for TowerBot {
                     );
 
                     if let Some(path) = path {
-                        return BotAction::Some(if true || rng.gen() {
-                            Command::deploy_force_from_path(path)
-                        } else {
-                            Command::SetSupplyLine {
-                                tower_id: path[0],
-                                path: Some(Path::new(path)),
-                            }
-                        });
+                        return BotAction::Some(Command::deploy_force_from_path(path));
                     }
                 }
             }
@@ -0,0 +1,425 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::service::TowerService;
+use crate::strategy::Difficulty;
+use common::chunk::ChunkInput;
+use common::death_reason::DeathReason;
+use common::force::{Force, Path};
+use common::tower::TowerId;
+use common::unit::Unit;
+use common::units::Units;
+use common::world::World;
+use core_protocol::id::PlayerId;
+use game_server::player::PlayerRepo;
+
+/// Who may use a chat command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Permission {
+    Moderator,
+    Admin,
+}
+
+impl TowerService {
+    /// Most bots `/spawnbots` can request, before the engine's own maximum applies.
+    const MAX_SPAWN_BOTS: usize = 1000;
+    /// Radius of `/bombard` if none is given, a giant circle about the size of Debased.
+    const BOMBARD_RADIUS: u16 = 500;
+    /// Radius of `/bombard all`, the whole world.
+    const BOMBARD_ALL_RADIUS: u16 = 10000;
+    const BOMBARD_BOMBERS: usize = 5;
+
+    /// Executes a chat command without its leading `/`, returning the reply or [`None`] if it
+    /// isn't recognized.
+    pub(crate) fn execute_chat_command(
+        &mut self,
+        command: &str,
+        player_id: PlayerId,
+        players: &PlayerRepo<Self>,
+    ) -> Option<String> {
+        let mut words = command.split_ascii_whitespace();
+        let first = words.next()?;
+        let args: Vec<&str> = words.collect();
+
+        let result = match first {
+            "ally" => self.ally_command(player_id, &args, false, players),
+            "unally" => self.ally_command(player_id, &args, true, players),
+            "stats" => self.stats_command(player_id, players),
+            "spawnbots" => self.spawn_bots_command(player_id, &args, players),
+            "botdifficulty" => self.bot_difficulty_command(player_id, &args, players),
+            "kill" => self.kill_command(player_id, &args, players),
+            "grant" => self.grant_command(player_id, &args, players),
+            "tp" => self.tp_command(player_id, &args, players),
+            "bombard" => self.bombard_command(player_id, &args, players),
+            _ => return None,
+        };
+        Some(result.unwrap_or_else(String::from))
+    }
+
+    /// `/ally <name>` requests or accepts an alliance, and `/unally <name>` breaks one.
+    fn ally_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        break_alliance: bool,
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        let (with, alias) = Self::find_player(args, players)?;
+        let accepting = !break_alliance
+            && self.world.player.contains_key(&with)
+            && self.world.player(with).allies.contains(&player_id);
+        self.alliance(player_id, with, break_alliance, None, players)?;
+
+        Ok(if break_alliance {
+            format!("no longer allied with {alias}")
+        } else if accepting {
+            format!("allied with {alias}")
+        } else {
+            format!("requested alliance with {alias}")
+        })
+    }
+
+    /// `/stats` summarizes the player's empire.
+    fn stats_command(
+        &self,
+        player_id: PlayerId,
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        let player = players
+            .borrow_player(player_id)
+            .ok_or("nonexistent player")?;
+        if !player.data.alive {
+            return Err("must be alive");
+        }
+
+        let allies = if self.world.player.contains_key(&player_id) {
+            self.world
+                .player(player_id)
+                .allies
+                .iter()
+                .filter(|&ally_id| {
+                    self.world.player.contains_key(ally_id)
+                        && self.world.player(*ally_id).allies.contains(&player_id)
+                })
+                .count()
+        } else {
+            0
+        };
+        let ruler = self.ruler_tower(&player.data).map_or_else(
+            || String::from("no ruler"),
+            |tower_id| format!("ruler at {}, {}", tower_id.0.x, tower_id.0.y),
+        );
+
+        Ok(format!(
+            "{} towers, {} score, {} allies, alive for {}m, {}",
+            player.data.towers.len(),
+            player.score,
+            allies,
+            player.data.lifetime.to_whole_secs() / 60,
+            ruler
+        ))
+    }
+
+    /// `/spawnbots <n>` changes the minimum number of bots.
+    fn spawn_bots_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        Self::require(player_id, Permission::Moderator, players)?;
+        let &[count] = args else {
+            return Err("usage: /spawnbots <count>");
+        };
+        let count = count
+            .parse::<usize>()
+            .map_err(|_| "failed to parse count")?
+            .min(Self::MAX_SPAWN_BOTS);
+        self.requested_min_bots = Some(count);
+        Ok(format!("minimum bots set to {count}, up to the maximum"))
+    }
+
+    /// `/botdifficulty [easy|normal|hard]` shows or changes how well bots play.
+    fn bot_difficulty_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        let difficulty = match args {
+            [] => return Ok(format!("bot difficulty is {}", self.bot_difficulty)),
+            [difficulty] => difficulty.parse::<Difficulty>()?,
+            _ => return Err("usage: /botdifficulty [easy|normal|hard]"),
+        };
+        Self::require(player_id, Permission::Moderator, players)?;
+        self.bot_difficulty = difficulty;
+        Ok(format!("bot difficulty set to {difficulty}"))
+    }
+
+    /// `/kill <name>` kills a player.
+    fn kill_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        Self::require(player_id, Permission::Moderator, players)?;
+        let (victim, alias) = Self::find_player(args, players)?;
+        let mut player = players
+            .borrow_player_mut(victim)
+            .filter(|p| p.data.alive)
+            .ok_or("player isn't alive")?;
+        player.data.death_reason = Some(DeathReason::KilledByModerator);
+        drop(player);
+        // Killed in post_update, which can make `ChunkMaintenance`s.
+        self.maybe_dead.insert(victim);
+        Ok(format!("killed {alias}"))
+    }
+
+    /// `/grant <unit> <count>` sends units to the player's ruler.
+    fn grant_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        Self::require(player_id, Permission::Moderator, players)?;
+        let &[unit, count] = args else {
+            return Err("usage: /grant <unit> <count>");
+        };
+        let unit = Unit::iter()
+            .find(|u| u.to_string().eq_ignore_ascii_case(unit))
+            .ok_or("unknown unit")?;
+        if !unit.is_mobile(None) || unit == Unit::Ruler {
+            return Err("can't grant unit");
+        }
+        let count = count
+            .parse::<usize>()
+            .map_err(|_| "failed to parse count")?;
+
+        let player = players
+            .borrow_player(player_id)
+            .ok_or("nonexistent player")?;
+        let destination = self.ruler_tower(&player.data).ok_or("no ruler")?;
+        drop(player);
+        let source = destination
+            .neighbors()
+            .find(|&neighbor_id| self.world.chunk.get(neighbor_id).is_some())
+            .ok_or("nowhere to send from")?;
+
+        let mut units = Units::default();
+        let granted = units.add(unit, count);
+        if granted == 0 {
+            return Err("no units granted");
+        }
+        let force = Force::new(player_id, units, Path::new(vec![source, destination]));
+        let (chunk_id, tower_id) = destination.split();
+        self.world.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::AddInboundForce { tower_id, force },
+            |_| unreachable!(),
+        );
+        Ok(format!("granted {granted} {unit}"))
+    }
+
+    /// `/tp <name>` focuses a player's ruler, and `/tp <x> <y>` focuses a tower. The camera stays
+    /// within the usual bounds.
+    fn tp_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        Self::require(player_id, Permission::Moderator, players)?;
+        let tower_id = if let &[x, y] = args
+            && let (Ok(x), Ok(y)) = (x.parse::<u16>(), y.parse::<u16>())
+        {
+            let tower_id = TowerId::new(x, y);
+            if self.world.chunk.get(tower_id).is_none() {
+                return Err("no such tower");
+            }
+            tower_id
+        } else {
+            let (followed, _) = Self::find_player(args, players)?;
+            let followed = players
+                .borrow_player(followed)
+                .ok_or("nonexistent player")?;
+            self.ruler_tower(&followed.data)
+                .ok_or("player has no ruler")?
+        };
+
+        let mut player = players
+            .borrow_player_mut(player_id)
+            .ok_or("nonexistent player")?;
+        player.focus = Some(tower_id);
+        Ok(format!("focused {}, {}", tower_id.0.x, tower_id.0.y))
+    }
+
+    /// `/bombard [radius|all]` sends bombers from the center of the world to every tower nearby
+    /// that the player doesn't own.
+    fn bombard_command(
+        &mut self,
+        player_id: PlayerId,
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<String, &'static str> {
+        Self::require(player_id, Permission::Admin, players)?;
+        let radius = match args {
+            [] => Self::BOMBARD_RADIUS,
+            ["all"] => Self::BOMBARD_ALL_RADIUS,
+            [radius] => radius.parse().map_err(|_| "failed to parse radius")?,
+            _ => return Err("usage: /bombard [radius|all]"),
+        };
+        if !players
+            .borrow_player(player_id)
+            .is_some_and(|p| p.data.alive)
+        {
+            return Err("must be alive");
+        }
+
+        let mut events = Vec::new();
+        for (tower_id, tower) in self.world.chunk.iter_towers_circle(World::center(), radius) {
+            if tower.player_id == Some(player_id) {
+                continue;
+            }
+            let mut units = Units::default();
+            units.add(Unit::Bomber, Self::BOMBARD_BOMBERS);
+
+            let mut src = World::center();
+            if tower_id == src {
+                src = src.connectivity_id().unwrap(); // We can't send from a tower to itself.
+            }
+
+            let force = Force::new(player_id, units, Path::new(vec![src, tower_id]));
+            let (chunk_id, tower_id) = tower_id.split();
+            events.push((chunk_id, ChunkInput::AddInboundForce { tower_id, force }));
+        }
+        let count = events.len();
+        for (chunk_id, event) in events {
+            self.world
+                .dispatch_chunk_input(chunk_id, event, |_| unreachable!());
+        }
+        Ok(format!("bombarded {count} towers"))
+    }
+
+    fn require(
+        player_id: PlayerId,
+        permission: Permission,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let player = players
+            .borrow_player(player_id)
+            .ok_or("nonexistent player")?;
+        let client = player.client().ok_or("not a real player")?;
+        let allowed = match permission {
+            Permission::Moderator => client.moderator || client.admin,
+            Permission::Admin => client.admin,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err("permission denied")
+        }
+    }
+
+    /// Finds a player by alias, ignoring case. Fails if more than one player has the alias.
+    fn find_player(
+        args: &[&str],
+        players: &PlayerRepo<Self>,
+    ) -> Result<(PlayerId, String), &'static str> {
+        if args.is_empty() {
+            return Err("missing name");
+        }
+        // Aliases may contain spaces.
+        let name = args.join(" ");
+        let mut matches = players
+            .iter_borrow()
+            .filter(|player| player.alias().as_str().eq_ignore_ascii_case(&name))
+            .map(|player| (player.player_id, player.alias().as_str().to_owned()));
+        let found = matches.next().ok_or("no player with that name")?;
+        if matches.next().is_some() {
+            return Err("more than one player with that name");
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::service::TowerService;
+    use crate::testing::{arena, spawn, tick};
+    use common::death_reason::DeathReason;
+    use core_protocol::id::PlayerId;
+    use game_server::headless::Headless;
+
+    fn chat(headless: &mut Headless<TowerService>, player_id: PlayerId, command: &str) -> String {
+        headless.chat_command(player_id, command).unwrap()
+    }
+
+    fn make_moderator(headless: &mut Headless<TowerService>, player_id: PlayerId) {
+        let mut player = headless.players().borrow_player_mut(player_id).unwrap();
+        player.client_mut().unwrap().moderator = true;
+    }
+
+    #[test]
+    fn permissions() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+
+        assert!(chat(&mut headless, alice, "stats").contains("towers"));
+        assert_eq!(chat(&mut headless, alice, "kill bob"), "permission denied");
+        assert_eq!(
+            chat(&mut headless, alice, "spawnbots 5"),
+            "permission denied"
+        );
+
+        make_moderator(&mut headless, alice);
+        assert_eq!(chat(&mut headless, alice, "bombard"), "permission denied");
+        assert_eq!(chat(&mut headless, alice, "kill bob"), "killed bob");
+        tick(&mut headless, 1);
+
+        let bob_data = headless.players().borrow_player(bob).unwrap();
+        assert!(!bob_data.alive);
+        assert_eq!(bob_data.death_reason, Some(DeathReason::KilledByModerator));
+    }
+
+    #[test]
+    fn arguments() {
+        let mut headless = arena(&Options::default());
+        let alice = spawn(&mut headless, "alice");
+        make_moderator(&mut headless, alice);
+
+        for (command, reply) in [
+            ("spawnbots", "usage: /spawnbots <count>"),
+            ("spawnbots many", "failed to parse count"),
+            ("spawnbots 5", "minimum bots set to 5, up to the maximum"),
+            ("grant soldier", "usage: /grant <unit> <count>"),
+            ("grant dragon 5", "unknown unit"),
+            ("grant ruler 1", "can't grant unit"),
+            ("grant soldier lots", "failed to parse count"),
+            ("botdifficulty hard", "bot difficulty set to hard"),
+            (
+                "botdifficulty hard now",
+                "usage: /botdifficulty [easy|normal|hard]",
+            ),
+            ("kill", "missing name"),
+            ("kill carol", "no player with that name"),
+            ("tp", "missing name"),
+        ] {
+            assert_eq!(chat(&mut headless, alice, command), reply, "/{command}");
+        }
+        assert_eq!(headless.chat_command(alice, "unknown"), None);
+    }
+
+    #[test]
+    fn ambiguous_name() {
+        let mut headless = arena(&Options::default());
+        let [alice, _, _] = ["alice", "bob", "BOB"].map(|alias| spawn(&mut headless, alias));
+
+        assert_eq!(
+            chat(&mut headless, alice, "ally bob"),
+            "more than one player with that name"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+#![feature(entry_insert)]
+#![feature(int_roundings)]
+#![feature(let_chains)]
+#![feature(type_alias_impl_trait)]
+
+pub mod agent;
+mod bot;
+mod chat;
+pub mod options;
+mod regulator;
+mod round;
+pub mod service;
+pub mod simulation;
+mod snapshot;
+pub mod strategy;
+#[cfg(test)]
+mod testing;
+mod world;
+mod zombies;
@@ -0,0 +1,119 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::strategy::{Difficulty, Personality};
+use common::balance::{Balance, BalanceFile};
+use common::generation::{Generation, GenerationFile};
+use core_protocol::prelude::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Kiomet specific server options, to be specified as arguments.
+#[derive(Clone, Debug, Default, StructOpt)]
+pub struct Options {
+    /// Load tower and unit balance overrides from this TOML file.
+    #[structopt(long)]
+    pub balance: Option<PathBuf>,
+    /// Load world generation overrides (seed, noise, water ratio, tower weights) from this TOML
+    /// file.
+    #[structopt(long)]
+    pub generation: Option<PathBuf>,
+    /// World generation seed, overriding the one in the generation file.
+    #[structopt(long)]
+    pub seed: Option<u32>,
+    /// Seed of the randomness of spawning, zombies and bots, which defaults to the world seed.
+    #[structopt(long)]
+    pub rng_seed: Option<u64>,
+    /// Width and height of the world in towers (a power of two from 64 to 1024), overriding the
+    /// one in the generation file.
+    #[structopt(long)]
+    pub world_size: Option<u16>,
+    /// Periodically save the world and players to this file.
+    #[structopt(long)]
+    pub snapshot: Option<PathBuf>,
+    /// Seconds between snapshots (at most 3600).
+    #[structopt(long, default_value = "60")]
+    pub snapshot_period: u16,
+    /// Resume from the snapshot file (if it exists) instead of generating a new world.
+    #[structopt(long, requires = "snapshot")]
+    pub restore: bool,
+    /// Record every tick to this replay file, which can be played back with the `replay` binary.
+    #[structopt(long)]
+    pub record: Option<PathBuf>,
+    /// Play in rounds of this many minutes, after which the world is reset. A round ends early if
+    /// a player and their allies reach the tower or center goal.
+    #[structopt(long)]
+    pub round_minutes: Option<u16>,
+    /// Percent of towers that wins a round (0 to disable).
+    #[structopt(long, default_value = "40")]
+    pub round_tower_percent: u8,
+    /// Minutes of holding the center of the world that wins a round (0 to disable).
+    #[structopt(long, default_value = "5")]
+    pub round_center_minutes: u16,
+    /// Send a wave of zombies against large empires every this many minutes. Each wave is
+    /// stronger than the last.
+    #[structopt(long)]
+    pub zombie_wave_minutes: Option<u16>,
+    /// Only attack players with at least this many towers.
+    #[structopt(long, default_value = "50")]
+    pub zombie_min_towers: u16,
+    /// Skip waves while at least this many real players are alive (0 to never skip).
+    #[structopt(long, default_value = "0")]
+    pub zombie_max_players: u16,
+    /// How well bots play (easy, normal or hard).
+    #[structopt(long, default_value = "normal")]
+    pub bot_difficulty: Difficulty,
+    /// Comma separated personalities that bots are evenly split between (turtle, expansionist,
+    /// naval or nuker), which may be repeated to give them a larger share. Bots have random
+    /// personalities by default.
+    #[structopt(long, use_delimiter = true)]
+    pub bot_personalities: Vec<Personality>,
+    /// Whether these are the options of a private realm, which uses the balance and generation the
+    /// main arena set when the process started, instead of setting them again.
+    #[structopt(skip)]
+    pub realm: bool,
+}
+
+impl Options {
+    /// Loads the [`Balance`] overrides, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the balance file can't be read or is invalid.
+    pub fn balance(&self) -> Balance {
+        self.balance
+            .as_deref()
+            .map(|path| {
+                let file: BalanceFile = read("balance", path);
+                Balance::try_from(file)
+                    .unwrap_or_else(|e| panic!("invalid balance {}: {e}", path.display()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Loads the [`Generation`] overrides, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the generation file can't be read or the generation is invalid.
+    pub fn generation(&self) -> Generation {
+        let mut file = self
+            .generation
+            .as_deref()
+            .map(|path| read("generation", path))
+            .unwrap_or_else(|| GenerationFile {
+                version: Generation::VERSION,
+                ..Default::default()
+            });
+        file.seed = self.seed.or(file.seed);
+        file.size = self.world_size.or(file.size);
+        Generation::try_from(file).unwrap_or_else(|e| panic!("invalid generation: {e}"))
+    }
+}
+
+fn read<F: DeserializeOwned>(kind: &str, path: &Path) -> F {
+    let contents = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("could not read {kind} {}: {e}", path.display()));
+    toml::from_str(&contents)
+        .unwrap_or_else(|e| panic!("could not parse {kind} {}: {e}", path.display()))
+}
@@ -0,0 +1,276 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::options::Options;
+use crate::service::TowerService;
+use common::chunk::ChunkMaintenance;
+use common::death_reason::DeathReason;
+use common::round::Round;
+use common::world::World;
+use core_protocol::id::PlayerId;
+use fxhash::FxHashMap;
+use game_server::context::Context;
+
+/// State of the current round, if the server is playing in rounds.
+#[derive(Debug)]
+pub struct Rounds {
+    /// Length of every round.
+    seconds: u16,
+    /// Zero means towers can't win.
+    tower_percent_goal: u8,
+    /// Zero means the center can't win.
+    center_seconds_goal: u16,
+    seconds_remaining: u16,
+    /// Who is holding the center (along with their allies), and for how many seconds.
+    center: Option<(PlayerId, u16)>,
+}
+
+/// How a round ended.
+enum Victory {
+    Towers(u8),
+    Center,
+    Timer,
+}
+
+impl Rounds {
+    /// Returns [`None`] if rounds are disabled.
+    pub fn new(options: &Options) -> Option<Self> {
+        let seconds = options.round_minutes.filter(|&m| m > 0)?.saturating_mul(60);
+        Some(Self {
+            seconds,
+            tower_percent_goal: options.round_tower_percent.min(100),
+            center_seconds_goal: options.round_center_minutes.saturating_mul(60),
+            seconds_remaining: seconds,
+            center: None,
+        })
+    }
+}
+
+impl TowerService {
+    /// Advances the round by a second, ending it if it was won or ran out of time. Ending a round
+    /// makes `ChunkMaintenance`s, which have to run before tick_before_inputs.
+    pub(crate) fn update_round(&mut self, context: &mut Context<Self>) {
+        let Some(rounds) = &mut self.rounds else {
+            return;
+        };
+        rounds.seconds_remaining = rounds.seconds_remaining.saturating_sub(1);
+
+        let mut towers = FxHashMap::<PlayerId, u32>::default();
+        let mut total_towers = 0u32;
+        for (_, tower) in self.world.chunk.iter_towers() {
+            total_towers += 1;
+            if let Some(player_id) = tower.player_id {
+                *towers.entry(player_id).or_default() += 1;
+            }
+        }
+        let mut center = FxHashMap::<PlayerId, u32>::default();
+        let mut total_center = 0u32;
+        for (_, tower) in self
+            .world
+            .chunk
+            .iter_towers_circle(World::center(), Round::CENTER_RADIUS)
+        {
+            total_center += 1;
+            if let Some(player_id) = tower.player_id {
+                *center.entry(player_id).or_default() += 1;
+            }
+        }
+
+        // Players share progress with their allies (and teammates).
+        let world = &self.world;
+        let allied = |a: PlayerId, b: PlayerId| {
+            a == b
+                || (world.player.contains_key(&a)
+                    && world.player.contains_key(&b)
+                    && world.have_alliance(a, b))
+        };
+        let allied_count = |counts: &FxHashMap<PlayerId, u32>, player_id: PlayerId| -> u32 {
+            counts
+                .iter()
+                .filter(|(&other_id, _)| allied(player_id, other_id))
+                .map(|(_, &count)| count)
+                .sum()
+        };
+        let percent = |count: u32| (count * 100 / total_towers.max(1)) as u8;
+
+        // Holding the center requires a majority of its towers.
+        let holder = center
+            .keys()
+            .map(|&player_id| (player_id, allied_count(&center, player_id)))
+            .filter(|&(_, count)| count * 2 > total_center)
+            .max_by_key(|&(player_id, count)| (count, player_id))
+            .map(|(player_id, _)| player_id);
+        rounds.center = match (rounds.center, holder) {
+            (Some((old_id, seconds)), Some(new_id)) if allied(old_id, new_id) => {
+                Some((old_id, seconds.saturating_add(1)))
+            }
+            (_, holder) => holder.map(|player_id| (player_id, 1)),
+        };
+
+        // The largest alliance, by number of towers.
+        let leader = towers
+            .keys()
+            .map(|&player_id| (player_id, allied_count(&towers, player_id)))
+            .max_by_key(|&(player_id, count)| (count, player_id));
+
+        let victory = if let Some((player_id, count)) = leader
+            && rounds.tower_percent_goal > 0
+            && percent(count) >= rounds.tower_percent_goal
+        {
+            Some((Some(player_id), Victory::Towers(percent(count))))
+        } else if let Some((player_id, seconds)) = rounds.center
+            && rounds.center_seconds_goal > 0
+            && seconds >= rounds.center_seconds_goal
+        {
+            Some((Some(player_id), Victory::Center))
+        } else if rounds.seconds_remaining == 0 {
+            Some((leader.map(|(player_id, _)| player_id), Victory::Timer))
+        } else {
+            None
+        };
+
+        for mut player in context.players.iter_borrow_mut() {
+            let player_id = player.player_id;
+            player.data.round = Round {
+                seconds_remaining: rounds.seconds_remaining,
+                tower_percent: percent(allied_count(&towers, player_id)),
+                tower_percent_goal: rounds.tower_percent_goal,
+                center_seconds: rounds
+                    .center
+                    .filter(|&(holder_id, _)| allied(holder_id, player_id))
+                    .map_or(0, |(_, seconds)| seconds),
+                center_seconds_goal: rounds.center_seconds_goal,
+            };
+        }
+
+        let Some((winner, victory)) = victory else {
+            return;
+        };
+        let winners: Vec<PlayerId> = winner
+            .map(|winner| {
+                context
+                    .players
+                    .iter_player_ids()
+                    .filter(|&player_id| allied(winner, player_id))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let announcement = if let Some(winner) = winner
+            && let Some(player) = context.players.borrow_player(winner)
+        {
+            let alias = player.alias();
+            drop(player);
+            let who = if winners.len() > 1 {
+                format!("{alias} and allies")
+            } else {
+                alias.to_string()
+            };
+            match victory {
+                Victory::Towers(percent) => {
+                    format!("{who} won the round by controlling {percent}% of towers!")
+                }
+                Victory::Center => format!("{who} won the round by holding the center!"),
+                Victory::Timer => format!("{who} won the round by controlling the most towers!"),
+            }
+        } else {
+            String::from("The round ended without a winner.")
+        };
+        context.announce(announcement);
+
+        for &player_id in &winners {
+            let score = context.players.borrow_player(player_id).map(|p| p.score);
+            if let Some(score) = score {
+                context.record_score(player_id, score);
+            }
+        }
+
+        rounds.seconds_remaining = rounds.seconds;
+        rounds.center = None;
+        if let Some(zombies) = &mut self.zombies {
+            zombies.reset();
+        }
+        self.reset_world(context);
+    }
+
+    /// Kills every player and destroys every tower, so the world regenerates as players spawn.
+    fn reset_world(&mut self, context: &mut Context<Self>) {
+        let alive: Vec<PlayerId> = context
+            .players
+            .iter_borrow()
+            .filter(|player| player.data.alive)
+            .map(|player| player.player_id)
+            .collect();
+        for player_id in alive {
+            if let Some(mut player) = context.players.borrow_player_mut(player_id) {
+                player.data.death_reason = Some(DeathReason::RoundEnded);
+            }
+            self.kill_player(player_id, &context.players);
+        }
+
+        let chunk_ids: Vec<_> = self.world.chunk.iter_chunks().map(|(id, _)| id).collect();
+        for chunk_id in chunk_ids {
+            self.world
+                .dispatch_chunk_maintenance(chunk_id, ChunkMaintenance::Clear, |_| {
+                    unreachable!("clear made info event")
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::round::Rounds;
+    use crate::testing::{arena, spawn, tick};
+    use common::death_reason::DeathReason;
+    use common::protocol::Command;
+    use common::ticks::Ticks;
+    use game_server::headless::Headless;
+
+    #[test]
+    fn disabled() {
+        assert!(Rounds::new(&Options::default()).is_none());
+        let options = Options {
+            round_minutes: Some(0),
+            ..Default::default()
+        };
+        assert!(Rounds::new(&options).is_none());
+    }
+
+    #[test]
+    fn timer() {
+        let options = Options {
+            round_minutes: Some(1),
+            ..Default::default()
+        };
+        let mut headless = arena(&options);
+        let alice = spawn(&mut headless, "alice");
+        let seconds = |headless: &Headless<_>| {
+            headless
+                .players()
+                .borrow_player(alice)
+                .unwrap()
+                .data
+                .round
+                .seconds_remaining
+        };
+
+        tick(&mut headless, Ticks::from_whole_secs(10).0 as usize);
+        assert!((48..=52).contains(&seconds(&headless)));
+
+        // The round ends, killing everyone and destroying every tower.
+        tick(&mut headless, Ticks::from_whole_secs(55).0 as usize);
+        let player = headless.players().borrow_player(alice).unwrap();
+        assert!(!player.alive);
+        assert_eq!(player.data.death_reason, Some(DeathReason::RoundEnded));
+        drop(player);
+        assert_eq!(headless.service().world.chunk.iter_towers().count(), 0);
+        assert!(seconds(&headless) > 50);
+
+        // The next round can be played.
+        headless.command(alice, Command::Spawn);
+        tick(&mut headless, 10);
+        assert!(headless.players().borrow_player(alice).unwrap().alive);
+    }
+}
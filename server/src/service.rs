@@ -1,36 +1,78 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::agent::AgentUpdate;
 use crate::bot::TowerBot;
+use crate::options::Options;
 use crate::regulator::Regulator;
+use crate::round::Rounds;
+use crate::snapshot::Snapshot;
+use crate::strategy::{Difficulty, Personality};
+use crate::zombies::Zombies;
 use atomic_refcell::AtomicRef;
-use common::alerts::{AlertFlag, Alerts};
+use common::alerts::{AlertFlag, AlertFlags, Alerts};
+use common::alliance::{AllianceRequest, AllianceRequests};
+use common::balance::Balance;
 use common::chunk::{ChunkId, ChunkRectangle};
 use common::death_reason::DeathReason;
+use common::force::Deployment;
+use common::generation::Generation;
 use common::info::{GainedTowerReason, Info, InfoEvent, LostRulerReason};
 use common::player::Player;
-use common::protocol::{Command, Diff, NonActor, Update};
+use common::protocol::{Command, Diff, NonActor, Spectate, Update};
+use common::replay::ReplayRecorder;
+use common::research::Research;
+use common::round::Round;
 use common::singleton::SingletonId;
 use common::ticks::Ticks;
-use common::tower::{TowerArray, TowerId, TowerRectangle};
-use common::unit::Unit;
+use common::tower::{Tower, TowerArray, TowerId, TowerRectangle};
+use common::unit::{Unit, UnitArray};
 use common::world::{Knowledge, Visibility, World, WorldChunks};
 use common_util::actor2::WorldTick;
 use common_util::storage::Map;
-use core_protocol::id::{GameId, PlayerId};
-use fxhash::FxHashSet;
+use core_protocol::id::{GameId, PlayerId, TeamId};
+use fxhash::{FxHashMap, FxHashSet};
 use game_server::context::Context;
-use game_server::game_service::GameArenaService;
+use game_server::game_service::{GameArenaService, RestoredPlayer};
 use game_server::player::{PlayerRepo, PlayerTuple};
-use log::warn;
+use log::{error, info, warn};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use std::cmp::Ordering;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 pub struct TowerService {
-    maybe_dead: FxHashSet<PlayerId>,
+    pub(crate) maybe_dead: FxHashSet<PlayerId>,
+    /// Players whose [`TeamId`] changed, until it can be given to their [`Player`] actor.
+    changed_team: FxHashSet<PlayerId>,
     pub regulator: Regulator,
     pub world: World,
+    /// Where and how often to save [`Snapshot`]s.
+    snapshots: Option<(PathBuf, Ticks)>,
+    /// Saves the last [`Snapshot`], so that only one is written at a time.
+    snapshot_thread: Option<JoinHandle<()>>,
+    /// Players from a [`Snapshot`], until they are given to the engine.
+    restored_players: Vec<RestoredPlayer<Self>>,
+    /// Records every tick, if enabled.
+    recorder: Option<ReplayRecorder>,
+    /// The current round, if playing in rounds.
+    pub(crate) rounds: Option<Rounds>,
+    /// When the next zombie wave comes, if there are waves.
+    pub(crate) zombies: Option<Zombies>,
+    /// Minimum number of bots from the `/spawnbots` chat command, until it is given to the engine.
+    pub(crate) requested_min_bots: Option<usize>,
+    /// How well bots play, which `/botdifficulty` changes.
+    pub(crate) bot_difficulty: Difficulty,
+    /// Personalities that bots are split between (see [`Personality::assigned`]), or any if empty.
+    pub(crate) bot_personalities: Vec<Personality>,
+    /// Source of all randomness after generating the world, so that a seed reproduces a game. The
+    /// same algorithm as [`StdRng`](rand::rngs::StdRng), but serializable for snapshots.
+    pub(crate) rng: ChaCha12Rng,
+    /// Drawn from [`Self::rng`] every tick, to seed each bot's RNG.
+    pub(crate) bot_seed: u64,
 }
 
 #[derive(Debug, Default)]
@@ -38,6 +80,10 @@ pub struct ClientData {
     knowledge: Knowledge,
     non_actor: NonActor,
     viewport: ChunkRectangle,
+    /// The latest [`Command::SetViewport`], until [`Self::viewport`] may change.
+    next_viewport: Option<ChunkRectangle>,
+    /// Whether the [`Balance`] and [`Generation`] were sent.
+    sent_initial: bool,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -52,6 +98,34 @@ pub struct PlayerData {
     pub death_reason: Option<DeathReason>,
     /// Cached alerts (some of which are used as persistent storage).
     pub(crate) alerts: Alerts,
+    /// If spectating, what is being watched.
+    pub spectate: Option<Spectate>,
+    /// Chunks within sensor range of [`Self::towers`], or [`None`] if there are none. Kept up to
+    /// date every tick, since every client that sees the player needs it.
+    pub sensor_coverage: Option<FxHashSet<ChunkId>>,
+    /// Whether [`Self::sensor_coverage`] must be recomputed, since a tower was gained, lost,
+    /// upgraded or EMPed.
+    pub(crate) sensor_coverage_stale: bool,
+    /// Towers that were inactive when [`Self::sensor_coverage`] was computed, which see farther
+    /// once they are active again.
+    pub(crate) sensor_inactive: Vec<TowerId>,
+    /// Rate limits [`Command::Spectate`].
+    pub spectate_cooldown: Ticks,
+    /// Progress of the current round.
+    pub round: Round,
+    /// Towers where waypoint orders broke, where they were going, and the units that stopped there,
+    /// until they are re-planned.
+    pub broken_waypoints: Vec<(TowerId, TowerId, UnitArray<u8>)>,
+    /// Minutes that requested alliances should last, by who they were requested with.
+    pub alliance_minutes: FxHashMap<PlayerId, u8>,
+    /// Seconds until timed alliances expire, by ally.
+    pub alliance_expiries: FxHashMap<PlayerId, u16>,
+    /// Rate limits [`Command::Alliance`] after breaking an alliance.
+    pub alliance_cooldown: Ticks,
+    /// Alliance flags to add to the next [`Alerts`], so they aren't reset before being sent.
+    pub alliance_alerts: AlertFlags,
+    /// Tower to pan the camera to in the next update, from the `/tp` chat command.
+    pub focus: Option<TowerId>,
 }
 
 impl GameArenaService for TowerService {
@@ -65,22 +139,103 @@ impl GameArenaService for TowerService {
     #[cfg(debug_assertions)]
     const LIVEBOARD_BOTS: bool = true;
     type Bot = TowerBot;
+    type Options = Options;
     type ClientData = ClientData;
     type GameUpdate = Update;
     type GameRequest = Command;
+    type AgentUpdate = AgentUpdate;
     type PlayerData = PlayerData;
     type PlayerExtension = ();
 
-    fn new(_: usize) -> Self {
-        print!("Generating world...");
-        let world = World::new(); // TODO Default?
-        println!("done!");
+    fn new(_: usize, options: &Options) -> Self {
+        // Must be set before generating the world.
+        if !options.realm {
+            Balance::set(options.balance());
+            let generation = options.generation();
+            println!("World seed: {}, size: {}", generation.seed, generation.size);
+            Generation::set(generation);
+        }
+        let rng_seed = options.rng_seed.unwrap_or(Generation::get().seed as u64);
+        println!("RNG seed: {rng_seed}");
 
-        Self {
+        let snapshots = options.snapshot.clone().map(|path| {
+            let period = Ticks::from_whole_secs(options.snapshot_period.clamp(1, 3600));
+            (path, period)
+        });
+        let restore = options
+            .restore
+            .then(|| snapshots.as_ref().map(|(path, _)| path.clone()))
+            .flatten()
+            .filter(|path| {
+                let exists = path.exists();
+                if !exists {
+                    warn!("no snapshot to restore at {}", path.display());
+                }
+                exists
+            });
+
+        let mut service = Self {
             maybe_dead: Default::default(),
+            changed_team: Default::default(),
             regulator: Default::default(),
-            world,
+            world: Default::default(),
+            snapshots,
+            snapshot_thread: None,
+            restored_players: Vec::new(),
+            recorder: None,
+            rounds: Rounds::new(options),
+            zombies: Zombies::new(options),
+            requested_min_bots: None,
+            bot_difficulty: options.bot_difficulty,
+            bot_personalities: options.bot_personalities.clone(),
+            rng: ChaCha12Rng::seed_from_u64(rng_seed),
+            bot_seed: 0,
+        };
+
+        if let Some(path) = restore {
+            let snapshot = Snapshot::load(&path)
+                .unwrap_or_else(|e| panic!("could not restore {}: {e}", path.display()));
+            if options.balance.is_some() || options.generation.is_some() {
+                warn!("ignoring balance and generation in favor of snapshot's");
+            }
+            service.restored_players = service.restore(snapshot);
+            println!(
+                "Restored world with {} players from {}",
+                service.restored_players.len(),
+                path.display()
+            );
+        } else {
+            print!("Generating world...");
+            service.world = World::new(); // TODO Default?
+            println!("done!");
         }
+
+        // Must be created after restoring, which may change the balance and generation.
+        service.recorder = options.record.as_deref().map(|path| {
+            ReplayRecorder::create(path)
+                .unwrap_or_else(|e| panic!("could not record {}: {e}", path.display()))
+        });
+        service
+    }
+
+    fn restored_players(&mut self) -> Vec<RestoredPlayer<Self>> {
+        std::mem::take(&mut self.restored_players)
+    }
+
+    fn realm_options(options: &Options, bot_difficulty: u8) -> Option<Options> {
+        // Balance and generation are global, so realms share them, but not files.
+        Some(Options {
+            snapshot: None,
+            restore: false,
+            record: None,
+            rng_seed: Some(thread_rng().gen()),
+            realm: true,
+            bot_difficulty: Difficulty::ALL
+                .get(bot_difficulty as usize)
+                .copied()
+                .unwrap_or(options.bot_difficulty),
+            ..options.clone()
+        })
     }
 
     fn player_joined(
@@ -115,12 +270,21 @@ impl GameArenaService for TowerService {
             Command::Alliance {
                 with,
                 break_alliance,
+                minutes,
             } => self
-                .alliance(player_id, with, break_alliance, players)
+                .alliance(player_id, with, break_alliance, minutes, players)
                 .map_err(wrap("Alliance")),
-            Command::DeployForce { tower_id, path } => self
-                .deploy_force(player_id, tower_id, path, players)
+            Command::DeployForce {
+                tower_id,
+                path,
+                legs,
+                deployment,
+            } => self
+                .deploy_force(player_id, tower_id, path, legs, deployment, players)
                 .map_err(wrap("DeployForce")),
+            Command::Research(research) => self
+                .research(player_id, research, players)
+                .map_err(wrap("Research")),
             Command::SetSupplyLine { tower_id, path } => {
                 if let Some(path) = path
                     .as_ref()
@@ -145,16 +309,22 @@ impl GameArenaService for TowerService {
                     })
                     .cloned()
                 {
-                    self.deploy_force(player_id, tower_id, path, players)
+                    self.deploy_force(player_id, tower_id, path, vec![], Deployment::All, players)
                         .map_err(wrap("SetSupplyLine/DeployForce"))?;
                 }
                 self.set_supply_line(player_id, tower_id, path, players)
                     .map_err(wrap("SetSupplyLine"))
             }
+            Command::SetSupplyLines {
+                tower_id,
+                supply_lines,
+            } => self
+                .set_supply_lines(player_id, tower_id, supply_lines, players)
+                .map_err(wrap("SetSupplyLines")),
             Command::SetViewport(viewport) => {
                 let mut player = player_tuple.borrow_player_mut();
                 if let Some(client) = player.client_mut() {
-                    client.data_mut().viewport = viewport;
+                    client.data_mut().next_viewport = Some(viewport);
                     Ok(())
                 } else {
                     debug_assert!(false);
@@ -163,12 +333,21 @@ impl GameArenaService for TowerService {
                 .map_err(wrap("SetViewport"))
             }
             Command::Spawn => self.spawn_player(player_id, players).map_err(wrap("Spawn")),
+            Command::Spectate(spectate) => self
+                .spectate(player_id, spectate, players)
+                .map_err(wrap("Spectate")),
+            Command::TransferTower { tower_id, to } => self
+                .transfer_tower(player_id, tower_id, to, players)
+                .map_err(wrap("TransferTower")),
             Command::Upgrade {
                 tower_id,
                 tower_type,
             } => self
                 .upgrade_tower(player_id, tower_id, tower_type, players)
                 .map_err(wrap("Upgrade")),
+            Command::UseRulerAbility { ability, target } => self
+                .use_ruler_ability(player_id, ability, target, players)
+                .map_err(wrap("UseRulerAbility")),
         })() {
             if !player_tuple.borrow_player().is_bot() {
                 warn!("{}", e);
@@ -177,6 +356,21 @@ impl GameArenaService for TowerService {
         None
     }
 
+    fn agent_command(
+        &mut self,
+        command: Self::GameRequest,
+        player_tuple: &Arc<PlayerTuple<Self>>,
+        _client_data: &mut Self::ClientData,
+        players: &PlayerRepo<Self>,
+    ) -> Option<Self::GameUpdate> {
+        if let Command::SetViewport(_) = command {
+            // Agents are sent what's around their towers instead.
+            None
+        } else {
+            self.player_command(command, player_tuple, players)
+        }
+    }
+
     fn player_left(&mut self, player_tuple: &Arc<PlayerTuple<Self>>, _: &PlayerRepo<Self>) {
         let player_id = player_tuple.borrow_player().player_id;
         self.regulator.leave(player_id);
@@ -185,11 +379,31 @@ impl GameArenaService for TowerService {
         self.maybe_dead.insert(player_id);
     }
 
+    fn player_changed_team(
+        &mut self,
+        player_tuple: &Arc<PlayerTuple<Self>>,
+        _old_team: Option<TeamId>,
+        _players: &PlayerRepo<Self>,
+    ) {
+        // Called after tick, but setting the team is PlayerMaintainance.
+        let player_id = player_tuple.borrow_player().player_id;
+        self.changed_team.insert(player_id);
+    }
+
+    fn chat_command(
+        &mut self,
+        command: &str,
+        player_id: PlayerId,
+        players: &PlayerRepo<Self>,
+    ) -> Option<String> {
+        self.execute_chat_command(command, player_id, players)
+    }
+
     fn get_game_update(
         &self,
         player_tuple: &Arc<PlayerTuple<Self>>,
         client_data: &mut Self::ClientData,
-        _players: &PlayerRepo<Self>,
+        players: &PlayerRepo<Self>,
     ) -> Option<Self::GameUpdate> {
         let player = player_tuple.borrow_player();
         if !self.regulator.active(player.player_id) {
@@ -198,47 +412,91 @@ impl GameArenaService for TowerService {
         let admin = if let Some(client) = player.client() {
             client.admin || cfg!(debug_assertions) /* || true */
         } else {
-            debug_assert!(false);
+            // Agents aren't clients.
+            debug_assert!(player.is_bot());
             false
         };
 
         let player_id = player.player_id;
         let player = AtomicRef::map(player, |player| &player.data);
 
-        let bounding_rectangle = if player.towers.is_empty() {
-            let middle: ChunkId = World::CENTER.into();
-            ChunkRectangle {
-                bottom_left: middle,
-                top_right: middle,
-            }
-            .into()
+        let world_rectangle = ChunkRectangle::new(
+            ChunkId::new(0, 0),
+            ChunkId::new(
+                WorldChunks::size_chunks() as u8 - 1,
+                WorldChunks::size_chunks() as u8 - 1,
+            ),
+        );
+
+        let followed = if let Some(Spectate::Player(followed_id)) = player.spectate {
+            players
+                .borrow_player(followed_id)
+                .filter(|followed| followed.alive)
+                .map(|followed| {
+                    (
+                        followed_id,
+                        AtomicRef::map(followed, |followed| &followed.data),
+                    )
+                })
+        } else {
+            None
+        };
+
+        let mut teammates = Vec::new();
+        let (bounding_rectangle, coverage) = if player.spectate == Some(Spectate::Viewport) {
+            (world_rectangle.into(), Vec::new())
         } else {
-            let margin = player
-                .tower_counts
+            // Spectators see what the player they follow sees.
+            let (watched_id, watched) = followed
+                .as_ref()
+                .map_or((player_id, &*player), |(id, followed)| (*id, &**followed));
+            let mut bounding_rectangle = Self::bounding_rectangle(watched);
+
+            // Teammates share vision.
+            teammates.extend(
+                self.world
+                    .teammates(watched_id)
+                    .filter_map(|teammate_id| players.borrow_player(teammate_id))
+                    .filter(|teammate| teammate.sensor_coverage.is_some()),
+            );
+            for teammate in &teammates {
+                bounding_rectangle =
+                    bounding_rectangle.union(Self::bounding_rectangle(&teammate.data));
+            }
+
+            // Without towers, everything within bounds is visible.
+            let coverage: Vec<_> = watched
+                .sensor_coverage
                 .iter()
-                .filter(|(_, c)| **c > 0)
-                .map(|(t, _)| t.sensor_radius() / TowerId::CONVERSION)
-                .max()
-                .unwrap_or(0)
-                .clamp(3, 12);
-            TowerRectangle::bounding(player.towers.iter().copied()).add_margin(margin)
+                .chain(teammates.iter().filter_map(|t| t.sensor_coverage.as_ref()))
+                .filter(|_| !admin)
+                .collect();
+            (bounding_rectangle, coverage)
         };
 
         debug_assert!(bounding_rectangle.is_valid());
 
+        // Spectators can watch anywhere, so limit how much they see at once and how often they
+        // can look elsewhere.
+        let watching_anywhere = !admin && player.spectate == Some(Spectate::Viewport);
+        if let Some(next_viewport) = client_data.next_viewport
+            && (!watching_anywhere || self.counter().every(Self::SPECTATOR_VIEWPORT_PERIOD))
+        {
+            client_data.viewport = next_viewport;
+            client_data.next_viewport = None;
+        }
+
         let effective_viewport = if admin {
             client_data.viewport
+        } else if watching_anywhere {
+            client_data
+                .viewport
+                .shrink_to(Spectate::MAX_VIEWPORT_CHUNKS)
         } else {
             // Viewport clamped to bounds.
             client_data.viewport.clamp_to(bounding_rectangle.into())
         }
-        .clamp_to(ChunkRectangle::new(
-            ChunkId::new(0, 0),
-            ChunkId::new(
-                WorldChunks::SIZE_CHUNKS as u8 - 1,
-                WorldChunks::SIZE_CHUNKS as u8 - 1,
-            ),
-        ));
+        .clamp_to(world_rectangle);
 
         let actor_update = self.world.get_update(
             &mut client_data.knowledge,
@@ -247,6 +505,9 @@ impl GameArenaService for TowerService {
                     let chunk_ids: FxHashSet<_> = Map::keys(&k.chunk).collect();
                     let mut governor: u8 = 6;
                     effective_viewport.into_iter().filter(move |chunk_id| {
+                        if !coverage.is_empty() && !coverage.iter().any(|c| c.contains(chunk_id)) {
+                            return false;
+                        }
                         chunk_ids.contains(chunk_id) || {
                             if let Some(new) = governor.checked_sub(1) {
                                 governor = new;
@@ -281,75 +542,88 @@ impl GameArenaService for TowerService {
             death_reason: player.death_reason.into(),
             alerts: player.alerts,
             bounding_rectangle,
+            round: player.round,
+            alliance_requests: self.alliance_requests(player_id, players),
+            focus: player.focus,
+            spectate: player.spectate,
         };
         let non_actor_diff = client_data.non_actor.diff(&non_actor);
         client_data.non_actor = non_actor;
 
+        let initial = !std::mem::replace(&mut client_data.sent_initial, true);
+        let balance = initial.then(|| Box::new(Balance::get().clone()));
+        let generation = initial.then(|| Box::new(Generation::get().clone()));
+
         // Always send even if there are no events, for accurate time-keeping.
         Some(Update {
             actor_update,
             non_actor_diff,
+            balance,
+            generation,
         })
     }
 
+    fn get_agent_update(
+        &self,
+        player_tuple: &Arc<PlayerTuple<Self>>,
+        _client_data: &mut Self::ClientData,
+        _players: &PlayerRepo<Self>,
+    ) -> Option<Self::AgentUpdate> {
+        let player = player_tuple.borrow_player();
+        self.regulator
+            .active(player.player_id)
+            .then(|| AgentUpdate::new(&self.world, player.player_id, &player.data))
+    }
+
     fn is_alive(&self, player_tuple: &Arc<PlayerTuple<Self>>) -> bool {
         player_tuple.borrow_player().data.alive
     }
 
+    fn is_spectator(&self, player_tuple: &Arc<PlayerTuple<Self>>) -> bool {
+        player_tuple.borrow_player().data.spectate.is_some()
+    }
+
     fn tick(&mut self, context: &mut Context<Self>) {
+        if let Some(min_bots) = self.requested_min_bots.take() {
+            context.set_min_bots(min_bots);
+        }
+        // Bots update after ticking.
+        self.bot_seed = self.rng.gen();
+
+        // Forces stopped at the end of the last tick, so they can leave before tick_after_inputs.
+        let mut broken_waypoints = Vec::new();
+        for mut player in context.players.iter_borrow_mut() {
+            let player_id = player.player_id;
+            broken_waypoints.extend(
+                player
+                    .broken_waypoints
+                    .drain(..)
+                    .map(|(tower_id, destination, units)| {
+                        (player_id, tower_id, destination, units)
+                    }),
+            );
+        }
+        for (player_id, tower_id, destination, units) in broken_waypoints {
+            // The tower may have been lost or emptied since, in which case the order is over.
+            let _ =
+                self.replan_waypoints(player_id, tower_id, destination, &units, &context.players);
+        }
+
         for mut player_ref in context.players.iter_borrow_mut() {
             let player = &mut *player_ref;
+            player.spectate_cooldown = player.spectate_cooldown.saturating_sub(Ticks::ONE);
+            player.alliance_cooldown = player.alliance_cooldown.saturating_sub(Ticks::ONE);
             if player.data.alive {
                 player.lifetime = player.lifetime.saturating_add(Ticks::ONE);
 
-                #[cfg(debug_assertions)]
-                if self.counter().every(Ticks::from_whole_secs(20))
-                    && matches!(player.alias().as_str(), "chonk" | "squonk")
-                {
-                    use common::chunk::ChunkInput;
-                    use common::force::{Force, Path};
-
-                    let alias = player.alias();
-                    let radius = if &*alias == "chonk" {
-                        500 // Chonk is a giant circle about the size of Debased.
-                    } else {
-                        10000 // Squonk is the whole world, aka Square + Chonk.
-                    };
-                    println!("{}", alias);
-
-                    let mut events = Vec::new();
-                    for (tower_id, tower) in
-                        self.world.chunk.iter_towers_circle(World::CENTER, radius)
-                    {
-                        if tower.player_id == Some(player.player_id) {
-                            continue;
-                        }
-                        let mut units = common::units::Units::default();
-                        units.add(Unit::Bomber, 5);
-
-                        let mut src = World::CENTER;
-                        if tower_id == src {
-                            src = src.connectivity_id().unwrap(); // We can't send from a tower to itself.
-                        }
-
-                        let force =
-                            Force::new(player.player_id, units, Path::new(vec![src, tower_id]));
-                        let (chunk_id, tower_id) = tower_id.split();
-                        events.push((chunk_id, ChunkInput::AddInboundForce { tower_id, force }));
-                    }
-                    for (chunk_id, event) in events {
-                        self.world
-                            .dispatch_chunk_input(chunk_id, event, |_| unreachable!());
-                    }
-                }
-
                 if self.counter().every(Ticks::from_whole_secs(1)) {
                     player.score = 0;
                     let mut tower_counts: TowerArray<u8> = TowerArray::default();
 
+                    let alliance_alerts = std::mem::take(&mut player.data.alliance_alerts);
                     let alerts = &mut player.data.alerts;
                     alerts.reset_ephemeral();
-                    let mut flags = alerts.flags();
+                    let mut flags = alerts.flags() | alliance_alerts;
 
                     // Assume ruler is not safe until proven otherwise.
                     flags |= AlertFlag::RulerNotSafe;
@@ -387,7 +661,7 @@ impl GameArenaService for TowerService {
                                         continue;
                                     }
                                     let generates = tower.unit_generation(unit).is_some();
-                                    if generates && tower.supply_line.is_some() {
+                                    if generates && !tower.supply_lines.is_empty() {
                                         // Problem will go away.
                                         continue;
                                     }
@@ -431,11 +705,48 @@ impl GameArenaService for TowerService {
             }
         }
 
+        if self.counter().every(Ticks::from_whole_secs(1)) {
+            self.expire_alliances(&context.players);
+            self.update_zombies(&context.players);
+        }
+
+        if self.counter().every(Research::POINTS_PERIOD) {
+            self.produce_research_points(&context.players);
+        }
+
         self.world
             .tick_after_inputs(&mut Self::on_info_event(&context.players, |_| {
                 unreachable!("tick_after_inputs killed player")
             }));
 
+        for mut player in context.players.iter_borrow_mut() {
+            let player = &mut *player;
+            // Towers finishing upgrades or recovering from EMPs don't make infos.
+            let activated = player
+                .sensor_inactive
+                .iter()
+                .any(|&tower_id| self.world.chunk.get(tower_id).map_or(true, Tower::active));
+            if !(player.sensor_coverage_stale || activated) {
+                continue;
+            }
+            player.sensor_coverage_stale = false;
+            player.sensor_inactive.clear();
+
+            let towers = player.towers.iter().filter_map(|&tower_id| {
+                let tower = self.world.chunk.get(tower_id);
+                debug_assert!(tower.is_some(), "missing tower");
+                Some((tower_id, tower?))
+            });
+            let sensor_inactive = &mut player.sensor_inactive;
+            let towers = towers.inspect(|(tower_id, tower)| {
+                if !tower.active() {
+                    sensor_inactive.push(*tower_id);
+                }
+            });
+            player.sensor_coverage =
+                (!player.towers.is_empty()).then(|| Self::sensor_coverage(towers));
+        }
+
         self.regulator.tick(|player_id, joining| {
             if joining {
                 self.world
@@ -448,10 +759,38 @@ impl GameArenaService for TowerService {
     }
 
     fn post_update(&mut self, context: &mut Context<Self>) {
+        // Record before the inputs are cleared.
+        if let Some(Err(e)) = self.recorder.as_mut().map(|r| r.record(&self.world)) {
+            error!("stopped recording replay: {e}");
+            self.recorder = None;
+        }
+
         self.world.post_update();
 
         // Boundary between old tick and new tick.
 
+        // Focus was sent in the last update.
+        for mut player in context.players.iter_borrow_mut() {
+            player.focus = None;
+        }
+
+        if let Some((path, period)) = &self.snapshots && self.counter().every(*period) {
+            // Writers would clobber each other's temporary file.
+            let saving = self.snapshot_thread.as_ref();
+            if saving.is_some_and(|thread| !thread.is_finished()) {
+                warn!("skipping snapshot since the last one is still being saved");
+            } else {
+                let snapshot = self.snapshot(&context.players);
+                let path = path.clone();
+                // Encoding and writing takes a while, so don't block the game.
+                let thread = std::thread::spawn(move || match snapshot.save(&path) {
+                    Ok(()) => info!("saved snapshot to {}", path.display()),
+                    Err(e) => error!("could not save snapshot to {}: {e}", path.display()),
+                });
+                self.snapshot_thread = Some(thread);
+            }
+        }
+
         // Take to avoid borrowing issue.
         let mut maybe_dead = std::mem::take(&mut self.maybe_dead);
         for player_id in maybe_dead.drain() {
@@ -460,6 +799,18 @@ impl GameArenaService for TowerService {
         }
         self.maybe_dead = maybe_dead;
 
+        let mut changed_team = std::mem::take(&mut self.changed_team);
+        for player_id in changed_team.drain() {
+            // Makes `PlayerMaintainance`s which have to run before tick_before_inputs.
+            self.change_team(player_id, &context.players);
+        }
+        self.changed_team = changed_team;
+
+        if self.counter().every(Ticks::from_whole_secs(1)) {
+            // Makes `ChunkMaintenance`s which have to run before tick_before_inputs.
+            self.update_round(context);
+        }
+
         if self.counter().next().every(Ticks::from_whole_secs(8)) {
             // Makes `ChunkMaintenance`s which have to run before tick_before_inputs.
             self.shrink(&context.players);
@@ -469,14 +820,6 @@ impl GameArenaService for TowerService {
             .tick_before_inputs(&mut Self::on_info_event(&context.players, |player_id| {
                 self.maybe_dead.insert(player_id);
             }));
-
-        /*
-        for player_id in context.players.iter_player_ids() {
-            if thread_rng().gen_bool(0.25) {
-                self.maybe_dead.insert(player_id);
-            }
-        }
-        */
     }
 
     fn world_size(&self) -> f32 {
@@ -504,10 +847,83 @@ impl GameArenaService for TowerService {
 }
 
 impl TowerService {
+    /// How often spectators watching [`Spectate::Viewport`] can move their viewport.
+    const SPECTATOR_VIEWPORT_PERIOD: Ticks = Ticks::from_whole_millis(500);
+
     fn counter(&self) -> Ticks {
         self.world.singleton().tick
     }
 
+    /// Bounds of a player's camera, which is an approximation of their towers.
+    fn bounding_rectangle(player: &PlayerData) -> TowerRectangle {
+        if player.towers.is_empty() {
+            return Self::center_rectangle();
+        }
+        let margin = player
+            .tower_counts
+            .iter()
+            .filter(|(_, c)| **c > 0)
+            .map(|(t, _)| t.sensor_radius() / TowerId::CONVERSION)
+            .max()
+            .unwrap_or(0)
+            .clamp(3, 12);
+        TowerRectangle::bounding(player.towers.iter().copied()).add_margin(margin)
+    }
+
+    /// Chunks within sensor range of `towers`, the union of circles of
+    /// [`TowerType::sensor_radius`][`common::tower::TowerType::sensor_radius`] around the active
+    /// ones. Inactive towers only see their own chunk.
+    fn sensor_coverage<'a>(
+        towers: impl IntoIterator<Item = (TowerId, &'a Tower)>,
+    ) -> FxHashSet<ChunkId> {
+        let mut coverage = FxHashSet::default();
+        for (tower_id, tower) in towers {
+            if tower.active() {
+                let radius = tower.tower_type.sensor_radius() / TowerId::CONVERSION;
+                coverage.extend(ChunkId::iter_circle(tower_id, radius));
+            } else {
+                coverage.insert(ChunkId::from(tower_id));
+            }
+        }
+        coverage
+    }
+
+    /// Alliances other players requested with `player_id`, which it hasn't accepted.
+    fn alliance_requests(
+        &self,
+        player_id: PlayerId,
+        players: &PlayerRepo<Self>,
+    ) -> AllianceRequests {
+        if !self.world.player.contains_key(&player_id) {
+            return AllianceRequests::default();
+        }
+        let allies = &self.world.player(player_id).allies;
+        let mut requests: Vec<_> = Map::iter(&self.world.player)
+            .filter(|(other_id, other)| {
+                other.actor.allies.contains(&player_id) && !allies.contains(other_id)
+            })
+            .map(|(other_id, _)| AllianceRequest {
+                player_id: other_id,
+                minutes: players
+                    .borrow_player(other_id)
+                    .and_then(|other| other.alliance_minutes.get(&player_id).copied()),
+            })
+            .collect();
+        // Don't resend requests because of iteration order.
+        requests.sort_unstable_by_key(|request| request.player_id);
+        AllianceRequests(requests)
+    }
+
+    /// What a player without towers can see.
+    fn center_rectangle() -> TowerRectangle {
+        let middle: ChunkId = World::center().into();
+        ChunkRectangle {
+            bottom_left: middle,
+            top_right: middle,
+        }
+        .into()
+    }
+
     pub(crate) fn on_info_event<'a>(
         players: &'a PlayerRepo<Self>,
         mut maybe_dead: impl FnMut(PlayerId) + 'a,
@@ -525,6 +941,7 @@ impl TowerService {
                     }
 
                     let inserted = new_player.towers.insert(tower_id);
+                    new_player.sensor_coverage_stale = true;
                     debug_assert!(
                         inserted,
                         "tower {:?} was already in set of {:?} but now inserted due to {:?}",
@@ -541,12 +958,16 @@ impl TowerService {
 
                 let mut player = players.borrow_player_mut(player_id).unwrap();
                 player.death_reason = match reason {
+                    LostRulerReason::KilledBy(None, unit) => {
+                        Some(DeathReason::RulerKilledByZombies { unit })
+                    }
                     LostRulerReason::KilledBy(attacker_player_id, unit) => {
                         Some(DeathReason::RulerKilled {
                             alias: get_alias(attacker_player_id),
                             unit,
                         })
                     }
+                    LostRulerReason::Stranded => Some(DeathReason::RulerStranded),
                 };
                 maybe_dead(player_id);
             }
@@ -558,11 +979,55 @@ impl TowerService {
                 if let Some(mut old_player) = players.borrow_player_mut(player_id) {
                     let removed = old_player.towers.remove(&tower_id);
                     debug_assert!(removed);
+                    old_player.sensor_coverage_stale = true;
                 } else {
                     debug_assert!(false);
                 }
             }
+            Info::WaypointsBroken {
+                tower_id,
+                player_id,
+                destination,
+                units,
+            } => {
+                if let Some(mut player) = players.borrow_player_mut(player_id) {
+                    player.broken_waypoints.push((tower_id, destination, units));
+                }
+            }
+            Info::Emp(_) => {
+                // The EMPed tower's owner isn't known, but EMPs are rare.
+                for mut player in players.iter_borrow_mut() {
+                    player.sensor_coverage_stale = true;
+                }
+            }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TowerService;
+    use common::chunk::ChunkId;
+    use common::tower::{Tower, TowerId, TowerType};
+    use std::num::NonZeroU8;
+
+    #[test]
+    fn sensor_coverage() {
+        let radar = Tower::with_type(TowerType::Radar);
+        let near = TowerId::new(100, 100);
+        let far = TowerId::new(300, 100);
+
+        let coverage = TowerService::sensor_coverage([(near, &radar), (far, &radar)]);
+        assert!(coverage.contains(&ChunkId::from(near)));
+        assert!(coverage.contains(&ChunkId::from(far)));
+        assert!(!coverage.contains(&ChunkId::from(TowerId::new(200, 100))));
+
+        // Upgrading towers only see themselves.
+        let mut upgrading = radar.clone();
+        upgrading.delay = NonZeroU8::new(1);
+        let coverage = TowerService::sensor_coverage([(near, &upgrading)]);
+        assert_eq!(coverage.len(), 1);
+        assert!(coverage.contains(&ChunkId::from(near)));
+    }
+}
@@ -0,0 +1,171 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::options::Options;
+use crate::service::TowerService;
+use crate::strategy::Personality;
+use core_protocol::id::PlayerId;
+use core_protocol::name::PlayerAlias;
+use game_server::headless::Headless;
+
+/// Pits bots against each other without any clients, as fast as possible, keeping track of how
+/// each bot does.
+pub struct Simulation {
+    headless: Headless<TowerService>,
+    bots: Vec<BotStats>,
+}
+
+/// How a bot did in a [`Simulation`].
+#[derive(Debug)]
+pub struct BotReport {
+    pub player_id: PlayerId,
+    pub alias: PlayerAlias,
+    pub personality: Personality,
+    /// Ticks spent alive, over all lives.
+    pub alive_ticks: usize,
+    pub deaths: usize,
+    /// Towers at the end, or zero if dead.
+    pub towers: usize,
+    pub peak_towers: usize,
+    pub score: u32,
+}
+
+/// How a bot is doing.
+struct BotStats {
+    player_id: PlayerId,
+    personality: Personality,
+    alive_ticks: usize,
+    was_alive: bool,
+    deaths: usize,
+    peak_towers: usize,
+}
+
+impl Simulation {
+    /// Creates a simulation with one bot per personality, in that order.
+    pub fn new(personalities: &[Personality], options: &Options) -> Self {
+        let options = Options {
+            bot_personalities: personalities.to_vec(),
+            ..options.clone()
+        };
+        let headless = Headless::new(personalities.len(), &options);
+        let bots = (0..personalities.len())
+            .map(|n| {
+                let player_id = PlayerId::nth_bot(n).unwrap();
+                BotStats {
+                    player_id,
+                    personality: Personality::assigned(personalities, player_id).unwrap(),
+                    alive_ticks: 0,
+                    was_alive: false,
+                    deaths: 0,
+                    peak_towers: 0,
+                }
+            })
+            .collect();
+        Self { headless, bots }
+    }
+
+    pub fn tick(&mut self) {
+        self.headless.tick();
+
+        for stats in &mut self.bots {
+            let Some(player) = self.headless.players().borrow_player(stats.player_id) else {
+                continue;
+            };
+            if player.alive {
+                stats.alive_ticks += 1;
+                stats.peak_towers = stats.peak_towers.max(player.towers.len());
+            } else if stats.was_alive {
+                stats.deaths += 1;
+            }
+            stats.was_alive = player.alive;
+        }
+    }
+
+    /// Reports how each bot that joined did so far.
+    pub fn report(&self) -> Vec<BotReport> {
+        self.bots
+            .iter()
+            .filter_map(|stats| {
+                let player = self.headless.players().borrow_player(stats.player_id)?;
+                Some(BotReport {
+                    player_id: stats.player_id,
+                    alias: player.alias(),
+                    personality: stats.personality,
+                    alive_ticks: stats.alive_ticks,
+                    deaths: stats.deaths,
+                    towers: if player.alive { player.towers.len() } else { 0 },
+                    peak_towers: stats.peak_towers,
+                    score: player.score,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::simulation::Simulation;
+    use crate::strategy::Personality;
+    use core_protocol::id::PlayerId;
+
+    #[test]
+    fn report() {
+        // Few enough that no bot quits, which takes at least 80 seconds.
+        const TICKS: usize = 300;
+        let personalities = [
+            Personality::Turtle,
+            Personality::Expansionist,
+            Personality::Naval,
+            Personality::Nuker,
+        ];
+        let mut simulation = Simulation::new(&personalities, &Options::default());
+        for _ in 0..TICKS {
+            simulation.tick();
+        }
+
+        let report = simulation.report();
+        assert_eq!(report.len(), personalities.len());
+        for (n, (bot, personality)) in report.iter().zip(personalities).enumerate() {
+            assert_eq!(bot.player_id, PlayerId::nth_bot(n).unwrap());
+            assert_eq!(bot.personality, personality);
+            assert!(bot.alive_ticks > 0 && bot.alive_ticks <= TICKS, "{bot:?}");
+            assert!(bot.peak_towers > 0, "{bot:?}");
+            assert!(bot.towers <= bot.peak_towers, "{bot:?}");
+            assert!(bot.deaths == 0 || bot.alive_ticks < TICKS, "{bot:?}");
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        // The same world, so that only the RNG seed could make runs differ.
+        let options = Options {
+            seed: Some(7),
+            rng_seed: Some(7),
+            ..Default::default()
+        };
+        let run = || {
+            let mut simulation = Simulation::new(&Personality::ALL, &options);
+            for _ in 0..200 {
+                simulation.tick();
+            }
+            let report: Vec<_> = simulation
+                .report()
+                .into_iter()
+                .map(|bot| (bot.player_id, bot.alive_ticks, bot.towers, bot.score))
+                .collect();
+            let world = &simulation.headless.service().world;
+            let owners: Vec<_> = world
+                .chunk
+                .iter_towers()
+                .map(|(tower_id, tower)| (tower_id, tower.player_id))
+                .collect();
+            (report, owners)
+        };
+
+        let (report, owners) = run();
+        assert!(!report.is_empty());
+        assert!(owners.iter().any(|(_, player_id)| player_id.is_some()));
+        assert_eq!(run(), (report, owners));
+    }
+}
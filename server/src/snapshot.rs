@@ -0,0 +1,300 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::service::{PlayerData, TowerService};
+use common::alerts::Alerts;
+use common::balance::Balance;
+use common::chunk::{Chunk, ChunkId, ChunkMaintenance};
+use common::death_reason::DeathReason;
+use common::generation::Generation;
+use common::player::Player;
+use common::singleton::{Singleton, SingletonId};
+use common::ticks::Ticks;
+use common::tower::TowerId;
+use common::world::{ChunkMap, World};
+use common_util::storage::Map;
+use core_protocol::id::{PlayerId, Token};
+use core_protocol::name::PlayerAlias;
+use core_protocol::prelude::*;
+use fxhash::FxHashSet;
+use game_server::game_service::RestoredPlayer;
+use game_server::player::PlayerRepo;
+use rand_chacha::ChaCha12Rng;
+use std::path::Path;
+
+/// Everything needed to resume a [`TowerService`] after a restart. Only real players are saved,
+/// since bots don't reconnect.
+#[derive(Encode, Decode)]
+pub struct Snapshot {
+    version: u16,
+    balance: Balance,
+    generation: Generation,
+    chunks: Vec<(ChunkId, Chunk)>,
+    singleton: Singleton,
+    players: Vec<SnapshotPlayer>,
+    /// So that a seeded game continues the same after a restart.
+    #[bitcode(with_serde)]
+    rng: ChaCha12Rng,
+}
+
+#[derive(Encode, Decode)]
+struct SnapshotPlayer {
+    player_id: PlayerId,
+    token: Token,
+    alias: PlayerAlias,
+    actor: Player,
+    alive: bool,
+    towers: Vec<TowerId>,
+    lifetime: Ticks,
+    #[bitcode(with_serde)]
+    death_reason: Option<DeathReason>,
+    #[bitcode(with_serde)]
+    alerts: Alerts,
+    alliance_minutes: Vec<(PlayerId, u8)>,
+    alliance_expiries: Vec<(PlayerId, u16)>,
+    alliance_cooldown: Ticks,
+}
+
+impl Snapshot {
+    /// Incremented whenever the format changes, since old snapshots can't be restored.
+    const VERSION: u16 = 12;
+
+    /// Writes to a temporary file first, so a crash while saving doesn't corrupt the previous
+    /// snapshot. Saves to the same path must not run at the same time, since they would share
+    /// the temporary file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = bitcode::encode(self).map_err(|e| e.to_string())?;
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, bytes).map_err(|e| e.to_string())?;
+        std::fs::rename(&temporary, path).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let snapshot: Self = bitcode::decode(&bytes).map_err(|e| e.to_string())?;
+        if snapshot.version != Self::VERSION {
+            return Err(format!(
+                "snapshot version {} is unsupported (expected {})",
+                snapshot.version,
+                Self::VERSION
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+impl TowerService {
+    /// Must be called between ticks, so no inputs are in flight.
+    pub(crate) fn snapshot(&self, players: &PlayerRepo<Self>) -> Snapshot {
+        let players = players
+            .iter_borrow()
+            .filter(|player| self.regulator.active(player.player_id))
+            .filter_map(|player| {
+                let client = player.client()?;
+                let actor = self.world.player.get(&player.player_id)?.actor.clone();
+                Some(SnapshotPlayer {
+                    player_id: player.player_id,
+                    token: client.token(),
+                    alias: player.alias(),
+                    actor,
+                    alive: player.alive,
+                    towers: player.towers.iter().copied().collect(),
+                    lifetime: player.lifetime,
+                    death_reason: player.death_reason,
+                    alerts: player.alerts,
+                    alliance_minutes: player
+                        .alliance_minutes
+                        .iter()
+                        .map(|(&k, &v)| (k, v))
+                        .collect(),
+                    alliance_expiries: player
+                        .alliance_expiries
+                        .iter()
+                        .map(|(&k, &v)| (k, v))
+                        .collect(),
+                    alliance_cooldown: player.alliance_cooldown,
+                })
+            })
+            .collect();
+
+        Snapshot {
+            version: Snapshot::VERSION,
+            balance: Balance::get().clone(),
+            generation: Generation::get().clone(),
+            chunks: self
+                .world
+                .chunk
+                .iter_chunks()
+                .map(|(chunk_id, chunk)| (chunk_id, chunk.clone()))
+                .collect(),
+            singleton: self.world.singleton().clone(),
+            players,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Sets the [`Balance`] and [`Generation`] and recreates the [`World`]. Returns the players
+    /// for the engine to recreate.
+    pub(crate) fn restore(&mut self, snapshot: Snapshot) -> Vec<RestoredPlayer<Self>> {
+        Balance::set(snapshot.balance);
+        Generation::set(snapshot.generation);
+        self.rng = snapshot.rng;
+
+        let mut world = World {
+            chunk: ChunkMap::default(),
+            player: Default::default(),
+            singleton: Some((SingletonId, snapshot.singleton.into())),
+        };
+        for (chunk_id, chunk) in snapshot.chunks {
+            world.chunk.insert(chunk_id, chunk.into());
+        }
+
+        let restored: FxHashSet<PlayerId> =
+            snapshot.players.iter().map(|p| p.player_id).collect();
+
+        // Bots and players that left are gone, so their towers and forces must be too.
+        let mut gone = FxHashSet::default();
+        for (_, tower) in world.chunk.iter_towers() {
+            let forces = tower.inbound_forces.iter().chain(&tower.outbound_forces);
+            let player_ids = tower
+                .player_id
+                .into_iter()
+                .chain(forces.filter_map(|f| f.player_id));
+            gone.extend(player_ids.filter(|player_id| !restored.contains(player_id)));
+        }
+        let chunk_ids: Vec<_> = world.chunk.iter_chunks().map(|(id, _)| id).collect();
+        for &player_id in &gone {
+            for &chunk_id in &chunk_ids {
+                world.dispatch_chunk_maintenance(
+                    chunk_id,
+                    ChunkMaintenance::KillPlayer { player_id },
+                    |_| {},
+                );
+            }
+        }
+
+        let mut restored_players = Vec::with_capacity(snapshot.players.len());
+        for mut player in snapshot.players {
+            player.actor.allies.retain(|ally| restored.contains(ally));
+            player.actor.new_alliances.clear();
+            // Teams aren't saved, so they can't be restored.
+            player.actor.team_id = None;
+            world.player.insert(player.player_id, player.actor.into());
+
+            // Restored players have already joined.
+            let fast_path = self.regulator.join(player.player_id);
+            debug_assert!(fast_path);
+
+            restored_players.push(RestoredPlayer {
+                player_id: player.player_id,
+                token: player.token,
+                alias: player.alias,
+                data: PlayerData {
+                    alive: player.alive,
+                    towers: player.towers.into_iter().collect(),
+                    lifetime: player.lifetime,
+                    tower_counts: Default::default(),
+                    death_reason: player.death_reason,
+                    alerts: player.alerts,
+                    sensor_coverage: None,
+                    sensor_coverage_stale: true,
+                    sensor_inactive: Vec::new(),
+                    spectate: None,
+                    spectate_cooldown: Ticks::ZERO,
+                    round: Default::default(),
+                    broken_waypoints: Vec::new(),
+                    alliance_minutes: player.alliance_minutes.into_iter().collect(),
+                    alliance_expiries: player.alliance_expiries.into_iter().collect(),
+                    alliance_cooldown: player.alliance_cooldown,
+                    alliance_alerts: Default::default(),
+                    focus: None,
+                },
+            });
+        }
+
+        world.post_update();
+        self.world = world;
+        restored_players
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::testing::{arena, spawn, tick};
+
+    #[test]
+    fn round_trip() {
+        let mut headless = arena(&Options::default());
+        let player_ids = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+        tick(&mut headless, 20);
+
+        let path = std::env::temp_dir().join(format!("snapshot_{}.bin", std::process::id()));
+        let snapshot = headless.service().snapshot(headless.players());
+        snapshot.save(&path).unwrap();
+
+        let options = Options {
+            snapshot: Some(path.clone()),
+            restore: true,
+            ..Default::default()
+        };
+        let restored = arena(&options);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            restored.service().world.singleton().tick,
+            headless.service().world.singleton().tick
+        );
+        assert_eq!(restored.service().rng, headless.service().rng);
+        for player_id in player_ids {
+            let before = headless.players().borrow_player(player_id).unwrap();
+            let after = restored.players().borrow_player(player_id).unwrap();
+            assert!(after.alive);
+            assert_eq!(after.alias(), before.alias());
+            assert_eq!(
+                after.client().unwrap().token(),
+                before.client().unwrap().token()
+            );
+            assert_eq!(after.towers, before.towers);
+            assert_eq!(after.lifetime, before.lifetime);
+            for tower_id in &after.towers {
+                let tower = restored.service().world.chunk.get(*tower_id).unwrap();
+                assert_eq!(tower.player_id, Some(player_id));
+            }
+        }
+    }
+
+    #[test]
+    fn mid_save() {
+        let mut headless = arena(&Options::default());
+        let player_id = spawn(&mut headless, "alice");
+        tick(&mut headless, 20);
+
+        let path = std::env::temp_dir().join(format!("mid_save_{}.bin", std::process::id()));
+        let snapshot = headless.service().snapshot(headless.players());
+        snapshot.save(&path).unwrap();
+
+        // The next save is interrupted after writing half of the temporary file.
+        tick(&mut headless, 20);
+        let snapshot = headless.service().snapshot(headless.players());
+        let bytes = bitcode::encode(&snapshot).unwrap();
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, &bytes[..bytes.len() / 2]).unwrap();
+
+        let options = Options {
+            snapshot: Some(path.clone()),
+            restore: true,
+            ..Default::default()
+        };
+        let restored = arena(&options);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&temporary).unwrap();
+
+        // Restored from the previous snapshot, 20 ticks behind.
+        assert_eq!(
+            restored.service().world.singleton().tick.0 + 20,
+            headless.service().world.singleton().tick.0
+        );
+        assert!(restored.players().borrow_player(player_id).unwrap().alive);
+    }
+}
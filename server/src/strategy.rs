@@ -0,0 +1,420 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::pathfinder::PathMode;
+use common::tower::{Tower, TowerType};
+use common::unit::Unit;
+use core_protocol::id::PlayerId;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// How well bots play, chosen with `--bot-difficulty` or, per realm, by its creator or with
+/// `/botdifficulty`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// In order of how well they play, so the index is the level of a realm's bot difficulty.
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Chance of acting on a given tick.
+    pub fn act_chance(self) -> f64 {
+        match self {
+            Self::Easy => 0.25,
+            Self::Normal | Self::Hard => 1.0,
+        }
+    }
+
+    /// Whether research and ruler abilities are used.
+    pub fn uses_abilities(self) -> bool {
+        self != Self::Easy
+    }
+
+    /// Whether bots join the wars of their allied bots.
+    pub fn coordinates(self) -> bool {
+        self != Self::Easy
+    }
+
+    /// How many random towers are considered per tick. The one with the most units acts.
+    pub fn towers_considered(self) -> usize {
+        match self {
+            Self::Easy | Self::Normal => 1,
+            Self::Hard => 3,
+        }
+    }
+
+    /// Least total damage of a force that is sent against an adversary.
+    pub fn formidable_damage(self) -> u32 {
+        match self {
+            Self::Easy => 10,
+            Self::Normal => 5,
+            Self::Hard => 8,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        })
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.to_string().eq_ignore_ascii_case(s))
+            .ok_or("unknown difficulty (expected easy, normal or hard)")
+    }
+}
+
+/// Each bot has a personality, which picks the [`Strategy`] it plays with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Personality {
+    /// Stays small, fortifies and rarely goes to war.
+    Turtle,
+    /// Grabs as many towers as it can.
+    Expansionist,
+    /// Builds ships and fights over water.
+    Naval,
+    /// Builds nukes and other ranged weapons, and goes to war often.
+    Nuker,
+}
+
+impl Personality {
+    pub const ALL: [Self; 4] = [Self::Turtle, Self::Expansionist, Self::Naval, Self::Nuker];
+
+    /// The `n`th bot has the `n`th of `allowed` (wrapping around), so that they are evenly
+    /// distributed. Returns [`None`] if `allowed` is empty, which means any personality.
+    pub fn assigned(allowed: &[Self], player_id: PlayerId) -> Option<Self> {
+        let n = player_id.bot_number()?;
+        (!allowed.is_empty()).then(|| allowed[n % allowed.len()])
+    }
+
+    pub fn strategy(self) -> &'static dyn Strategy {
+        match self {
+            Self::Turtle => &Turtle,
+            Self::Expansionist => &Expansionist,
+            Self::Naval => &Naval,
+            Self::Nuker => &Nuker,
+        }
+    }
+}
+
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Turtle => "turtle",
+            Self::Expansionist => "expansionist",
+            Self::Naval => "naval",
+            Self::Nuker => "nuker",
+        })
+    }
+}
+
+impl FromStr for Personality {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.to_string().eq_ignore_ascii_case(s))
+            .ok_or("unknown personality (expected turtle, expansionist, naval or nuker)")
+    }
+}
+
+/// What a bot prioritizes. The defaults are those of the original (and only) bot.
+pub trait Strategy: Sync {
+    /// Range of how many towers the bot tries to accumulate.
+    fn territorial_ambition(&self) -> RangeInclusive<u8> {
+        8..=12
+    }
+
+    /// Relative likelihood of upgrading to `tower_type` (at least 1).
+    fn upgrade_weight(&self, tower_type: TowerType) -> u32 {
+        let _ = tower_type;
+        1
+    }
+
+    /// Percent of a tower's shield capacity it needs before being upgraded.
+    fn upgrade_shield_percent(&self, at_war: bool) -> usize {
+        if at_war {
+            100
+        } else {
+            50
+        }
+    }
+
+    /// Chance per tick of looking for a war.
+    fn war_chance(&self) -> f64 {
+        0.005
+    }
+
+    /// Chance per tick of looking for an alliance.
+    fn alliance_chance(&self) -> f64 {
+        0.0025
+    }
+
+    /// Whether to attack `tower` outside of war. `enemy` is its owner's tower count and score, if
+    /// it has an owner.
+    fn raid(
+        &self,
+        tower: &Tower,
+        enemy: Option<(usize, u32)>,
+        ambition: u8,
+        formidable: bool,
+    ) -> bool {
+        raid(tower, enemy, ambition, formidable)
+    }
+
+    /// Percent of capacity that interior towers keep frontier towers filled to with supply
+    /// lines, or [`None`] to not use supply lines.
+    fn supply_percent(&self) -> Option<u8> {
+        Some(75)
+    }
+
+    /// What paths of forces and supply lines prioritize.
+    fn path_mode(&self) -> PathMode {
+        PathMode::Safest
+    }
+}
+
+/// Default of [`Strategy::raid`].
+fn raid(tower: &Tower, enemy: Option<(usize, u32)>, ambition: u8, formidable: bool) -> bool {
+    enemy.map_or(true, |(towers, score)| {
+        // They're big; get em!
+        towers / 4 > ambition as usize
+            || score > 1000
+            // Don't do too much damage to smol's.
+            || !formidable
+            // Recently changed hands?
+            || tower.units.available(Unit::Shield) < 5
+    })
+}
+
+/// Returns true if `tower_type` generates any unit matching `filter`.
+fn generates(tower_type: TowerType, filter: impl Fn(Unit) -> bool) -> bool {
+    Unit::iter().any(|unit| filter(unit) && tower_type.unit_generation(unit).is_some())
+}
+
+/// See [`Personality::Turtle`].
+struct Turtle;
+
+impl Strategy for Turtle {
+    fn territorial_ambition(&self) -> RangeInclusive<u8> {
+        4..=6
+    }
+
+    fn upgrade_weight(&self, tower_type: TowerType) -> u32 {
+        let ranged = tower_type.ranged_distance().is_some() as u32;
+        1 + tower_type.raw_unit_capacity(Unit::Shield) as u32 / 10 + ranged * 4
+    }
+
+    fn upgrade_shield_percent(&self, _: bool) -> usize {
+        100
+    }
+
+    fn war_chance(&self) -> f64 {
+        0.001
+    }
+
+    fn alliance_chance(&self) -> f64 {
+        0.01
+    }
+
+    fn raid(&self, _: &Tower, enemy: Option<(usize, u32)>, _: u8, _: bool) -> bool {
+        // Only take what nobody owns.
+        enemy.is_none()
+    }
+
+    fn supply_percent(&self) -> Option<u8> {
+        Some(100)
+    }
+}
+
+/// See [`Personality::Expansionist`].
+struct Expansionist;
+
+impl Strategy for Expansionist {
+    fn territorial_ambition(&self) -> RangeInclusive<u8> {
+        16..=24
+    }
+
+    fn upgrade_weight(&self, tower_type: TowerType) -> u32 {
+        1 + generates(tower_type, Unit::can_capture) as u32 * 3
+    }
+
+    fn upgrade_shield_percent(&self, at_war: bool) -> usize {
+        if at_war {
+            75
+        } else {
+            25
+        }
+    }
+
+    fn supply_percent(&self) -> Option<u8> {
+        Some(50)
+    }
+
+    fn path_mode(&self) -> PathMode {
+        PathMode::Capture
+    }
+}
+
+/// See [`Personality::Naval`].
+struct Naval;
+
+impl Strategy for Naval {
+    fn upgrade_weight(&self, tower_type: TowerType) -> u32 {
+        let naval = tower_type.is_aquatic() || generates(tower_type, Unit::is_aquatic);
+        1 + naval as u32 * 4
+    }
+
+    fn raid(
+        &self,
+        tower: &Tower,
+        enemy: Option<(usize, u32)>,
+        ambition: u8,
+        formidable: bool,
+    ) -> bool {
+        // Water is theirs.
+        tower.tower_type.is_aquatic() || raid(tower, enemy, ambition, formidable)
+    }
+}
+
+/// See [`Personality::Nuker`].
+struct Nuker;
+
+impl Strategy for Nuker {
+    fn upgrade_weight(&self, tower_type: TowerType) -> u32 {
+        let ranged =
+            tower_type.ranged_distance().is_some() || generates(tower_type, Unit::is_ranged);
+        1 + ranged as u32 * 4
+    }
+
+    fn war_chance(&self) -> f64 {
+        0.01
+    }
+
+    fn path_mode(&self) -> PathMode {
+        // Get there before the nukes do.
+        PathMode::Fastest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strategy::{Difficulty, Personality};
+    use common::tower::{Tower, TowerType};
+    use common::unit::Unit;
+    use core_protocol::id::PlayerId;
+
+    #[test]
+    fn parse() {
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+        }
+        assert_eq!("Hard".parse(), Ok(Difficulty::Hard));
+        assert!("impossible".parse::<Difficulty>().is_err());
+
+        for personality in Personality::ALL {
+            assert_eq!(personality.to_string().parse(), Ok(personality));
+        }
+        assert_eq!("NAVAL".parse(), Ok(Personality::Naval));
+        assert!("pacifist".parse::<Personality>().is_err());
+    }
+
+    #[test]
+    fn assigned() {
+        use Personality::*;
+        let allowed = [Turtle, Naval];
+        let bot = |n| PlayerId::nth_bot(n).unwrap();
+
+        let assigned: Vec<_> = (0..4)
+            .map(|n| Personality::assigned(&allowed, bot(n)))
+            .collect();
+        assert_eq!(
+            assigned,
+            [Some(Turtle), Some(Naval), Some(Turtle), Some(Naval)]
+        );
+        assert_eq!(Personality::assigned(&[], bot(0)), None);
+        assert_eq!(
+            Personality::assigned(&allowed, PlayerId::SOLO_OFFLINE),
+            None
+        );
+    }
+
+    #[test]
+    fn raid() {
+        let mut village = Tower::with_type(TowerType::Village);
+        village.units.add(Unit::Shield, 10);
+        let mut buoy = Tower::with_type(TowerType::Buoy);
+        buoy.units.add(Unit::Shield, 5);
+        let small = Some((4, 100));
+        let big = Some((40, 100));
+
+        // Everyone takes what nobody owns.
+        for personality in Personality::ALL {
+            assert!(personality.strategy().raid(&village, None, 8, true));
+        }
+
+        let turtle = Personality::Turtle.strategy();
+        assert!(!turtle.raid(&village, big, 8, true));
+
+        let expansionist = Personality::Expansionist.strategy();
+        assert!(!expansionist.raid(&village, small, 8, true));
+        assert!(expansionist.raid(&village, big, 8, true));
+        assert!(expansionist.raid(&village, small, 8, false));
+        assert!(!expansionist.raid(&buoy, small, 8, true));
+
+        let naval = Personality::Naval.strategy();
+        assert!(!naval.raid(&village, small, 8, true));
+        assert!(naval.raid(&buoy, small, 8, true));
+    }
+
+    #[test]
+    fn priorities() {
+        use Personality::*;
+        let weights = |personality: Personality, better: TowerType| {
+            let strategy = personality.strategy();
+            (
+                strategy.upgrade_weight(better),
+                strategy.upgrade_weight(TowerType::Quarry),
+            )
+        };
+        for (personality, better) in [
+            (Turtle, TowerType::Bunker),
+            (Expansionist, TowerType::Barracks),
+            (Naval, TowerType::Dock),
+            (Nuker, TowerType::Artillery),
+        ] {
+            let (better_weight, quarry_weight) = weights(personality, better);
+            assert!(better_weight > quarry_weight, "{personality} {better:?}");
+        }
+
+        let ambition = |personality: Personality| personality.strategy().territorial_ambition();
+        assert!(ambition(Turtle).end() < ambition(Naval).start());
+        assert!(ambition(Naval).end() < ambition(Expansionist).start());
+
+        let war_chance = |personality: Personality| personality.strategy().war_chance();
+        assert!(war_chance(Turtle) < war_chance(Naval));
+        assert!(war_chance(Naval) < war_chance(Nuker));
+
+        assert!(Difficulty::Easy.act_chance() < Difficulty::Hard.act_chance());
+        assert!(Difficulty::Easy.towers_considered() < Difficulty::Hard.towers_considered());
+        assert!(!Difficulty::Easy.coordinates());
+        assert!(Difficulty::Hard.coordinates());
+    }
+}
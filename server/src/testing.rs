@@ -0,0 +1,88 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Helpers for testing a [`TowerService`] in a [`Headless`] arena, with real players instead of
+//! bots.
+
+use crate::options::Options;
+use crate::service::TowerService;
+use common::protocol::Command;
+use common::tower::TowerId;
+use core_protocol::id::{PlayerId, TeamId};
+use core_protocol::name::{PlayerAlias, TeamName};
+use core_protocol::rpc::{TeamRequest, TeamUpdate};
+use game_server::headless::Headless;
+
+/// An arena without bots.
+pub(crate) fn arena(options: &Options) -> Headless<TowerService> {
+    Headless::new(0, options)
+}
+
+/// Adds a real player and spawns them.
+pub(crate) fn spawn(headless: &mut Headless<TowerService>, alias: &str) -> PlayerId {
+    let player_id = headless.join(PlayerAlias::new_unsanitized(alias));
+    headless.command(player_id, Command::Spawn);
+    for _ in 0..10 {
+        headless.tick();
+        if headless.players().borrow_player(player_id).unwrap().alive {
+            return player_id;
+        }
+    }
+    panic!("{alias} didn't spawn");
+}
+
+/// Puts `members` on a new team led by `captain`, and ticks so the world knows.
+pub(crate) fn team(
+    headless: &mut Headless<TowerService>,
+    captain: PlayerId,
+    members: &[PlayerId],
+) -> TeamId {
+    let name = TeamName::new_sanitized("team");
+    let Ok(TeamUpdate::Created(team_id, _)) =
+        headless.team_request(captain, TeamRequest::Create(name))
+    else {
+        panic!("couldn't create team");
+    };
+    for &member in members {
+        headless
+            .team_request(member, TeamRequest::Join(team_id))
+            .unwrap();
+        headless
+            .team_request(captain, TeamRequest::Accept(member))
+            .unwrap();
+    }
+    headless.tick();
+    team_id
+}
+
+/// Makes `a` and `b` allies, and ticks so the world knows.
+pub(crate) fn ally(headless: &mut Headless<TowerService>, a: PlayerId, b: PlayerId) {
+    for (player_id, with) in [(a, b), (b, a)] {
+        headless.command(
+            player_id,
+            Command::Alliance {
+                with,
+                break_alliance: false,
+                minutes: None,
+            },
+        );
+    }
+    headless.tick();
+    assert!(headless.service().world.have_alliance(a, b));
+}
+
+/// Who owns a tower, if anyone.
+pub(crate) fn owner(headless: &Headless<TowerService>, tower_id: TowerId) -> Option<PlayerId> {
+    headless
+        .service()
+        .world
+        .chunk
+        .get(tower_id)
+        .and_then(|tower| tower.player_id)
+}
+
+pub(crate) fn tick(headless: &mut Headless<TowerService>, ticks: usize) {
+    for _ in 0..ticks {
+        headless.tick();
+    }
+}
@@ -1,21 +1,29 @@
 // SPDX-FileCopyrightText: 2023 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::service::PlayerData;
 use crate::TowerService;
 use common::alerts::{AlertFlag, Alerts};
+use common::alliance::AllianceRequest;
 use common::chunk::{ChunkId, ChunkInput, ChunkMaintenance, RelativeTowerId};
-use common::force::Path;
+use common::force::{Deployment, Path};
 use common::info::InfoEvent;
+use common::pathfinder::PathMode;
 use common::player::{PlayerInput, PlayerMaintainance};
+use common::protocol::Spectate;
+use common::research::Research;
+use common::ruler::RulerAbility;
+use common::supply_line::SupplyLine;
 use common::ticks::Ticks;
-use common::tower::{TowerId, TowerSet, TowerType};
+use common::tower::{Tower, TowerId, TowerSet, TowerType};
+use common::unit::{Unit, UnitArray};
+use common::units::Units;
 use common::world::{World, WorldChunks};
 use common_util::x_vec2::U16Vec2;
 use core_protocol::id::PlayerId;
 use fxhash::{FxHashMap, FxHashSet};
 use game_server::player::PlayerRepo;
 use glam::IVec2;
-use rand::thread_rng;
 use std::collections::VecDeque;
 use std::time::Instant;
 
@@ -45,7 +53,6 @@ impl TowerService {
         // In towers.
         let mut search_radius = (search_area as f32 * (1.0 / std::f32::consts::PI)).sqrt() as u16;
 
-        let mut rng = thread_rng();
         let result = loop {
             if governor == 0 {
                 println!(
@@ -59,12 +66,12 @@ impl TowerService {
 
             let tower_id = TowerId(
                 U16Vec2::try_from(
-                    (common_util::range::gen_radius(&mut rng, search_radius as f32)
-                        + World::CENTER.0.as_vec2()
+                    (common_util::range::gen_radius(&mut self.rng, search_radius as f32)
+                        + World::center().0.as_vec2()
                         + 0.5)
                         .floor()
                         .as_ivec2()
-                        .clamp(IVec2::ZERO, IVec2::splat(WorldChunks::SIZE as i32 - 1))
+                        .clamp(IVec2::ZERO, IVec2::splat(WorldChunks::size() as i32 - 1))
                         .as_uvec2(),
                 )
                 .unwrap(),
@@ -83,6 +90,7 @@ impl TowerService {
                 player.death_reason = None;
                 player.score = 0;
                 player.alerts = Alerts::default();
+                player.spectate = None;
 
                 break Ok(tower_id);
             }
@@ -136,28 +144,111 @@ impl TowerService {
         Ok(())
     }
 
+    pub fn spectate(
+        &mut self,
+        player_id: PlayerId,
+        spectate: Option<Spectate>,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let Some(mut player) = players.borrow_player_mut(player_id) else {
+            return Err("player not in game");
+        };
+        if player.is_bot() {
+            return Err("bots can't spectate");
+        }
+        if player.alive {
+            return Err("can't spectate while alive");
+        }
+        if player.spectate_cooldown != Ticks::ZERO {
+            return Err("rate limited");
+        }
+        if let Some(Spectate::Player(followed)) = spectate
+            && (followed == player_id || !self.regulator.active(followed))
+        {
+            return Err("can't follow player");
+        }
+
+        player.spectate = spectate;
+        player.spectate_cooldown = Ticks::from_whole_secs(1);
+        Ok(())
+    }
+
+    /// How long a player can't request alliances after breaking one (but can still accept them).
+    const ALLIANCE_COOLDOWN: Ticks = Ticks::from_whole_secs(60);
+
+    /// Requests an alliance, or accepts one that `with` requested. Breaking an alliance that
+    /// `with` requested declines it, and breaking an established alliance starts a cooldown.
     pub fn alliance(
         &mut self,
         player_id: PlayerId,
         with: PlayerId,
         break_alliance: bool,
+        minutes: Option<u8>,
         players: &PlayerRepo<Self>,
     ) -> Result<(), &'static str> {
+        if player_id == with {
+            return Err("alliance with self");
+        }
+        if !(self.regulator.active(player_id) && self.regulator.active(with)) {
+            return Err("alliance with inactive player");
+        }
+        if minutes.is_some_and(|m| !(1..=AllianceRequest::MAX_MINUTES).contains(&m)) {
+            return Err("invalid minutes");
+        }
+
         // TODO visible to player?
         let Some(mut player) = players.borrow_player_mut(player_id) else {
             return Err("non-existent player");
         };
-        let _a = &mut player.alerts;
-        // TODO Add AlertFlag::MadeAlliance.
-        drop(player);
+        let requested = self.world.player(player_id).allies.contains(&with);
+        let requested_by = self.world.player(with).allies.contains(&player_id);
+        if !break_alliance {
+            if requested {
+                return Err("alliance already requested");
+            }
+            if player.alliance_cooldown != Ticks::ZERO && !requested_by {
+                return Err("alliance cooldown");
+            }
+        }
 
-        if !(self.regulator.active(player_id) && self.regulator.active(with)) {
-            return Err("alliance with inactive player");
+        let established = requested && requested_by;
+        let new_alliance = !break_alliance && requested_by;
+        if break_alliance {
+            player.alliance_expiries.remove(&with);
+            if established {
+                player.alliance_cooldown = Self::ALLIANCE_COOLDOWN;
+                player.alliance_alerts |= AlertFlag::BrokeAlliance;
+            }
+        } else if new_alliance {
+            player.alliance_alerts |= AlertFlag::MadeAlliance;
+        } else if let Some(minutes) = minutes {
+            player.alliance_minutes.insert(with, minutes);
+        } else {
+            player.alliance_minutes.remove(&with);
         }
+        drop(player);
 
-        let new_alliance = !break_alliance
-            && !self.world.player(player_id).allies.contains(&with)
-            && self.world.player(with).allies.contains(&player_id);
+        if let Some(mut other) = players.borrow_player_mut(with) {
+            if break_alliance {
+                other.alliance_expiries.remove(&player_id);
+                if established {
+                    other.alliance_alerts |= AlertFlag::BrokeAlliance;
+                }
+            } else if new_alliance {
+                other.alliance_alerts |= AlertFlag::MadeAlliance;
+                // The requester decides how long the alliance lasts.
+                if let Some(minutes) = other.alliance_minutes.remove(&player_id) {
+                    let seconds = minutes as u16 * 60;
+                    other.alliance_expiries.insert(player_id, seconds);
+                    drop(other);
+                    if let Some(mut player) = players.borrow_player_mut(player_id) {
+                        player.alliance_expiries.insert(with, seconds);
+                    }
+                }
+            } else {
+                other.alliance_alerts |= AlertFlag::AllianceRequested;
+            }
+        }
 
         if new_alliance {
             for (a, b) in [(player_id, with), (with, player_id)] {
@@ -190,11 +281,56 @@ impl TowerService {
         Ok(())
     }
 
+    /// Counts down timed alliances, breaking the ones that expired. Must be called once per
+    /// second, when inputs can be dispatched.
+    pub(crate) fn expire_alliances(&mut self, players: &PlayerRepo<Self>) {
+        let world = &self.world;
+        let requested = |a: PlayerId, b: PlayerId| {
+            world.player.contains_key(&a)
+                && world.player.contains_key(&b)
+                && world.player(a).allies.contains(&b)
+        };
+
+        let mut expired = Vec::new();
+        for mut player in players.iter_borrow_mut() {
+            let player_id = player.player_id;
+            // Forget requests and alliances that ended some other way, such as by dying.
+            player
+                .alliance_minutes
+                .retain(|&with, _| requested(player_id, with) && !requested(with, player_id));
+            player.alliance_expiries.retain(|&with, seconds| {
+                if !(requested(player_id, with) && requested(with, player_id)) {
+                    return false;
+                }
+                *seconds = seconds.saturating_sub(1);
+                if *seconds == 0 && player_id < with {
+                    expired.push((player_id, with));
+                }
+                *seconds > 0
+            });
+        }
+
+        for (a, b) in expired {
+            for (player_id, with) in [(a, b), (b, a)] {
+                self.world.dispatch_player_input(
+                    player_id,
+                    PlayerInput::RemoveAlly(with),
+                    Self::on_info_event(players, |_| unreachable!()),
+                );
+                if let Some(mut player) = players.borrow_player_mut(player_id) {
+                    player.alliance_alerts |= AlertFlag::BrokeAlliance;
+                }
+            }
+        }
+    }
+
     pub fn deploy_force(
         &mut self,
         player_id: PlayerId,
         tower_id: TowerId,
         path: Path,
+        legs: Vec<Path>,
+        deployment: Deployment,
         players: &PlayerRepo<Self>,
     ) -> Result<(), &'static str> {
         let tower = self.world.chunk.get(tower_id).ok_or("no tower")?;
@@ -202,15 +338,41 @@ impl TowerService {
             return Err("source not under player's control");
         }
 
-        let strength = tower.force_units();
-        if strength.is_empty() {
-            return Err("empty force");
-        }
+        let strength = tower.deployment_units(&deployment)?;
 
         // Always some since strength isn't empty.
         let max_edge_distance = strength.max_edge_distance();
         let path = path.validate(&self.world.chunk, tower_id, max_edge_distance)?;
 
+        // Forces that reach an ally's tower reinforce it, but rulers can't be given away.
+        if strength.contains(Unit::Ruler)
+            && let Some(destination_player_id) = self
+                .world
+                .chunk
+                .get(path.destination())
+                .and_then(|t| t.player_id)
+            && destination_player_id != player_id
+            && self.world.have_alliance(player_id, destination_player_id)
+        {
+            return Err("can't send ruler to ally");
+        }
+
+        if !legs.is_empty() && max_edge_distance.is_some() {
+            return Err("ranged force can't have legs");
+        }
+        if legs.len() >= World::MAX_PATH_LEGS {
+            return Err("too many legs");
+        }
+        let mut leg_source = path.destination();
+        let legs = legs
+            .into_iter()
+            .map(|leg| {
+                let leg = leg.validate(&self.world.chunk, leg_source, None)?;
+                leg_source = leg.destination();
+                Ok(leg)
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+
         if !player_id.is_bot() {
             let mut player = players.borrow_player_mut(player_id).ok_or_else(|| {
                 debug_assert!(false, "missing player in deploy force");
@@ -223,7 +385,12 @@ impl TowerService {
         let (chunk_id, tower_id) = tower_id.split();
         self.world.dispatch_chunk_input(
             chunk_id,
-            ChunkInput::DeployForce { tower_id, path },
+            ChunkInput::DeployForce {
+                tower_id,
+                path,
+                legs,
+                deployment,
+            },
             Self::on_info_event(players, |player_id| {
                 debug_assert!(
                     false,
@@ -236,12 +403,72 @@ impl TowerService {
         Ok(())
     }
 
+    /// Continues a waypoint order that broke at `tower_id`, by finding a new route to its
+    /// `destination` for the `units` that were following it (as many of them as are still there).
+    pub fn replan_waypoints(
+        &mut self,
+        player_id: PlayerId,
+        tower_id: TowerId,
+        destination: TowerId,
+        units: &UnitArray<u8>,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let tower = self.world.chunk.get(tower_id).ok_or("no tower")?;
+        if tower.player_id != Some(player_id) {
+            return Err("source not under player's control");
+        }
+        // Never put the ruler in danger without being told to.
+        let available = tower.force_units();
+        let mut strength = Units::default();
+        for (unit, &count) in units.iter() {
+            if unit != Unit::Ruler {
+                strength.add(unit, (count as usize).min(available.available(unit)));
+            }
+        }
+        if strength.is_empty() {
+            return Err("no units");
+        }
+        let max_edge_distance = strength.max_edge_distance();
+
+        // The original route broke, likely because of enemies, so go around them.
+        let path = self
+            .world
+            .find_best_path(
+                tower_id,
+                destination,
+                max_edge_distance,
+                player_id,
+                PathMode::Safest,
+                |_| true,
+            )
+            .ok_or("no path")?;
+        let mut legs = Path::new_legs(path);
+        if legs.len() > World::MAX_PATH_LEGS {
+            return Err("route too long");
+        }
+        let path = legs.remove(0);
+        let deployment = Deployment::Units(strength);
+        self.deploy_force(player_id, tower_id, path, legs, deployment, players)
+    }
+
+    /// Replaces all of a tower's supply lines with `path`, or removes them if [`None`].
     pub fn set_supply_line(
         &mut self,
         player_id: PlayerId,
         tower_id: TowerId,
         path: Option<Path>,
         players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let supply_lines = path.map(SupplyLine::new).into_iter().collect();
+        self.set_supply_lines(player_id, tower_id, supply_lines, players)
+    }
+
+    pub fn set_supply_lines(
+        &mut self,
+        player_id: PlayerId,
+        tower_id: TowerId,
+        supply_lines: Vec<SupplyLine>,
+        players: &PlayerRepo<Self>,
     ) -> Result<(), &'static str> {
         let tower = self.world.chunk.get(tower_id).ok_or("no tower")?;
         if tower.player_id != Some(player_id) {
@@ -251,12 +478,29 @@ impl TowerService {
         if !tower.generates_mobile_units() {
             return Err("invalid supply line");
         }
+        if supply_lines.len() > Tower::MAX_SUPPLY_LINES {
+            return Err("too many supply lines");
+        }
 
         let max_edge_distance = tower.tower_type.ranged_distance();
-        let path = path
-            .map(|p| p.validate(&self.world.chunk, tower_id, max_edge_distance))
-            .transpose()?
-            .filter(|p| Some(p) != tower.supply_line.as_ref());
+        let mut validated: Vec<SupplyLine> = Vec::with_capacity(supply_lines.len());
+        for supply_line in supply_lines {
+            supply_line.validate()?;
+            let path = supply_line
+                .path
+                .validate(&self.world.chunk, tower_id, max_edge_distance)?;
+            if validated.iter().any(|v| v.path == path) {
+                return Err("duplicate supply line");
+            }
+            validated.push(SupplyLine {
+                path,
+                // Recalculated next tick.
+                paused: false,
+                halted: false,
+                ..supply_line
+            });
+        }
+        let supply_lines = validated;
 
         if !player_id.is_bot() {
             let mut player = players.borrow_player_mut(player_id).ok_or_else(|| {
@@ -267,7 +511,7 @@ impl TowerService {
             let a = &mut player.alerts;
             a.set_flags(
                 a.flags()
-                    | if path.is_some() {
+                    | if !supply_lines.is_empty() {
                         AlertFlag::SetAnySupplyLine
                     } else {
                         AlertFlag::UnsetAnySupplyLine
@@ -278,7 +522,10 @@ impl TowerService {
         let (chunk_id, tower_id) = tower_id.split();
         self.world.dispatch_chunk_input(
             chunk_id,
-            ChunkInput::SetSupplyLine { tower_id, path },
+            ChunkInput::SetSupplyLines {
+                tower_id,
+                supply_lines,
+            },
             |info| {
                 debug_assert!(false, "expected no info: {info:?}");
             },
@@ -323,6 +570,7 @@ impl TowerService {
         } else if tower.tower_type.basis() != upgrade {
             return Err("invalid upgrade path");
         }
+        player.sensor_coverage_stale = true;
 
         drop(player);
 
@@ -345,6 +593,242 @@ impl TowerService {
         Ok(())
     }
 
+    /// Uses a ruler ability, which must have a `target` if [`RulerAbility::has_target`].
+    pub fn use_ruler_ability(
+        &mut self,
+        player_id: PlayerId,
+        ability: RulerAbility,
+        target: Option<TowerId>,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let player = players
+            .borrow_player(player_id)
+            .ok_or("player not in game")?;
+        if !player.alive {
+            return Err("not alive");
+        }
+        if !self.world.player(player_id).can_use_ability(ability) {
+            return Err("ability on cooldown");
+        }
+        if target.is_some() != ability.has_target() {
+            return Err("invalid target");
+        }
+        let ruler_id = self.ruler_tower(&player.data).ok_or("ruler not in tower")?;
+
+        match ability {
+            RulerAbility::Teleport => {
+                drop(player);
+                self.world.teleport_ruler(
+                    player_id,
+                    ruler_id,
+                    target.unwrap(),
+                    Self::on_info_event(players, |player_id| {
+                        debug_assert!(
+                            false,
+                            "teleporting should not have killed player {:?}",
+                            player_id
+                        );
+                    }),
+                )?;
+            }
+            RulerAbility::ShieldSurge => {
+                drop(player);
+                let (chunk_id, tower_id) = ruler_id.split();
+                self.world.dispatch_chunk_input(
+                    chunk_id,
+                    ChunkInput::ShieldSurge { tower_id },
+                    Self::on_info_event(players, |player_id| {
+                        debug_assert!(
+                            false,
+                            "surging shields should not have killed player {:?}",
+                            player_id
+                        );
+                    }),
+                );
+            }
+            RulerAbility::Rally => {
+                let sources: Vec<TowerId> = player
+                    .towers
+                    .iter()
+                    .copied()
+                    .filter(|&tower_id| {
+                        tower_id != ruler_id
+                            && tower_id.distance(ruler_id) <= RulerAbility::RALLY_DISTANCE
+                    })
+                    .collect();
+                drop(player);
+
+                let mut rallied = 0;
+                for tower_id in sources {
+                    let Some(tower) = self.world.chunk.get(tower_id) else {
+                        continue;
+                    };
+                    let strength = tower.force_units();
+                    if strength.is_empty() || strength.max_edge_distance().is_some() {
+                        // Ranged units would attack the ruler's tower.
+                        continue;
+                    }
+                    let Some(path) = self
+                        .world
+                        .find_best_path(
+                            tower_id,
+                            ruler_id,
+                            None,
+                            player_id,
+                            PathMode::Fastest,
+                            |tower_id| {
+                                self.world
+                                    .chunk
+                                    .get(tower_id)
+                                    .map_or(false, |t| t.player_id == Some(player_id))
+                            },
+                        )
+                        .filter(|path| path.len() <= World::MAX_PATH_ROADS)
+                    else {
+                        continue;
+                    };
+                    let deployment = Deployment::Units(strength);
+                    if self
+                        .deploy_force(
+                            player_id,
+                            tower_id,
+                            Path::new(path),
+                            Vec::new(),
+                            deployment,
+                            players,
+                        )
+                        .is_ok()
+                    {
+                        rallied += 1;
+                    }
+                }
+                if rallied == 0 {
+                    return Err("no units to rally");
+                }
+            }
+        }
+
+        self.world.dispatch_player_input(
+            player_id,
+            PlayerInput::UsedAbility(ability),
+            Self::on_info_event(players, |_| unreachable!()),
+        );
+        Ok(())
+    }
+
+    /// Spends research points on the next level of `research`.
+    pub fn research(
+        &mut self,
+        player_id: PlayerId,
+        research: Research,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let player = players
+            .borrow_player(player_id)
+            .ok_or("player not in game")?;
+        if !player.alive {
+            return Err("not alive");
+        }
+        drop(player);
+
+        let actor = self.world.player(player_id);
+        if actor.research[research] >= research.max_level() {
+            return Err("already fully researched");
+        }
+        if !actor.can_research(research) {
+            return Err("not enough research points");
+        }
+
+        self.world.dispatch_player_input(
+            player_id,
+            PlayerInput::Research(research),
+            Self::on_info_event(players, |_| unreachable!()),
+        );
+        Ok(())
+    }
+
+    /// Gives every player a research point per active lab. Called every
+    /// [`Research::POINTS_PERIOD`].
+    pub(crate) fn produce_research_points(&mut self, players: &PlayerRepo<Self>) {
+        let points: Vec<(PlayerId, u16)> = players
+            .iter_borrow()
+            .filter(|player| player.data.alive)
+            .filter_map(|player| {
+                // Tower counts are only updated every second, so check each lab is active now.
+                let labs = player
+                    .data
+                    .towers
+                    .iter()
+                    .filter_map(|&tower_id| self.world.chunk.get(tower_id))
+                    .filter(|tower| tower.tower_type == TowerType::Lab && tower.active())
+                    .count();
+                (labs != 0).then_some((player.player_id, labs as u16))
+            })
+            .collect();
+
+        for (player_id, points) in points {
+            self.world.dispatch_player_input(
+                player_id,
+                PlayerInput::AddResearchPoints(points),
+                Self::on_info_event(players, |_| unreachable!()),
+            );
+        }
+    }
+
+    /// Where the player's ruler is, if it is in one of their towers.
+    pub(crate) fn ruler_tower(&self, player: &PlayerData) -> Option<TowerId> {
+        player.towers.iter().copied().find(|&tower_id| {
+            self.world
+                .chunk
+                .get(tower_id)
+                .map_or(false, |tower| tower.units.has_ruler())
+        })
+    }
+
+    /// Gives a tower to an ally, who also gets its units (except shields).
+    pub fn transfer_tower(
+        &mut self,
+        player_id: PlayerId,
+        tower_id: TowerId,
+        to: PlayerId,
+        players: &PlayerRepo<Self>,
+    ) -> Result<(), &'static str> {
+        let tower = self.world.chunk.get(tower_id).ok_or("no tower")?;
+        if tower.player_id != Some(player_id) {
+            return Err("cannot transfer tower not owned");
+        }
+        if tower.units.has_ruler() {
+            return Err("cannot transfer ruler");
+        }
+        if to == player_id || !self.regulator.active(to) {
+            return Err("invalid recipient");
+        }
+        if !players.borrow_player(to).map_or(false, |p| p.alive) {
+            return Err("recipient not alive");
+        }
+        if !self.world.have_alliance(player_id, to) {
+            return Err("recipient not an ally");
+        }
+
+        let (chunk_id, tower_id) = tower_id.split();
+        self.world.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::TransferTower {
+                tower_id,
+                player_id: to,
+            },
+            Self::on_info_event(players, |player_id| {
+                debug_assert!(
+                    false,
+                    "transferring tower should not have killed player {:?}",
+                    player_id
+                );
+            }),
+        );
+
+        Ok(())
+    }
+
     /// # Panics
     ///
     /// If player wasn't passed in and doesn't exist.
@@ -406,10 +890,46 @@ impl TowerService {
         );
     }
 
+    /// Gives a player's [`TeamId`] to their [`Player`] actor, making them permanent allies with the
+    /// rest of their team.
+    ///
+    /// [`TeamId`]: core_protocol::id::TeamId
+    /// [`Player`]: common::player::Player
+    pub fn change_team(&mut self, player_id: PlayerId, players: &PlayerRepo<Self>) {
+        // The player may have left since.
+        if !self.world.player.contains_key(&player_id) {
+            return;
+        }
+        let team_id = players
+            .borrow_player(player_id)
+            .filter(|player| player.alive)
+            .and_then(|player| player.team_id());
+        if self.world.player(player_id).team_id == team_id {
+            return;
+        }
+
+        let mut on_info = Self::on_info_event(players, |_| unreachable!());
+        self.world.dispatch_player_maintenance(
+            player_id,
+            PlayerMaintainance::SetTeam(team_id),
+            &mut on_info,
+        );
+
+        // Like a new alliance, halts forces and supply lines headed for each other's towers.
+        for teammate_id in self.world.teammates(player_id).collect::<Vec<_>>() {
+            for (a, b) in [(player_id, teammate_id), (teammate_id, player_id)] {
+                self.world.dispatch_player_maintenance(
+                    a,
+                    PlayerMaintainance::NewTeammate(b),
+                    &mut on_info,
+                );
+            }
+        }
+    }
+
     /// Removes towers if there are too many.
     pub fn shrink(&mut self, players: &PlayerRepo<Self>) {
-        // TODO don't allocate max world size.
-        let mut locked = TowerSet::with_bounds(WorldChunks::RECTANGLE);
+        let mut locked = TowerSet::with_bounds(WorldChunks::rectangle());
         for (tower_id, tower) in self.world.chunk.iter_towers() {
             if !tower.can_destroy() {
                 let mut t = tower_id;
@@ -620,3 +1140,349 @@ fn group(tower_ids: impl IntoIterator<Item = TowerId>) -> FxHashMap<ChunkId, Vec
     }
     chunk_map
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::service::TowerService;
+    use crate::testing::{ally, arena, owner, spawn, team, tick};
+    use common::alerts::AlertFlag;
+    use common::chunk::ChunkInput;
+    use common::force::{Force, Path};
+    use common::protocol::Command;
+    use common::ruler::RulerAbility;
+    use common::ticks::Ticks;
+    use common::tower::TowerId;
+    use common::unit::Unit;
+    use common::units::Units;
+    use core_protocol::id::PlayerId;
+    use core_protocol::rpc::TeamRequest;
+    use game_server::headless::Headless;
+
+    fn ruler_tower(headless: &Headless<TowerService>, player_id: PlayerId) -> TowerId {
+        let player = headless.players().borrow_player(player_id).unwrap();
+        headless.service().ruler_tower(&player.data).unwrap()
+    }
+
+    /// A neighbor of `tower_id` that no one owns or defends.
+    fn empty_neighbor(
+        headless: &Headless<TowerService>,
+        tower_id: TowerId,
+        except: &[TowerId],
+    ) -> TowerId {
+        let chunk = &headless.service().world.chunk;
+        tower_id
+            .neighbors()
+            .find(|tower_id| {
+                !except.contains(tower_id)
+                    && chunk
+                        .get(*tower_id)
+                        .is_some_and(|tower| tower.player_id.is_none() && tower.units.is_empty())
+            })
+            .unwrap()
+    }
+
+    fn alliance(
+        headless: &mut Headless<TowerService>,
+        player_id: PlayerId,
+        with: PlayerId,
+        break_alliance: bool,
+        minutes: Option<u8>,
+    ) {
+        headless.command(
+            player_id,
+            Command::Alliance {
+                with,
+                break_alliance,
+                minutes,
+            },
+        );
+    }
+
+    fn add_inbound_force(headless: &mut Headless<TowerService>, force: Force) {
+        let (chunk_id, tower_id) = force.current_destination().split();
+        headless.service_mut().world.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::AddInboundForce { tower_id, force },
+            |_| {},
+        );
+    }
+
+    #[test]
+    fn form_and_leave_team() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob, carol] =
+            ["alice", "bob", "carol"].map(|alias| spawn(&mut headless, alias));
+        team(&mut headless, alice, &[bob]);
+
+        let world = &headless.service().world;
+        assert!(world.are_teammates(alice, bob));
+        assert!(world.have_alliance(alice, bob));
+        assert!(!world.are_teammates(alice, carol));
+        assert_eq!(world.teammates(alice).collect::<Vec<_>>(), vec![bob]);
+
+        headless.team_request(bob, TeamRequest::Leave).unwrap();
+        tick(&mut headless, 1);
+
+        let world = &headless.service().world;
+        assert!(!world.are_teammates(alice, bob));
+        assert!(!world.have_alliance(alice, bob));
+        assert_eq!(world.teammates(alice).count(), 0);
+    }
+
+    #[test]
+    fn pass_through_teammate() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+        team(&mut headless, alice, &[bob]);
+
+        // Send a force of alice's through bob's ruler tower, to an empty tower beyond it.
+        let bob_data = headless.players().borrow_player(bob).unwrap();
+        let through = headless.service().ruler_tower(&bob_data.data).unwrap();
+        drop(bob_data);
+        let service = headless.service_mut();
+        let dst = through
+            .neighbors()
+            .find(|&tower_id| {
+                service
+                    .world
+                    .chunk
+                    .get(tower_id)
+                    .is_some_and(|tower| tower.player_id.is_none() && tower.units.is_empty())
+            })
+            .unwrap();
+        let src = through
+            .neighbors()
+            .find(|&tower_id| tower_id != dst && service.world.chunk.get(tower_id).is_some())
+            .unwrap();
+
+        let mut units = Units::default();
+        units.add(Unit::Soldier, 10);
+        let force = Force::new(alice, units, Path::new(vec![src, through, dst]));
+        let (chunk_id, tower_id) = through.split();
+        service.world.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::AddInboundForce { tower_id, force },
+            |_| {},
+        );
+
+        for _ in 0..100 {
+            tick(&mut headless, 1);
+            if owner(&headless, dst).is_some() {
+                break;
+            }
+        }
+        assert_eq!(owner(&headless, dst), Some(alice));
+        assert_eq!(owner(&headless, through), Some(bob));
+    }
+
+    #[test]
+    fn replan_arriving_units() {
+        let mut headless = arena(&Options::default());
+        let alice = spawn(&mut headless, "alice");
+        // Let the soldiers sent out when spawning arrive.
+        tick(&mut headless, 100);
+
+        let stop = ruler_tower(&headless, alice);
+        let mut neighbors = stop
+            .neighbors()
+            .filter(|&tower_id| owner(&headless, tower_id) == Some(alice));
+        let (src, dst) = (neighbors.next().unwrap(), neighbors.next().unwrap());
+
+        // The next leg doesn't start where the force stops, so the order is broken.
+        let mut units = Units::default();
+        units.add(Unit::Soldier, 2);
+        let force = Force::new(alice, units.clone(), Path::new(vec![src, stop]))
+            .with_legs(vec![Path::new(vec![src, dst])]);
+        add_inbound_force(&mut headless, force);
+
+        for _ in 0..100 {
+            tick(&mut headless, 1);
+            let tower = headless.service().world.chunk.get(stop).unwrap();
+            if let Some(force) = tower.outbound_forces.first() {
+                // Only the units that were following the order continue, never the ruler.
+                assert_eq!(force.units, units);
+                assert_eq!(force.path().destination(), dst);
+                assert!(tower.units.has_ruler());
+                return;
+            }
+        }
+        panic!("waypoints weren't re-planned");
+    }
+
+    #[test]
+    fn waypoints_through_neutral_and_ally() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+        ally(&mut headless, alice, bob);
+        // Let the soldiers sent out when spawning arrive.
+        tick(&mut headless, 100);
+
+        // Stop at an empty tower, then at one of bob's towers, then end beyond it.
+        let src = ruler_tower(&headless, bob);
+        let through = src
+            .neighbors()
+            .find(|&tower_id| owner(&headless, tower_id) == Some(bob))
+            .unwrap();
+        let neutral = empty_neighbor(&headless, through, &[]);
+        let dst = empty_neighbor(&headless, through, &[neutral]);
+
+        let mut units = Units::default();
+        units.add(Unit::Soldier, 10);
+        let force = Force::new(alice, units, Path::new(vec![src, neutral])).with_legs(vec![
+            Path::new(vec![neutral, through]),
+            Path::new(vec![through, dst]),
+        ]);
+        add_inbound_force(&mut headless, force);
+
+        for _ in 0..200 {
+            tick(&mut headless, 1);
+            if owner(&headless, dst).is_some() {
+                break;
+            }
+        }
+        assert_eq!(owner(&headless, neutral), Some(alice));
+        assert_eq!(owner(&headless, through), Some(bob));
+        assert_eq!(owner(&headless, dst), Some(alice));
+    }
+
+    #[test]
+    fn request_and_accept_alliance() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+
+        alliance(&mut headless, alice, bob, false, None);
+        assert!(!headless.service().world.have_alliance(alice, bob));
+        let bob_data = headless.players().borrow_player(bob).unwrap();
+        assert!(bob_data
+            .alliance_alerts
+            .contains(AlertFlag::AllianceRequested));
+        drop(bob_data);
+
+        alliance(&mut headless, bob, alice, false, None);
+        assert!(headless.service().world.have_alliance(alice, bob));
+        for player_id in [alice, bob] {
+            let player = headless.players().borrow_player(player_id).unwrap();
+            assert!(player.alliance_alerts.contains(AlertFlag::MadeAlliance));
+        }
+    }
+
+    #[test]
+    fn alliance_expiry() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+
+        alliance(&mut headless, alice, bob, false, Some(1));
+        alliance(&mut headless, bob, alice, false, None);
+        assert!(headless.service().world.have_alliance(alice, bob));
+
+        tick(&mut headless, Ticks::from_whole_secs(30).0 as usize);
+        assert!(headless.service().world.have_alliance(alice, bob));
+
+        tick(&mut headless, Ticks::from_whole_secs(32).0 as usize);
+        let world = &headless.service().world;
+        assert!(!world.have_alliance(alice, bob));
+        assert!(!world.player(alice).allies.contains(&bob));
+        assert!(!world.player(bob).allies.contains(&alice));
+        for player_id in [alice, bob] {
+            let player = headless.players().borrow_player(player_id).unwrap();
+            assert!(player.alliance_expiries.is_empty());
+        }
+    }
+
+    #[test]
+    fn alliance_cooldown() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob, carol] =
+            ["alice", "bob", "carol"].map(|alias| spawn(&mut headless, alias));
+        ally(&mut headless, alice, bob);
+
+        alliance(&mut headless, alice, bob, true, None);
+        assert!(!headless.service().world.have_alliance(alice, bob));
+
+        // Can't request another alliance right after breaking one.
+        alliance(&mut headless, alice, carol, false, None);
+        let world = &headless.service().world;
+        assert!(!world.player(alice).allies.contains(&carol));
+
+        // But can accept one.
+        alliance(&mut headless, carol, alice, false, None);
+        alliance(&mut headless, alice, carol, false, None);
+        assert!(headless.service().world.have_alliance(alice, carol));
+    }
+
+    #[test]
+    fn transfer_tower() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob, carol] =
+            ["alice", "bob", "carol"].map(|alias| spawn(&mut headless, alias));
+        ally(&mut headless, alice, bob);
+        // Let the soldiers sent out when spawning arrive.
+        tick(&mut headless, 100);
+
+        let ruler = ruler_tower(&headless, alice);
+        let tower_id = ruler
+            .neighbors()
+            .find(|&tower_id| owner(&headless, tower_id) == Some(alice))
+            .unwrap();
+        let transfer = |headless: &mut Headless<TowerService>, tower_id, to| {
+            headless.command(alice, Command::TransferTower { tower_id, to });
+            tick(headless, 1);
+        };
+
+        // Only allies can be given towers, and never the ruler's.
+        transfer(&mut headless, tower_id, carol);
+        assert_eq!(owner(&headless, tower_id), Some(alice));
+        transfer(&mut headless, tower_id, alice);
+        assert_eq!(owner(&headless, tower_id), Some(alice));
+        transfer(&mut headless, ruler, bob);
+        assert_eq!(owner(&headless, ruler), Some(alice));
+
+        transfer(&mut headless, tower_id, bob);
+        assert_eq!(owner(&headless, tower_id), Some(bob));
+        let owns = |player_id: PlayerId| {
+            let player = headless.players().borrow_player(player_id).unwrap();
+            player.towers.contains(&tower_id)
+        };
+        assert!(!owns(alice));
+        assert!(owns(bob));
+    }
+
+    #[test]
+    fn teleport_ruler() {
+        let mut headless = arena(&Options::default());
+        let [alice, bob] = ["alice", "bob"].map(|alias| spawn(&mut headless, alias));
+        // Let the soldiers sent out when spawning arrive.
+        tick(&mut headless, 100);
+
+        let ruler = ruler_tower(&headless, alice);
+        let teleport = |headless: &mut Headless<TowerService>, target| {
+            headless.command(
+                alice,
+                Command::UseRulerAbility {
+                    ability: RulerAbility::Teleport,
+                    target: Some(target),
+                },
+            );
+        };
+
+        // Only to the player's own towers.
+        let bobs_ruler = ruler_tower(&headless, bob);
+        teleport(&mut headless, bobs_ruler);
+        assert_eq!(ruler_tower(&headless, alice), ruler);
+
+        // Arrives without ticking, so nothing can intercept it.
+        let destination = ruler
+            .neighbors()
+            .find(|&tower_id| owner(&headless, tower_id) == Some(alice))
+            .unwrap();
+        teleport(&mut headless, destination);
+        assert_eq!(ruler_tower(&headless, alice), destination);
+        let chunk = &headless.service().world.chunk;
+        assert!(!chunk.get(ruler).unwrap().units.has_ruler());
+
+        // On cooldown.
+        teleport(&mut headless, ruler);
+        assert_eq!(ruler_tower(&headless, alice), destination);
+    }
+}
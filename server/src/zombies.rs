@@ -0,0 +1,212 @@
+// SPDX-FileCopyrightText: 2023 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::options::Options;
+use crate::service::TowerService;
+use common::chunk::ChunkInput;
+use common::force::Path;
+use common::tower::TowerId;
+use common::units::Units;
+use game_server::player::PlayerRepo;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Schedule of zombie waves, if the server has them.
+#[derive(Debug)]
+pub struct Zombies {
+    /// Time between waves.
+    seconds: u16,
+    /// Only players with at least this many towers are attacked.
+    min_towers: u16,
+    /// Waves are skipped while at least this many real players are alive (zero means never).
+    max_players: u16,
+    seconds_remaining: u16,
+    /// Number of waves so far, which makes each wave stronger.
+    wave: u16,
+}
+
+impl Zombies {
+    /// Damage of each zombie force in the first wave.
+    const BASE_DAMAGE: u32 = 8;
+    /// Extra damage of each zombie force per wave.
+    const WAVE_DAMAGE: u32 = 4;
+    /// Waves stop getting stronger after this many.
+    const MAX_ESCALATION: u16 = 20;
+    /// Towers per zombie force, so larger empires are attacked in more places.
+    const TOWERS_PER_FORCE: usize = 20;
+    const MAX_FORCES: usize = 12;
+
+    /// Returns [`None`] if zombie waves are disabled.
+    pub fn new(options: &Options) -> Option<Self> {
+        let seconds = options
+            .zombie_wave_minutes
+            .filter(|&m| m > 0)?
+            .saturating_mul(60);
+        Some(Self {
+            seconds,
+            min_towers: options.zombie_min_towers,
+            max_players: options.zombie_max_players,
+            seconds_remaining: seconds,
+            wave: 0,
+        })
+    }
+
+    /// Starts escalating from the first wave again.
+    pub fn reset(&mut self) {
+        self.seconds_remaining = self.seconds;
+        self.wave = 0;
+    }
+}
+
+impl TowerService {
+    /// Counts down to the next zombie wave, and launches it. Must be called once per second, when
+    /// inputs can be dispatched.
+    pub(crate) fn update_zombies(&mut self, players: &PlayerRepo<Self>) {
+        let Some(zombies) = &mut self.zombies else {
+            return;
+        };
+        zombies.seconds_remaining = zombies.seconds_remaining.saturating_sub(1);
+        if zombies.seconds_remaining > 0 {
+            return;
+        }
+        zombies.seconds_remaining = zombies.seconds;
+
+        let humans = players
+            .iter_borrow()
+            .filter(|player| !player.is_bot() && player.data.alive)
+            .count();
+        if zombies.max_players > 0 && humans >= zombies.max_players as usize {
+            return;
+        }
+        zombies.wave = zombies.wave.saturating_add(1);
+        let damage = Zombies::BASE_DAMAGE
+            + Zombies::WAVE_DAMAGE * zombies.wave.min(Zombies::MAX_ESCALATION) as u32;
+
+        // Players are iterated in a random order, so sort them to draw from the RNG reproducibly.
+        let mut player_ids: Vec<_> = players.iter_player_ids().collect();
+        player_ids.sort_unstable();
+        let mut launches = Vec::new();
+        for player in player_ids
+            .into_iter()
+            .filter_map(|player_id| players.borrow_player(player_id))
+        {
+            let towers = &player.data.towers;
+            if !player.data.alive || towers.len() < zombies.min_towers as usize {
+                continue;
+            }
+
+            // Zombies rise from unowned towers along the border.
+            let border: Vec<(TowerId, TowerId)> = towers
+                .iter()
+                .filter_map(|&tower_id| {
+                    tower_id
+                        .neighbors()
+                        .find(|&neighbor_id| {
+                            self.world
+                                .chunk
+                                .get(neighbor_id)
+                                .map_or(false, |tower| tower.player_id.is_none())
+                        })
+                        .map(|source| (source, tower_id))
+                })
+                .collect();
+            let count = (towers.len() / Zombies::TOWERS_PER_FORCE).clamp(1, Zombies::MAX_FORCES);
+            for &(source, target) in border.choose_multiple(&mut self.rng, count) {
+                let units = Units::random_units(damage, false, self.rng.gen());
+                if !units.is_empty() {
+                    launches.push((source, target, units));
+                }
+            }
+        }
+
+        for (source, target, units) in launches {
+            let (chunk_id, tower_id) = source.split();
+            self.world.dispatch_chunk_input(
+                chunk_id,
+                ChunkInput::LaunchZombies {
+                    tower_id,
+                    path: Path::new(vec![source, target]),
+                    units,
+                },
+                Self::on_info_event(players, |player_id| {
+                    debug_assert!(
+                        false,
+                        "launching zombies should not have killed player {:?}",
+                        player_id
+                    );
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+    use crate::service::TowerService;
+    use crate::testing::{arena, spawn, tick};
+    use common::ticks::Ticks;
+    use core_protocol::id::PlayerId;
+    use game_server::headless::Headless;
+
+    fn wave(headless: &Headless<TowerService>) -> u16 {
+        headless.service().zombies.as_ref().unwrap().wave
+    }
+
+    /// Whether zombies are attacking any of the player's towers.
+    fn attacked(headless: &Headless<TowerService>, player_id: PlayerId) -> bool {
+        let player = headless.players().borrow_player(player_id).unwrap();
+        player.towers.iter().any(|&tower_id| {
+            let tower = headless.service().world.chunk.get(tower_id).unwrap();
+            tower.inbound_forces.iter().any(|f| f.player_id.is_none())
+        })
+    }
+
+    #[test]
+    fn disabled() {
+        let headless = arena(&Options::default());
+        assert!(headless.service().zombies.is_none());
+    }
+
+    #[test]
+    fn waves() {
+        let mut headless = arena(&Options {
+            zombie_wave_minutes: Some(1),
+            zombie_min_towers: 1,
+            ..Default::default()
+        });
+        let alice = spawn(&mut headless, "alice");
+
+        for _ in 0..Ticks::from_whole_secs(61).0 {
+            tick(&mut headless, 1);
+            if wave(&headless) > 0 {
+                break;
+            }
+        }
+        assert_eq!(wave(&headless), 1);
+        // Forces arrive at their destination's chunk on the next tick.
+        tick(&mut headless, 2);
+        assert!(attacked(&headless, alice));
+
+        // Each wave restarts the countdown.
+        tick(&mut headless, Ticks::from_whole_secs(30).0 as usize);
+        assert_eq!(wave(&headless), 1);
+        tick(&mut headless, Ticks::from_whole_secs(31).0 as usize);
+        assert_eq!(wave(&headless), 2);
+    }
+
+    #[test]
+    fn skipped_while_busy() {
+        let mut headless = arena(&Options {
+            zombie_wave_minutes: Some(1),
+            zombie_min_towers: 1,
+            zombie_max_players: 1,
+            ..Default::default()
+        });
+        let alice = spawn(&mut headless, "alice");
+
+        tick(&mut headless, Ticks::from_whole_secs(61).0 as usize);
+        assert_eq!(wave(&headless), 0);
+        assert!(!attacked(&headless, alice));
+    }
+}